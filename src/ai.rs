@@ -0,0 +1,168 @@
+use crate::bot_protocol::{self, ProcessAi};
+use crate::game_state::GameState;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Improve(usize, usize),
+    Capture(usize, usize),
+    Pass,
+}
+
+pub trait Ai {
+    fn name(&self) -> &str;
+    fn choose_action(&mut self, game_state: &GameState, player: usize) -> Action;
+}
+
+pub const AI_NAMES: [&str; 2] = ["random", "greedy"];
+
+// Bots answer in their own time, so they cannot play network matches or be replayed.
+pub fn is_bot_name(name: &str) -> bool {
+    return name.starts_with(bot_protocol::BOT_PREFIX);
+}
+
+// Checks a name for `get_ai` without starting a bot.
+pub fn is_ai_name(name: &str) -> bool {
+    if let Some(command_line) = name.strip_prefix(bot_protocol::BOT_PREFIX) {
+        return !command_line.trim().is_empty();
+    }
+    return AI_NAMES.contains(&name.to_lowercase().as_str());
+}
+
+// `bot:<command>` starts an external bot, see docs/bot_protocol.md.
+pub fn get_ai(name: &str, seed: u64) -> Result<Box<dyn Ai>, String> {
+    if let Some(command_line) = name.strip_prefix(bot_protocol::BOT_PREFIX) {
+        match ProcessAi::new(command_line) {
+            Ok(process_ai) => return Ok(Box::new(process_ai)),
+            Err(error) => return Err("bot failed to start: ".to_string() + &error),
+        }
+    }
+    match name.to_lowercase().as_str() {
+        "random" => Ok(Box::new(RandomAi::new(seed))),
+        "greedy" => Ok(Box::new(GreedyAi {})),
+        _ => Err(format!(
+            "unknown AI \"{}\", available: {}",
+            name,
+            AI_NAMES.join(", ")
+        )),
+    }
+}
+
+// The AI of the `opponent` setting for one seat, seeded per seat so every peer and a replay get
+// the same decisions.
+pub fn get_opponent_ai(game_state: &GameState, player: usize) -> Result<Box<dyn Ai>, String> {
+    let seed: u64 = game_state.seed.wrapping_add(player as u64);
+    return get_ai(&game_state.game_settings.opponent, seed);
+}
+
+// One opponent AI for every seat not played by a human.
+pub fn get_opponent_ais(game_state: &GameState) -> Result<Vec<Option<Box<dyn Ai>>>, String> {
+    return (0..game_state.players_cash.len())
+        .map(|player| -> Result<Option<Box<dyn Ai>>, String> {
+            if game_state.players_human[player] {
+                Ok(None)
+            } else {
+                Ok(Some(get_opponent_ai(game_state, player)?))
+            }
+        })
+        .collect();
+}
+
+pub fn get_capture_targets(game_state: &GameState, player: usize) -> Vec<(usize, usize)> {
+    let mut capture_targets: Vec<(usize, usize)> = Vec::new();
+    for i in 0..game_state.game_area_width {
+        for j in 0..game_state.game_area_height {
+            if game_state.can_attack_tile(player, i, j) {
+                capture_targets.push((i, j));
+            }
+        }
+    }
+    return capture_targets;
+}
+
+// Picks a random tile and either improves it or tries to take it, the original opponent.
+pub struct RandomAi {
+    rng: StdRng,
+}
+
+impl RandomAi {
+    pub fn new(seed: u64) -> RandomAi {
+        return RandomAi {
+            rng: StdRng::seed_from_u64(seed),
+        };
+    }
+}
+
+impl Ai for RandomAi {
+    fn name(&self) -> &str {
+        return "random";
+    }
+
+    fn choose_action(&mut self, game_state: &GameState, player: usize) -> Action {
+        let mut random_row: usize = self.rng.gen_range(0, game_state.game_area_height);
+        let mut random_column: usize = self.rng.gen_range(0, game_state.game_area_width);
+        if game_state.tile_owned_by[random_column][random_row] == player as i32
+            && self
+                .rng
+                .gen_bool(game_state.players_faction[player].get_ai_capture_preference())
+        {
+            let capture_targets: Vec<(usize, usize)> = get_capture_targets(game_state, player);
+            if !capture_targets.is_empty() {
+                let (target_column, target_row) =
+                    capture_targets[self.rng.gen_range(0, capture_targets.len())];
+                random_column = target_column;
+                random_row = target_row;
+            }
+        }
+
+        if game_state.tile_owned_by[random_column][random_row] == player as i32 {
+            return Action::Improve(random_column, random_row);
+        } else if game_state.can_attack_tile(player, random_column, random_row) {
+            return Action::Capture(random_column, random_row);
+        } else {
+            return Action::Pass;
+        }
+    }
+}
+
+// Takes the cheapest tile it can afford, otherwise grows its cheapest settlement.
+pub struct GreedyAi {}
+
+impl Ai for GreedyAi {
+    fn name(&self) -> &str {
+        return "greedy";
+    }
+
+    fn choose_action(&mut self, game_state: &GameState, player: usize) -> Action {
+        let cash: f64 = game_state.get_cash(player);
+        let cheapest_capture: Option<Action> = get_capture_targets(game_state, player)
+            .into_iter()
+            .map(|(tile_x, tile_y)| Action::Capture(tile_x, tile_y))
+            .filter(|&action| game_state.get_action_cost(player, action) <= cash)
+            .min_by(|&a, &b| {
+                game_state
+                    .get_action_cost(player, a)
+                    .partial_cmp(&game_state.get_action_cost(player, b))
+                    .unwrap()
+            });
+        if let Some(action) = cheapest_capture {
+            return action;
+        }
+
+        let mut cheapest_improvement: Action = Action::Pass;
+        let mut cheapest_cost: f64 = cash;
+        for i in 0..game_state.game_area_width {
+            for j in 0..game_state.game_area_height {
+                if game_state.tile_owned_by[i][j] != player as i32 {
+                    continue;
+                }
+                let cost: f64 = game_state.get_action_cost(player, Action::Improve(i, j));
+                if cost <= cheapest_cost {
+                    cheapest_cost = cost;
+                    cheapest_improvement = Action::Improve(i, j);
+                }
+            }
+        }
+        return cheapest_improvement;
+    }
+}
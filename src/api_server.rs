@@ -0,0 +1,431 @@
+use crate::ai::Action;
+use crate::game_state::GameState;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+pub const API_HOST: &str = "127.0.0.1";
+// WebSocket clients get the state 10 times per second instead of every tick.
+pub const STATE_PUSH_INTERVAL_TICKS: u64 = 6;
+const MAX_REQUEST_HEADER_SIZE: usize = 8192;
+const MAX_REQUEST_BODY_SIZE: usize = 65536;
+const SOCKET_POLL_INTERVAL_MS: u64 = 20;
+
+#[derive(Serialize)]
+pub struct ApiState {
+    pub tick: u64,
+    pub game_area_width: usize,
+    pub game_area_height: usize,
+    pub tile_owned_by: Vec<Vec<i32>>,
+    pub tile_population_number: Vec<Vec<i32>>,
+    pub tile_improvement_level: Vec<Vec<i32>>,
+    pub players_cash: Vec<f64>,
+    pub players_eliminated: Vec<bool>,
+    pub is_win: bool,
+    pub winner_team: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ApiAction {
+    pub player: usize,
+    pub action: String,
+    pub x: Option<usize>,
+    pub y: Option<usize>,
+}
+
+struct SharedState {
+    tick: u64,
+    number_of_players: usize,
+    // In a network match only the local player's seat is played from this machine.
+    controlled_player: Option<usize>,
+    state_json: String,
+    actions: Vec<(usize, Action)>,
+}
+
+// Serves the match on localhost: `GET /state` returns the state as JSON, `POST /action` queues
+// an action for any player, or only the local one in a network match, and `/ws` upgrades to a
+// WebSocket that pushes the state and accepts the same action messages. Queued actions are
+// applied by the game loop. Every request needs the session's `token`, and requests from web
+// pages on other sites are refused, so a page open in the browser cannot read the match or
+// play it.
+pub struct ApiServer {
+    shared: Arc<Mutex<SharedState>>,
+    pub address: String,
+    pub token: String,
+}
+
+impl ApiServer {
+    pub fn start(port: u16) -> io::Result<ApiServer> {
+        let listener: TcpListener = TcpListener::bind((API_HOST, port))?;
+        let address: String = listener.local_addr()?.to_string();
+        let token: String = format!("{:016x}", rand::random::<u64>());
+        let shared: Arc<Mutex<SharedState>> = Arc::new(Mutex::new(SharedState {
+            tick: 0,
+            number_of_players: 0,
+            controlled_player: None,
+            state_json: "{}".to_string(),
+            actions: Vec::new(),
+        }));
+
+        let listener_shared: Arc<Mutex<SharedState>> = shared.clone();
+        let listener_address: String = address.clone();
+        let listener_token: String = token.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let connection_shared: Arc<Mutex<SharedState>> = listener_shared.clone();
+                let address: String = listener_address.clone();
+                let token: String = listener_token.clone();
+                thread::spawn(move || {
+                    let _ = handle_connection(stream, connection_shared, &address, &token);
+                });
+            }
+        });
+
+        return Ok(ApiServer {
+            shared,
+            address,
+            token,
+        });
+    }
+
+    pub fn publish(&self, game_state: &GameState) {
+        let state_json: String =
+            serde_json::to_string(&get_api_state(game_state)).unwrap_or_default();
+        let mut shared = self.shared.lock().unwrap();
+        shared.tick = game_state.tick;
+        shared.number_of_players = game_state.players_cash.len();
+        shared.state_json = state_json;
+    }
+
+    // `Some(player)` refuses actions for every other seat, `None` accepts all of them.
+    pub fn set_controlled_player(&self, controlled_player: Option<usize>) {
+        self.shared.lock().unwrap().controlled_player = controlled_player;
+    }
+
+    pub fn take_actions(&self) -> Vec<(usize, Action)> {
+        let mut shared = self.shared.lock().unwrap();
+        return shared.actions.drain(..).collect();
+    }
+}
+
+pub fn get_api_state(game_state: &GameState) -> ApiState {
+    return ApiState {
+        tick: game_state.tick,
+        game_area_width: game_state.game_area_width,
+        game_area_height: game_state.game_area_height,
+        tile_owned_by: game_state.tile_owned_by.clone(),
+        tile_population_number: game_state.tile_population_number.clone(),
+        tile_improvement_level: game_state.tile_improvement_level.clone(),
+        players_cash: (0..game_state.players_cash.len())
+            .map(|player| game_state.get_cash(player))
+            .collect(),
+        players_eliminated: game_state.players_eliminated.clone(),
+        is_win: game_state.is_win,
+        winner_team: if game_state.is_win {
+            Some(game_state.winner_team)
+        } else {
+            None
+        },
+    };
+}
+
+pub fn parse_action(api_action: &ApiAction) -> Result<Action, String> {
+    let tile: Option<(usize, usize)> = match (api_action.x, api_action.y) {
+        (Some(tile_x), Some(tile_y)) => Some((tile_x, tile_y)),
+        _ => None,
+    };
+    match (api_action.action.as_str(), tile) {
+        ("improve", Some((tile_x, tile_y))) => Ok(Action::Improve(tile_x, tile_y)),
+        ("capture", Some((tile_x, tile_y))) => Ok(Action::Capture(tile_x, tile_y)),
+        ("pass", _) => Ok(Action::Pass),
+        ("improve", None) | ("capture", None) => Err("x and y are required".to_string()),
+        (action, _) => Err("unknown action ".to_string() + action),
+    }
+}
+
+// Returns the JSON reply to an action message, or the HTTP status and reason it was refused.
+fn queue_action(
+    shared: &Arc<Mutex<SharedState>>,
+    body: &str,
+) -> Result<String, (&'static str, String)> {
+    let api_action: ApiAction = serde_json::from_str(body).map_err(|error| {
+        (
+            "400 Bad Request",
+            "invalid action: ".to_string() + &error.to_string(),
+        )
+    })?;
+    let action: Action = parse_action(&api_action).map_err(|error| ("400 Bad Request", error))?;
+    let mut shared = shared.lock().unwrap();
+    if api_action.player >= shared.number_of_players {
+        return Err((
+            "400 Bad Request",
+            "unknown player ".to_string() + &api_action.player.to_string(),
+        ));
+    }
+    if let Some(controlled_player) = shared.controlled_player {
+        if api_action.player != controlled_player {
+            return Err((
+                "403 Forbidden",
+                "player ".to_string()
+                    + &api_action.player.to_string()
+                    + " is played on another machine",
+            ));
+        }
+    }
+    shared.actions.push((api_action.player, action));
+    return Ok(format!("{{\"queued\":true,\"tick\":{}}}", shared.tick));
+}
+
+fn get_error_json(error: &str) -> String {
+    return serde_json::json!({ "error": error }).to_string();
+}
+
+// Pages served from this machine may use the API, pages from anywhere else may not.
+fn is_local_origin(origin: &str) -> bool {
+    let host: &str = origin
+        .trim_start_matches("http://")
+        .trim_start_matches("https://");
+    let host_name: &str = host.split(':').next().unwrap_or("");
+    return host_name == "localhost" || host_name == API_HOST;
+}
+
+// A `Host` other than this server's address means the name was rebound to it by a web page.
+fn is_local_host(host: &str, address: &str) -> bool {
+    let port: &str = address.rsplit(':').next().unwrap_or("");
+    return host == address || host == "localhost:".to_string() + port;
+}
+
+// The token comes as `Authorization: Bearer <token>`, or as `?token=<token>` for WebSockets,
+// which browsers open without custom headers.
+fn has_token(authorization: Option<String>, query: &str, token: &str) -> bool {
+    let is_header_token: bool =
+        authorization.is_some_and(|value| value == "Bearer ".to_string() + token);
+    let is_query_token: bool = query
+        .split('&')
+        .any(|parameter| parameter == "token=".to_string() + token);
+    return is_header_token || is_query_token;
+}
+
+fn write_response(mut stream: TcpStream, status: &str, response_body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response_body.len(),
+        response_body
+    )?;
+    return stream.flush();
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    shared: Arc<Mutex<SharedState>>,
+    address: &str,
+    token: &str,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader: BufReader<TcpStream> = BufReader::new(stream.try_clone()?);
+    // The request line and the headers together are read up to `MAX_REQUEST_HEADER_SIZE`.
+    let mut header_reader: io::Take<&mut BufReader<TcpStream>> =
+        (&mut reader).take(MAX_REQUEST_HEADER_SIZE as u64);
+    let mut request_line: String = String::new();
+    header_reader.read_line(&mut request_line)?;
+    let mut request_parts = request_line.split_whitespace();
+    let method: String = request_parts.next().unwrap_or("").to_string();
+    let target: String = request_parts.next().unwrap_or("").to_string();
+    let (path, query): (&str, &str) = target.split_once('?').unwrap_or((&target, ""));
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut is_header_complete: bool = false;
+    loop {
+        let mut header_line: String = String::new();
+        if header_reader.read_line(&mut header_line)? == 0 {
+            break;
+        }
+        if header_line.trim().is_empty() {
+            is_header_complete = true;
+            break;
+        }
+        if let Some((name, value)) = header_line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    if !is_header_complete {
+        return write_response(
+            stream,
+            "431 Request Header Fields Too Large",
+            &get_error_json("request header too large"),
+        );
+    }
+    let get_header = |name: &str| -> Option<String> {
+        headers
+            .iter()
+            .find(|(header_name, _)| header_name == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    if !get_header("host").is_some_and(|host| is_local_host(&host, address)) {
+        return write_response(stream, "403 Forbidden", &get_error_json("unknown host"));
+    }
+    if !get_header("origin").is_none_or(|origin| is_local_origin(&origin)) {
+        return write_response(
+            stream,
+            "403 Forbidden",
+            &get_error_json("origin not allowed"),
+        );
+    }
+    if !has_token(get_header("authorization"), query, token) {
+        return write_response(
+            stream,
+            "401 Unauthorized",
+            &get_error_json("missing or wrong token"),
+        );
+    }
+
+    if path == "/ws"
+        && get_header("upgrade").map(|value| value.to_lowercase()) == Some("websocket".to_string())
+    {
+        let key: String = match get_header("sec-websocket-key") {
+            Some(key) => key,
+            None => {
+                return write_response(
+                    stream,
+                    "400 Bad Request",
+                    &get_error_json("missing Sec-WebSocket-Key"),
+                )
+            }
+        };
+        let mut stream: TcpStream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            tungstenite::handshake::derive_accept_key(key.as_bytes())
+        )?;
+        return handle_websocket(
+            WebSocket::from_raw_socket(stream, tungstenite::protocol::Role::Server, None),
+            shared,
+        );
+    }
+
+    let content_length: usize = get_header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_REQUEST_BODY_SIZE);
+    let mut body: Vec<u8> = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    // A page can only send a JSON body after a CORS preflight, which this server never allows.
+    let is_json: bool = get_header("content-type")
+        .is_some_and(|value| value.to_lowercase().starts_with("application/json"));
+    let (status, response_body): (&str, String) = match (method.as_str(), path) {
+        ("GET", "/state") => ("200 OK", shared.lock().unwrap().state_json.clone()),
+        ("POST", "/action") if !is_json => (
+            "415 Unsupported Media Type",
+            get_error_json("Content-Type must be application/json"),
+        ),
+        ("POST", "/action") => match queue_action(&shared, &String::from_utf8_lossy(&body)) {
+            Ok(reply) => ("202 Accepted", reply),
+            Err((status, error)) => (status, get_error_json(&error)),
+        },
+        _ => ("404 Not Found", get_error_json("not found")),
+    };
+    return write_response(stream, status, &response_body);
+}
+
+fn handle_websocket(
+    mut websocket: WebSocket<TcpStream>,
+    shared: Arc<Mutex<SharedState>>,
+) -> io::Result<()> {
+    websocket
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(SOCKET_POLL_INTERVAL_MS)))?;
+    let mut last_sent_tick: Option<u64> = None;
+    loop {
+        match websocket.read() {
+            Ok(Message::Text(text)) => {
+                let reply: String = match queue_action(&shared, &text) {
+                    Ok(reply) => reply,
+                    Err((_, error)) => get_error_json(&error),
+                };
+                if websocket.send(Message::Text(reply)).is_err() {
+                    return Ok(());
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(ref error))
+                if error.kind() == io::ErrorKind::WouldBlock
+                    || error.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => return Ok(()),
+        }
+
+        let (tick, state_json): (u64, String) = {
+            let shared = shared.lock().unwrap();
+            (shared.tick, shared.state_json.clone())
+        };
+        let is_due: bool = match last_sent_tick {
+            Some(last_sent_tick) => {
+                tick >= last_sent_tick + STATE_PUSH_INTERVAL_TICKS || tick < last_sent_tick
+            }
+            None => true,
+        };
+        if is_due {
+            if websocket.send(Message::Text(state_json)).is_err() {
+                return Ok(());
+            }
+            last_sent_tick = Some(tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_session_token_is_accepted() {
+        let token: &str = "00000000deadbeef";
+        assert!(has_token(
+            Some("Bearer 00000000deadbeef".to_string()),
+            "",
+            token
+        ));
+        assert!(has_token(None, "token=00000000deadbeef", token));
+        assert!(has_token(None, "player=1&token=00000000deadbeef", token));
+        assert!(!has_token(None, "", token));
+        assert!(!has_token(
+            Some("Bearer 00000000deadbeee".to_string()),
+            "",
+            token
+        ));
+        assert!(!has_token(Some("00000000deadbeef".to_string()), "", token));
+        assert!(!has_token(None, "token=00000000deadbeef0", token));
+        assert!(!has_token(None, "xtoken=00000000deadbeef", token));
+    }
+
+    #[test]
+    fn only_this_server_is_accepted_as_host() {
+        let address: &str = "127.0.0.1:7000";
+        assert!(is_local_host("127.0.0.1:7000", address));
+        assert!(is_local_host("localhost:7000", address));
+        assert!(!is_local_host("localhost:7001", address));
+        assert!(!is_local_host("127.0.0.1", address));
+        assert!(!is_local_host("attacker.example:7000", address));
+        assert!(!is_local_host("", address));
+    }
+
+    #[test]
+    fn only_local_pages_are_accepted_as_origin() {
+        assert!(is_local_origin("http://localhost:8080"));
+        assert!(is_local_origin("https://localhost"));
+        assert!(is_local_origin("http://127.0.0.1:3000"));
+        assert!(!is_local_origin("http://localhost.attacker.example"));
+        assert!(!is_local_origin("http://attacker.example"));
+        assert!(!is_local_origin("https://127.0.0.1.attacker.example"));
+        assert!(!is_local_origin("null"));
+    }
+}
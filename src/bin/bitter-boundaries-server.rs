@@ -0,0 +1,336 @@
+extern crate bitter_boundaries;
+
+use bitter_boundaries::ai::{self, Ai};
+use bitter_boundaries::game_settings::{self, GameSettings};
+use bitter_boundaries::game_state::GameState;
+use bitter_boundaries::lockstep::{self, LockstepMessage, LockstepSession};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_PORT: u16 = 7878;
+const DEFAULT_ROOM_SIZE: usize = 2;
+const DEFAULT_ROOM_NAME: &str = "default";
+const TICKS_PER_SECOND: u128 = 60;
+
+struct ServerSettings {
+    port: u16,
+    room_size: usize,
+    max_ticks: Option<u64>,
+    results_path: Option<String>,
+}
+
+struct JoinRequest {
+    stream: TcpStream,
+    name: String,
+    version: u32,
+    room: String,
+    rejoin_token: Option<u64>,
+}
+
+// Every room is a separate lobby and match, its session is the host of that match.
+struct Room {
+    name: String,
+    session: LockstepSession,
+    game_state: Option<GameState>,
+    ais: Vec<Option<Box<dyn Ai>>>,
+    started_at: Instant,
+}
+
+#[derive(Serialize)]
+struct ResultRecord {
+    finished_at: u64,
+    room: String,
+    seed: u64,
+    players: Vec<String>,
+    result: String,
+    winner_team: Option<usize>,
+    winners: Vec<String>,
+    ticks: u64,
+}
+
+fn parse_server_arguments(arguments: &Vec<String>) -> ServerSettings {
+    let mut server_settings: ServerSettings = ServerSettings {
+        port: DEFAULT_PORT,
+        room_size: DEFAULT_ROOM_SIZE,
+        max_ticks: None,
+        results_path: None,
+    };
+
+    let mut i: usize = 1;
+    while i < arguments.len() {
+        match arguments[i].as_str() {
+            "--port" if i + 1 < arguments.len() => {
+                if let Ok(port) = arguments[i + 1].parse::<u16>() {
+                    server_settings.port = port;
+                }
+                i += 1;
+            }
+            "--room-size" if i + 1 < arguments.len() => {
+                if let Ok(room_size) = arguments[i + 1].parse::<usize>() {
+                    server_settings.room_size =
+                        room_size.clamp(1, game_settings::MAX_NUMBER_OF_PLAYERS);
+                }
+                i += 1;
+            }
+            "--max-ticks" if i + 1 < arguments.len() => {
+                server_settings.max_ticks = arguments[i + 1].parse::<u64>().ok();
+                i += 1;
+            }
+            "--results" if i + 1 < arguments.len() => {
+                server_settings.results_path = Some(arguments[i + 1].clone());
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    return server_settings;
+}
+
+// Reads the `Hello` of every new connection on its own thread, so a slow client cannot hold up
+// the others, and hands the connection to the main loop.
+fn spawn_listener(listener: TcpListener, sender: Sender<JoinRequest>) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream: TcpStream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let sender: Sender<JoinRequest> = sender.clone();
+            thread::spawn(move || {
+                if stream
+                    .set_read_timeout(Some(lockstep::HANDSHAKE_TIMEOUT))
+                    .is_err()
+                {
+                    return;
+                }
+                if let Ok(LockstepMessage::Hello {
+                    name,
+                    version,
+                    room,
+                    rejoin_token,
+                }) = lockstep::read_message(&mut stream)
+                {
+                    if stream.set_read_timeout(None).is_ok() {
+                        let _ = sender.send(JoinRequest {
+                            stream,
+                            name,
+                            version,
+                            room,
+                            rejoin_token,
+                        });
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn log_result(room: &Room, game_state: &GameState, result: &str, results_path: &Option<String>) {
+    let players: Vec<String> = room.session.get_player_names();
+    let winners: Vec<String> = if game_state.is_win {
+        players
+            .iter()
+            .enumerate()
+            .filter(|&(player, _)| game_state.get_team(player) == game_state.winner_team)
+            .map(|(_, name)| name.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    println!(
+        "Room {}: {} after {} ticks, winners: {}",
+        room.name,
+        result,
+        game_state.tick,
+        if winners.is_empty() {
+            "none".to_string()
+        } else {
+            winners.join(", ")
+        }
+    );
+
+    let results_path: &String = match results_path {
+        Some(results_path) => results_path,
+        None => return,
+    };
+    let result_record: ResultRecord = ResultRecord {
+        finished_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        room: room.name.clone(),
+        seed: game_state.seed,
+        players,
+        result: result.to_string(),
+        winner_team: if game_state.is_win {
+            Some(game_state.winner_team)
+        } else {
+            None
+        },
+        winners,
+        ticks: game_state.tick,
+    };
+    let written: io::Result<()> = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(results_path)
+        .and_then(|mut file| {
+            let line: String = serde_json::to_string(&result_record)? + "\n";
+            file.write_all(line.as_bytes())
+        });
+    if let Err(error) = written {
+        eprintln!("Could not write {}: {}", results_path, error);
+    }
+}
+
+fn update_room(room: &mut Room, game_settings: &GameSettings, server_settings: &ServerSettings) {
+    let mut game_state: GameState = match room.game_state.take() {
+        Some(game_state) => game_state,
+        None => {
+            room.session.poll();
+            if room.session.get_number_of_connected_players() < server_settings.room_size
+                || !room.session.get_unready_players().is_empty()
+            {
+                return;
+            }
+            match room.session.start(game_settings) {
+                Ok(match_settings) => {
+                    let game_state: GameState = GameState::new(match_settings);
+                    room.ais = match ai::get_opponent_ais(&game_state) {
+                        Ok(ais) => ais,
+                        Err(error) => {
+                            eprintln!("Room {}: {}", room.name, error);
+                            room.session.end_match();
+                            return;
+                        }
+                    };
+                    room.started_at = Instant::now();
+                    println!(
+                        "Room {}: match started with {} on seed {}",
+                        room.name,
+                        room.session.get_player_names().join(", "),
+                        game_state.seed
+                    );
+                    game_state
+                }
+                Err(error) => {
+                    eprintln!("Room {}: {}", room.name, error);
+                    return;
+                }
+            }
+        }
+    };
+
+    let is_over_tick_limit = |game_state: &GameState| -> bool {
+        return server_settings
+            .max_ticks
+            .is_some_and(|max_ticks| game_state.tick >= max_ticks);
+    };
+    let due_tick: u64 = (room.started_at.elapsed().as_millis() * TICKS_PER_SECOND / 1000) as u64;
+    while game_state.tick < due_tick && !game_state.is_win && !is_over_tick_limit(&game_state) {
+        if room
+            .session
+            .advance(&mut game_state, &mut room.ais)
+            .is_none()
+        {
+            break;
+        }
+    }
+
+    let result: &str = if game_state.is_win {
+        "victory"
+    } else if is_over_tick_limit(&game_state) {
+        "tick limit"
+    } else if room.session.get_number_of_connected_players() == 0 {
+        "abandoned"
+    } else {
+        room.game_state = Some(game_state);
+        return;
+    };
+    log_result(room, &game_state, result, &server_settings.results_path);
+    room.session.end_match();
+}
+
+// Hosts matches without graphics. Clients join with `--join <address> --room <name>`, a room
+// starts its match with the game options given here once `--room-size` players are in it and
+// all of them are ready.
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    let server_settings: ServerSettings = parse_server_arguments(&arguments);
+    let game_settings: GameSettings = game_settings::parse_arguments(arguments);
+    if ai::is_bot_name(&game_settings.opponent) {
+        eprintln!("Bots cannot play network matches, pick a built-in opponent");
+        process::exit(1);
+    }
+
+    let listener: TcpListener = match TcpListener::bind(("0.0.0.0", server_settings.port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!(
+                "Could not listen on port {}: {}",
+                server_settings.port, error
+            );
+            process::exit(1);
+        }
+    };
+    let (sender, join_requests): (Sender<JoinRequest>, Receiver<JoinRequest>) = mpsc::channel();
+    spawn_listener(listener, sender);
+    println!(
+        "Listening on port {}, {} players per room",
+        server_settings.port, server_settings.room_size
+    );
+
+    let mut rooms: Vec<Room> = Vec::new();
+    loop {
+        while let Ok(join_request) = join_requests.try_recv() {
+            let room_name: String = if join_request.room.is_empty() {
+                DEFAULT_ROOM_NAME.to_string()
+            } else {
+                join_request.room
+            };
+            let index: usize = match rooms.iter().position(|room| room.name == room_name) {
+                Some(index) => index,
+                None => {
+                    println!("Room {}: opened", room_name);
+                    let mut session: LockstepSession = LockstepSession::dedicated("Server");
+                    session.set_lobby_settings(&game_settings);
+                    rooms.push(Room {
+                        name: room_name.clone(),
+                        session,
+                        game_state: None,
+                        ais: Vec::new(),
+                        started_at: Instant::now(),
+                    });
+                    rooms.len() - 1
+                }
+            };
+            println!("Room {}: {} joined", room_name, join_request.name);
+            rooms[index].session.add_peer(
+                join_request.stream,
+                join_request.name,
+                join_request.version,
+                join_request.rejoin_token,
+            );
+        }
+
+        for room in rooms.iter_mut() {
+            update_room(room, &game_settings, &server_settings);
+        }
+        rooms.retain(|room| {
+            let is_open: bool =
+                room.game_state.is_some() || room.session.get_number_of_connected_players() > 0;
+            if !is_open {
+                println!("Room {}: closed", room.name);
+            }
+            return is_open;
+        });
+        thread::sleep(Duration::from_millis(1));
+    }
+}
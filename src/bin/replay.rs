@@ -0,0 +1,97 @@
+extern crate bitter_boundaries;
+
+use bitter_boundaries::ai;
+use bitter_boundaries::desync_utility;
+use bitter_boundaries::replay::{self, Replay};
+use std::process;
+
+// Plays a replay file again and checks that the match comes out the same. On the first
+// difference both states are written next to the replay.
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    let mut replay_path: Option<String> = None;
+    let mut report_path: Option<String> = None;
+    let mut i: usize = 1;
+    while i < arguments.len() {
+        match arguments[i].as_str() {
+            "--report" if i + 1 < arguments.len() => {
+                report_path = Some(arguments[i + 1].clone());
+                i += 1;
+            }
+            argument if !argument.starts_with("--") => {
+                replay_path = Some(argument.to_string());
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let replay_path: String = match replay_path {
+        Some(replay_path) => replay_path,
+        None => {
+            eprintln!("Usage: replay <replay.json> [--report <path>]");
+            process::exit(1);
+        }
+    };
+    let replay: Replay = match replay::read_replay(&replay_path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Could not read {}: {}", replay_path, error);
+            process::exit(1);
+        }
+    };
+    if replay.version != replay::REPLAY_VERSION {
+        eprintln!(
+            "Replay version {} is not supported, expected {}",
+            replay.version,
+            replay::REPLAY_VERSION
+        );
+        process::exit(1);
+    }
+    if ai::is_bot_name(&replay.game_settings.opponent) {
+        eprintln!(
+            "Matches against {} cannot be replayed, bots do not answer the same way twice",
+            replay.game_settings.opponent
+        );
+        process::exit(1);
+    }
+
+    println!(
+        "Replaying seed {} with {} commands, {} chat lines and {} checkpoints",
+        replay.game_settings.seed.unwrap_or(0),
+        replay.commands.len(),
+        replay.chat.len(),
+        replay.checkpoints.len()
+    );
+    match replay::verify_replay(&replay) {
+        Ok(ticks) => println!("Replay matches the recording through tick {}", ticks),
+        Err(replay::VerifyError::Opponent(error)) => {
+            eprintln!("Could not replay the match: {}", error);
+            process::exit(1);
+        }
+        Err(replay::VerifyError::Desync(states)) => {
+            let (recorded, replayed) = *states;
+            let report_path: String = report_path.unwrap_or_else(|| {
+                replay_path.trim_end_matches(".json").to_string() + "_desync.json"
+            });
+            println!("Desync at tick {}", recorded.tick);
+            for difference in desync_utility::get_differences(&recorded, &replayed)
+                .iter()
+                .take(10)
+            {
+                println!("  {}", difference);
+            }
+            match desync_utility::write_desync_report(
+                &report_path,
+                "recorded",
+                &recorded,
+                "replayed",
+                &replayed,
+            ) {
+                Ok(()) => println!("Both states written to {}", report_path),
+                Err(error) => eprintln!("Could not write {}: {}", report_path, error),
+            }
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+extern crate bitter_boundaries;
+
+use bitter_boundaries::ai;
+use bitter_boundaries::game_settings::{self, GameSettings};
+use bitter_boundaries::simulation_utility::{self, MatchResult};
+use bitter_boundaries::statistics_utility;
+use std::process;
+
+const DEFAULT_NUMBER_OF_MATCHES: u64 = 1000;
+
+struct SimulatorSettings {
+    number_of_matches: u64,
+    seed_start: u64,
+    max_ticks: u64,
+    ai_names: Vec<String>,
+    is_rotating: bool,
+}
+
+fn parse_simulator_arguments(arguments: &Vec<String>) -> SimulatorSettings {
+    let mut simulator_settings: SimulatorSettings = SimulatorSettings {
+        number_of_matches: DEFAULT_NUMBER_OF_MATCHES,
+        seed_start: 0,
+        max_ticks: simulation_utility::DEFAULT_MAX_TICKS,
+        ai_names: vec!["random".to_string()],
+        is_rotating: false,
+    };
+
+    let mut i: usize = 1;
+    while i < arguments.len() {
+        match arguments[i].as_str() {
+            "--matches" if i + 1 < arguments.len() => {
+                if let Ok(number_of_matches) = arguments[i + 1].parse::<u64>() {
+                    simulator_settings.number_of_matches = number_of_matches;
+                }
+                i += 1;
+            }
+            "--seed-start" if i + 1 < arguments.len() => {
+                if let Ok(seed_start) = arguments[i + 1].parse::<u64>() {
+                    simulator_settings.seed_start = seed_start;
+                }
+                i += 1;
+            }
+            "--max-ticks" if i + 1 < arguments.len() => {
+                if let Ok(max_ticks) = arguments[i + 1].parse::<u64>() {
+                    simulator_settings.max_ticks = max_ticks;
+                }
+                i += 1;
+            }
+            "--ai" if i + 1 < arguments.len() => {
+                simulator_settings.ai_names = arguments[i + 1]
+                    .split(',')
+                    .map(|ai_name| ai_name.trim().to_string())
+                    .collect();
+                i += 1;
+            }
+            "--rotate" => {
+                simulator_settings.is_rotating = true;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    return simulator_settings;
+}
+
+// Seat `player` gets the AIs from the pairing in order, repeated if there are more seats.
+// Rotating shifts the pairing by one seat every match so every AI plays from every seat.
+fn get_ai_names(
+    simulator_settings: &SimulatorSettings,
+    number_of_players: usize,
+    match_index: u64,
+) -> Vec<String> {
+    let offset: usize = if simulator_settings.is_rotating {
+        match_index as usize
+    } else {
+        0
+    };
+    return (0..number_of_players)
+        .map(|player| {
+            simulator_settings.ai_names[(player + offset) % simulator_settings.ai_names.len()]
+                .clone()
+        })
+        .collect();
+}
+
+fn print_report(game_settings: &GameSettings, match_results: &Vec<MatchResult>) {
+    let number_of_matches: u64 = match_results.len() as u64;
+    let number_of_players: usize = game_settings.number_of_players;
+    let team_mode: game_settings::TeamMode = game_settings.team_mode;
+    let number_of_teams: usize = number_of_players / team_mode.get_team_size();
+
+    let mut seat_wins: Vec<u64> = vec![0; number_of_players];
+    let mut ai_names: Vec<String> = Vec::new();
+    let mut ai_seats: Vec<u64> = Vec::new();
+    let mut ai_wins: Vec<u64> = Vec::new();
+    let mut number_of_draws: u64 = 0;
+    let mut total_ticks: u64 = 0;
+    for match_result in match_results {
+        total_ticks += match_result.ticks;
+        if match_result.winner_team.is_none() {
+            number_of_draws += 1;
+        }
+        for (player, ai_name) in match_result.ai_names.iter().enumerate() {
+            let index: usize = match ai_names.iter().position(|name| name == ai_name) {
+                Some(index) => index,
+                None => {
+                    ai_names.push(ai_name.clone());
+                    ai_seats.push(0);
+                    ai_wins.push(0);
+                    ai_names.len() - 1
+                }
+            };
+            ai_seats[index] += 1;
+            if match_result.winner_team == Some(team_mode.get_team(player)) {
+                seat_wins[player] += 1;
+                ai_wins[index] += 1;
+            }
+        }
+    }
+
+    let average_ticks: u64 = total_ticks.checked_div(number_of_matches).unwrap_or(0);
+    println!(
+        "Map {}x{}, {} players, {}, {}, {}",
+        game_settings.game_area_width,
+        game_settings.game_area_height,
+        number_of_players,
+        team_mode.name(),
+        game_settings.game_mode.name(),
+        game_settings.victory_condition.name()
+    );
+    println!(
+        "Matches: {}   Draws (tick limit): {} ({:.1}%)",
+        number_of_matches,
+        number_of_draws,
+        simulation_utility::get_percentage(number_of_draws, number_of_matches)
+    );
+    println!(
+        "Average match length: {} ticks ({})",
+        average_ticks,
+        statistics_utility::get_time_played(average_ticks)
+    );
+    println!();
+    println!("Win rate by seat:");
+    for player in 0..number_of_players {
+        println!(
+            "  Player {} (team {}): {:>6.1}%",
+            player,
+            team_mode.get_team(player),
+            simulation_utility::get_percentage(seat_wins[player], number_of_matches)
+        );
+    }
+    println!("Win rate by AI (share of seats on the winning team):");
+    for k in 0..ai_names.len() {
+        println!(
+            "  {:<10} {:>6.1}% of {} seats",
+            ai_names[k],
+            simulation_utility::get_percentage(ai_wins[k], ai_seats[k]),
+            ai_seats[k]
+        );
+    }
+    let expected_win_rate: f64 = 100.0 / number_of_teams as f64;
+    let first_player_win_rate: f64 =
+        simulation_utility::get_percentage(seat_wins[0], number_of_matches);
+    println!(
+        "First-player advantage: {:+.1} points ({:.1}% won vs {:.1}% expected)",
+        first_player_win_rate - expected_win_rate,
+        first_player_win_rate,
+        expected_win_rate
+    );
+}
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    let simulator_settings: SimulatorSettings = parse_simulator_arguments(&arguments);
+    let mut game_settings: GameSettings = game_settings::parse_arguments(arguments);
+    game_settings.export_prefix = None;
+
+    for ai_name in simulator_settings.ai_names.iter() {
+        if !ai::is_ai_name(ai_name) {
+            eprintln!(
+                "Unknown AI \"{}\", available: {}",
+                ai_name,
+                ai::AI_NAMES.join(", ")
+            );
+            process::exit(1);
+        }
+    }
+
+    let mut match_results: Vec<MatchResult> = Vec::new();
+    for match_index in 0..simulator_settings.number_of_matches {
+        game_settings.seed = Some(simulator_settings.seed_start + match_index);
+        match simulation_utility::play_match(
+            &game_settings,
+            get_ai_names(
+                &simulator_settings,
+                game_settings.number_of_players,
+                match_index,
+            ),
+            simulator_settings.max_ticks,
+        ) {
+            Ok(match_result) => match_results.push(match_result),
+            Err(error) => {
+                eprintln!("Match {} failed: {}", match_index, error);
+                process::exit(1);
+            }
+        }
+    }
+    print_report(&game_settings, &match_results);
+}
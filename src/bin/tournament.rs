@@ -0,0 +1,274 @@
+extern crate bitter_boundaries;
+extern crate rand;
+
+use bitter_boundaries::ai;
+use bitter_boundaries::elo_utility::{self, GameRecord};
+use bitter_boundaries::game_settings::{self, GameSettings, TeamMode};
+use bitter_boundaries::simulation_utility::{self, MatchResult};
+use rand::{rngs::StdRng, SeedableRng};
+use std::fs::File;
+use std::io::Write;
+use std::process;
+
+const DEFAULT_NUMBER_OF_SEEDS: u64 = 10;
+const DEFAULT_REPORT_PATH: &str = "tournament_report.md";
+
+struct TournamentSettings {
+    ai_names: Vec<String>,
+    maps: Vec<(usize, usize)>,
+    number_of_seeds: u64,
+    seed_start: u64,
+    max_ticks: u64,
+    report_path: String,
+}
+
+struct Standing {
+    ai_name: String,
+    rating: f64,
+    confidence_interval: (f64, f64),
+    wins: u64,
+    draws: u64,
+    losses: u64,
+}
+
+fn parse_tournament_arguments(arguments: &Vec<String>) -> TournamentSettings {
+    let mut tournament_settings: TournamentSettings = TournamentSettings {
+        ai_names: ai::AI_NAMES
+            .iter()
+            .map(|ai_name| ai_name.to_string())
+            .collect(),
+        maps: vec![(
+            game_settings::DEFAULT_GAME_AREA_WIDTH,
+            game_settings::DEFAULT_GAME_AREA_HEIGHT,
+        )],
+        number_of_seeds: DEFAULT_NUMBER_OF_SEEDS,
+        seed_start: 0,
+        max_ticks: simulation_utility::DEFAULT_MAX_TICKS,
+        report_path: DEFAULT_REPORT_PATH.to_string(),
+    };
+
+    let mut i: usize = 1;
+    while i < arguments.len() {
+        match arguments[i].as_str() {
+            "--ai" if i + 1 < arguments.len() => {
+                tournament_settings.ai_names = arguments[i + 1]
+                    .split(',')
+                    .map(|ai_name| ai_name.trim().to_string())
+                    .collect();
+                i += 1;
+            }
+            "--maps" if i + 1 < arguments.len() => {
+                tournament_settings.maps = arguments[i + 1]
+                    .split(',')
+                    .filter_map(|map_size| game_settings::parse_map_size(map_size.trim()))
+                    .collect();
+                i += 1;
+            }
+            "--seeds" if i + 1 < arguments.len() => {
+                if let Ok(number_of_seeds) = arguments[i + 1].parse::<u64>() {
+                    tournament_settings.number_of_seeds = number_of_seeds;
+                }
+                i += 1;
+            }
+            "--seed-start" if i + 1 < arguments.len() => {
+                if let Ok(seed_start) = arguments[i + 1].parse::<u64>() {
+                    tournament_settings.seed_start = seed_start;
+                }
+                i += 1;
+            }
+            "--max-ticks" if i + 1 < arguments.len() => {
+                if let Ok(max_ticks) = arguments[i + 1].parse::<u64>() {
+                    tournament_settings.max_ticks = max_ticks;
+                }
+                i += 1;
+            }
+            "--report" if i + 1 < arguments.len() => {
+                tournament_settings.report_path = arguments[i + 1].clone();
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    return tournament_settings;
+}
+
+// Every pair of AIs meets on every map and seed, once from each seat.
+fn play_round_robin(
+    game_settings: &GameSettings,
+    tournament_settings: &TournamentSettings,
+) -> Vec<GameRecord> {
+    let mut games: Vec<GameRecord> = Vec::new();
+    let number_of_ais: usize = tournament_settings.ai_names.len();
+    for first in 0..number_of_ais {
+        for second in first + 1..number_of_ais {
+            for &(game_area_width, game_area_height) in tournament_settings.maps.iter() {
+                for seed_index in 0..tournament_settings.number_of_seeds {
+                    for &(player, opponent) in [(first, second), (second, first)].iter() {
+                        let mut match_settings: GameSettings = game_settings.clone();
+                        match_settings.game_area_width = game_area_width;
+                        match_settings.game_area_height = game_area_height;
+                        match_settings.seed = Some(tournament_settings.seed_start + seed_index);
+                        let match_result: MatchResult = match simulation_utility::play_match(
+                            &match_settings,
+                            vec![
+                                tournament_settings.ai_names[player].clone(),
+                                tournament_settings.ai_names[opponent].clone(),
+                            ],
+                            tournament_settings.max_ticks,
+                        ) {
+                            Ok(match_result) => match_result,
+                            Err(error) => {
+                                eprintln!("Match failed: {}", error);
+                                process::exit(1);
+                            }
+                        };
+                        games.push(GameRecord {
+                            player,
+                            opponent,
+                            score: match match_result.winner_team {
+                                Some(0) => 1.0,
+                                Some(_) => 0.0,
+                                None => 0.5,
+                            },
+                        });
+                    }
+                }
+            }
+            println!(
+                "{} vs {}: done",
+                tournament_settings.ai_names[first], tournament_settings.ai_names[second]
+            );
+        }
+    }
+    return games;
+}
+
+fn get_standings(
+    games: &Vec<GameRecord>,
+    tournament_settings: &TournamentSettings,
+) -> Vec<Standing> {
+    let number_of_ais: usize = tournament_settings.ai_names.len();
+    let ratings: Vec<f64> = elo_utility::fit_ratings(games, number_of_ais);
+    let confidence_intervals: Vec<(f64, f64)> = elo_utility::get_confidence_intervals(
+        games,
+        number_of_ais,
+        &mut StdRng::seed_from_u64(tournament_settings.seed_start),
+    );
+
+    let mut standings: Vec<Standing> = (0..number_of_ais)
+        .map(|k| Standing {
+            ai_name: tournament_settings.ai_names[k].clone(),
+            rating: ratings[k],
+            confidence_interval: confidence_intervals[k],
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        })
+        .collect();
+    for game in games {
+        for &(k, score) in [(game.player, game.score), (game.opponent, 1.0 - game.score)].iter() {
+            if score > 0.5 {
+                standings[k].wins += 1;
+            } else if score < 0.5 {
+                standings[k].losses += 1;
+            } else {
+                standings[k].draws += 1;
+            }
+        }
+    }
+    standings.sort_by(|a, b| b.rating.partial_cmp(&a.rating).unwrap());
+    return standings;
+}
+
+fn get_report(
+    game_settings: &GameSettings,
+    tournament_settings: &TournamentSettings,
+    games: &Vec<GameRecord>,
+    standings: &Vec<Standing>,
+) -> String {
+    let maps: Vec<String> = tournament_settings
+        .maps
+        .iter()
+        .map(|(game_area_width, game_area_height)| {
+            game_area_width.to_string() + "x" + &game_area_height.to_string()
+        })
+        .collect();
+    let mut report: String = "# Tournament Report\n\n".to_string();
+    report.push_str(&format!(
+        "{} AIs, maps {}, seeds {} to {}, both seatings, {} games. {} mode, {} victory, draws after {} ticks.\n\n",
+        tournament_settings.ai_names.len(),
+        maps.join(", "),
+        tournament_settings.seed_start,
+        tournament_settings.seed_start + tournament_settings.number_of_seeds.max(1) - 1,
+        games.len(),
+        game_settings.game_mode.name(),
+        game_settings.victory_condition.name(),
+        tournament_settings.max_ticks
+    ));
+    report.push_str(&format!(
+        "| Rank | AI | Elo | {}% CI | Games | Wins | Draws | Losses | Score |\n",
+        (elo_utility::CONFIDENCE_LEVEL * 100.0) as i32
+    ));
+    report.push_str("|------|----|-----|--------|-------|------|-------|--------|-------|\n");
+    for (k, standing) in standings.iter().enumerate() {
+        let number_of_games: u64 = standing.wins + standing.draws + standing.losses;
+        report.push_str(&format!(
+            "| {} | {} | {:.0} | {:.0} to {:.0} | {} | {} | {} | {} | {:.1}% |\n",
+            k + 1,
+            standing.ai_name,
+            standing.rating,
+            standing.confidence_interval.0,
+            standing.confidence_interval.1,
+            number_of_games,
+            standing.wins,
+            standing.draws,
+            standing.losses,
+            simulation_utility::get_percentage(
+                standing.wins * 2 + standing.draws,
+                number_of_games * 2
+            )
+        ));
+    }
+    return report;
+}
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    let tournament_settings: TournamentSettings = parse_tournament_arguments(&arguments);
+    let mut game_settings: GameSettings = game_settings::parse_arguments(arguments);
+    game_settings.team_mode = TeamMode::FreeForAll;
+    game_settings.number_of_players = 2;
+    game_settings.export_prefix = None;
+
+    if tournament_settings.ai_names.len() < 2 || tournament_settings.maps.is_empty() {
+        eprintln!("A tournament needs at least two AIs and one map");
+        process::exit(1);
+    }
+    for ai_name in tournament_settings.ai_names.iter() {
+        if !ai::is_ai_name(ai_name) {
+            eprintln!(
+                "Unknown AI \"{}\", available: {}",
+                ai_name,
+                ai::AI_NAMES.join(", ")
+            );
+            process::exit(1);
+        }
+    }
+
+    let games: Vec<GameRecord> = play_round_robin(&game_settings, &tournament_settings);
+    let standings: Vec<Standing> = get_standings(&games, &tournament_settings);
+    let report: String = get_report(&game_settings, &tournament_settings, &games, &standings);
+    println!();
+    print!("{}", report);
+
+    match File::create(&tournament_settings.report_path)
+        .and_then(|mut file| file.write_all(report.as_bytes()))
+    {
+        Ok(()) => println!("\nReport written to {}", tournament_settings.report_path),
+        Err(error) => {
+            eprintln!("Could not write the report: {}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::ai::{Action, Ai};
+use crate::game_state::GameState;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+pub const BOT_PROTOCOL_VERSION: u32 = 1;
+pub const BOT_PREFIX: &str = "bot:";
+pub const HANDSHAKE_TIMEOUT_MS: u64 = 5000;
+// Counted in ticks of the match, 100 milliseconds at normal speed.
+pub const MOVE_TIMEOUT_TICKS: u64 = 6;
+// After this many timeouts or illegal replies the bot only passes for the rest of the match.
+pub const MAX_STRIKES: u32 = 10;
+
+// An opponent played by an external program that speaks the line protocol described in
+// docs/bot_protocol.md on its stdin and stdout. The game never waits for a move, the seat
+// passes until the bot's answer has arrived. When that is depends on the bot's process, so
+// bots only play local matches and their matches cannot be replayed.
+pub struct ProcessAi {
+    name: String,
+    child: Child,
+    messages: Sender<String>,
+    replies: Receiver<String>,
+    // The tick of the state the bot is thinking about.
+    asked_at_tick: Option<u64>,
+    strikes: u32,
+}
+
+impl ProcessAi {
+    pub fn new(command_line: &str) -> Result<ProcessAi, String> {
+        let mut arguments = command_line.split_whitespace();
+        let program: &str = match arguments.next() {
+            Some(program) => program,
+            None => return Err("empty bot command".to_string()),
+        };
+        let mut child: Child = Command::new(program)
+            .args(arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| {
+                "could not start ".to_string() + program + ": " + &error.to_string()
+            })?;
+        let mut stdin: ChildStdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Messages are written and replies read on their own threads, so a bot that stops
+        // reading or answering cannot block the game.
+        let (messages, message_receiver) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for message in message_receiver {
+                if stdin
+                    .write_all((message + "\n").as_bytes())
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let (sender, replies) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut process_ai: ProcessAi = ProcessAi {
+            name: program.to_string(),
+            child,
+            messages,
+            replies,
+            asked_at_tick: None,
+            strikes: 0,
+        };
+        process_ai.send("bitterboundaries ".to_string() + &BOT_PROTOCOL_VERSION.to_string());
+        match process_ai.receive(HANDSHAKE_TIMEOUT_MS) {
+            Some(reply) if reply.starts_with("ready") => {
+                let name: &str = reply["ready".len()..].trim();
+                if !name.is_empty() {
+                    process_ai.name = name.to_string();
+                }
+                return Ok(process_ai);
+            }
+            Some(reply) => return Err("unexpected handshake reply: ".to_string() + &reply),
+            None => return Err(program.to_string() + " did not answer the handshake"),
+        }
+    }
+
+    fn send(&mut self, message: String) {
+        let _ = self.messages.send(message);
+    }
+
+    // Replies that arrive after a timeout belong to an earlier turn.
+    fn ask(&mut self, game_state: &GameState, player: usize) {
+        while self.replies.try_recv().is_ok() {}
+        self.send(get_state_message(game_state, player));
+        self.asked_at_tick = Some(game_state.tick);
+    }
+
+    fn try_receive(&mut self) -> Option<String> {
+        while let Ok(line) = self.replies.try_recv() {
+            if !line.trim().is_empty() {
+                return Some(line.trim().to_string());
+            }
+        }
+        return None;
+    }
+
+    fn receive(&mut self, timeout_ms: u64) -> Option<String> {
+        loop {
+            match self.replies.recv_timeout(Duration::from_millis(timeout_ms)) {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => return Some(line.trim().to_string()),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return None
+                }
+            }
+        }
+    }
+
+    fn add_strike(&mut self, reason: String) {
+        self.strikes += 1;
+        self.send("illegal ".to_string() + &reason);
+        if self.strikes == MAX_STRIKES {
+            eprintln!(
+                "Bot {} reached {} strikes and will only pass from now on",
+                self.name, MAX_STRIKES
+            );
+        }
+    }
+}
+
+impl Ai for ProcessAi {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn choose_action(&mut self, game_state: &GameState, player: usize) -> Action {
+        if self.strikes >= MAX_STRIKES {
+            return Action::Pass;
+        }
+
+        let asked_at_tick: u64 = match self.asked_at_tick {
+            Some(asked_at_tick) => asked_at_tick,
+            None => {
+                self.ask(game_state, player);
+                return Action::Pass;
+            }
+        };
+        let reply: String = match self.try_receive() {
+            Some(reply) => reply,
+            None => {
+                if game_state.tick >= asked_at_tick + MOVE_TIMEOUT_TICKS {
+                    self.add_strike("timeout".to_string());
+                    self.ask(game_state, player);
+                }
+                return Action::Pass;
+            }
+        };
+        let action: Action = match parse_action(&reply) {
+            Some(action) if game_state.is_legal_action(player, action) => action,
+            Some(_) => {
+                self.add_strike("move ".to_string() + &reply);
+                Action::Pass
+            }
+            None => {
+                self.add_strike("unknown reply ".to_string() + &reply);
+                Action::Pass
+            }
+        };
+        self.ask(game_state, player);
+        return action;
+    }
+}
+
+impl Drop for ProcessAi {
+    fn drop(&mut self) {
+        self.send("quit".to_string());
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn get_grid_line<T: ToString>(
+    key: &str,
+    grid: &Vec<Vec<T>>,
+    width: usize,
+    height: usize,
+) -> String {
+    let mut values: Vec<String> = Vec::new();
+    for j in 0..height {
+        for i in 0..width {
+            values.push(grid[i][j].to_string());
+        }
+    }
+    return key.to_string() + " " + &values.join(" ");
+}
+
+// Grids are sent row by row, so the tile at column x and row y is value y * width + x.
+pub fn get_state_message(game_state: &GameState, player: usize) -> String {
+    let width: usize = game_state.game_area_width;
+    let height: usize = game_state.game_area_height;
+    let cash: Vec<String> = (0..game_state.players_cash.len())
+        .map(|player| (game_state.get_cash(player).round() as i64).to_string())
+        .collect();
+    let lines: Vec<String> = vec![
+        format!(
+            "state {} {} {} {} {}",
+            game_state.tick,
+            player,
+            width,
+            height,
+            game_state.players_cash.len()
+        ),
+        "cash ".to_string() + &cash.join(" "),
+        get_grid_line("owners", &game_state.tile_owned_by, width, height),
+        get_grid_line(
+            "populations",
+            &game_state.tile_population_number,
+            width,
+            height,
+        ),
+        get_grid_line("levels", &game_state.tile_improvement_level, width, height),
+        "go".to_string(),
+    ];
+    return lines.join("\n");
+}
+
+pub fn parse_action(reply: &str) -> Option<Action> {
+    let words: Vec<&str> = reply.split_whitespace().collect();
+    match words.as_slice() {
+        ["pass"] => Some(Action::Pass),
+        [kind, tile_x, tile_y] => {
+            let tile_x: usize = tile_x.parse::<usize>().ok()?;
+            let tile_y: usize = tile_y.parse::<usize>().ok()?;
+            match *kind {
+                "improve" => Some(Action::Improve(tile_x, tile_y)),
+                "capture" => Some(Action::Capture(tile_x, tile_y)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_settings;
+    use std::fs;
+    use std::time::Instant;
+
+    fn get_game_state() -> GameState {
+        let mut game_settings: game_settings::GameSettings =
+            game_settings::parse_arguments(Vec::new());
+        game_settings.seed = Some(3);
+        return GameState::new(game_settings);
+    }
+
+    // Starts `sh` with a script that answers the handshake and then runs `turns`.
+    fn start_stub_bot(name: &str, turns: &str) -> ProcessAi {
+        let path: String = std::env::temp_dir()
+            .join(format!(
+                "bitter_boundaries_{}_{}.sh",
+                name,
+                std::process::id()
+            ))
+            .to_string_lossy()
+            .to_string();
+        fs::write(
+            &path,
+            "read line\necho ready ".to_string() + name + "\n" + turns + "\n",
+        )
+        .unwrap();
+        let process_ai: ProcessAi = ProcessAi::new(&("sh ".to_string() + &path)).unwrap();
+        let _ = fs::remove_file(&path);
+        return process_ai;
+    }
+
+    #[test]
+    fn valid_replies_are_parsed() {
+        assert_eq!(parse_action("pass"), Some(Action::Pass));
+        assert_eq!(parse_action("improve 3 1"), Some(Action::Improve(3, 1)));
+        assert_eq!(parse_action("  capture 0 2 "), Some(Action::Capture(0, 2)));
+    }
+
+    #[test]
+    fn malformed_replies_are_refused() {
+        for reply in [
+            "",
+            "pass now",
+            "improve",
+            "improve 3",
+            "improve 3 1 4",
+            "improve -1 2",
+            "improve x 2",
+            "Capture 1 2",
+            "attack 1 2",
+        ]
+        .iter()
+        {
+            assert_eq!(parse_action(reply), None, "{:?}", reply);
+        }
+    }
+
+    #[test]
+    fn grids_are_sent_row_by_row() {
+        let mut game_state: GameState = get_game_state();
+        let width: usize = game_state.game_area_width;
+        let height: usize = game_state.game_area_height;
+        for i in 0..width {
+            for j in 0..height {
+                game_state.tile_population_number[i][j] = (j * width + i) as i32;
+            }
+        }
+        let message: String = get_state_message(&game_state, 1);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("state {} 1 {} {} 2", game_state.tick, width, height)
+        );
+        let populations: Vec<i32> = lines[3]
+            .strip_prefix("populations ")
+            .unwrap()
+            .split(' ')
+            .map(|value| value.parse::<i32>().unwrap())
+            .collect();
+        assert_eq!(
+            populations,
+            (0..(width * height) as i32).collect::<Vec<i32>>()
+        );
+        assert_eq!(lines[lines.len() - 1], "go");
+    }
+
+    #[test]
+    fn late_replies_count_as_strikes() {
+        let mut game_state: GameState = get_game_state();
+        let mut process_ai: ProcessAi = start_stub_bot("silent", "cat > /dev/null");
+        assert_eq!(process_ai.name(), "silent");
+        assert_eq!(process_ai.choose_action(&game_state, 1), Action::Pass);
+        game_state.tick += MOVE_TIMEOUT_TICKS - 1;
+        assert_eq!(process_ai.choose_action(&game_state, 1), Action::Pass);
+        assert_eq!(process_ai.strikes, 0);
+        game_state.tick += 1;
+        assert_eq!(process_ai.choose_action(&game_state, 1), Action::Pass);
+        assert_eq!(process_ai.strikes, 1);
+    }
+
+    #[test]
+    fn bot_only_passes_after_too_many_strikes() {
+        let game_state: GameState = get_game_state();
+        let mut process_ai: ProcessAi = start_stub_bot(
+            "unknown",
+            "while read line; do [ \"$line\" = go ] && echo fly 1 2; done",
+        );
+        let started_at: Instant = Instant::now();
+        while process_ai.strikes < MAX_STRIKES {
+            assert!(started_at.elapsed() < Duration::from_secs(10));
+            assert_eq!(process_ai.choose_action(&game_state, 1), Action::Pass);
+            thread::sleep(Duration::from_millis(5));
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(process_ai.choose_action(&game_state, 1), Action::Pass);
+        assert_eq!(process_ai.strikes, MAX_STRIKES);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::diplomacy_utility::DiplomaticState;
+use crate::event_utility::ActiveEvent;
+use crate::game_state::GameState;
+use crate::tech_utility::Research;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, Write};
+
+// Peers compare checksums this often during a network match.
+pub const CHECKSUM_INTERVAL_TICKS: u64 = 60;
+// The match keeps a full state dump this often, for replays.
+pub const CHECKPOINT_INTERVAL_TICKS: u64 = 300;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// The part of the state that is hashed. Nothing in it is a float, happiness is kept as its
+// bits, so two machines that ran the same match produce exactly the same dump.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StateDump {
+    pub tick: u64,
+    pub checksum: u64,
+    pub tile_owned_by: Vec<Vec<i32>>,
+    pub tile_population_number: Vec<Vec<i32>>,
+    pub tile_improvement_level: Vec<Vec<i32>>,
+    pub tile_happiness_bits: Vec<Vec<u64>>,
+    pub players_cash: Vec<i64>,
+    pub players_loan: Vec<i64>,
+    pub players_eliminated: Vec<bool>,
+    pub players_research: Vec<Research>,
+    pub diplomacy: Vec<Vec<DiplomaticState>>,
+    pub active_events: Vec<ActiveEvent>,
+}
+
+#[derive(Serialize)]
+struct DesyncReport<'a> {
+    tick: u64,
+    differences: Vec<String>,
+    local_name: &'a str,
+    local: &'a StateDump,
+    remote_name: &'a str,
+    remote: &'a StateDump,
+}
+
+// FNV-1a over the little-endian bytes, the same on every platform.
+fn add_to_hash(hash: u64, value: i64) -> u64 {
+    let mut hash: u64 = hash;
+    for byte in value.to_le_bytes().iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    return hash;
+}
+
+pub fn get_state_checksum(game_state: &GameState) -> u64 {
+    let mut hash: u64 = add_to_hash(FNV_OFFSET_BASIS, game_state.tick as i64);
+    hash = add_to_hash(hash, game_state.game_area_width as i64);
+    hash = add_to_hash(hash, game_state.game_area_height as i64);
+    for i in 0..game_state.game_area_width {
+        for j in 0..game_state.game_area_height {
+            hash = add_to_hash(hash, game_state.tile_owned_by[i][j] as i64);
+            hash = add_to_hash(hash, game_state.tile_population_number[i][j] as i64);
+            hash = add_to_hash(hash, game_state.tile_improvement_level[i][j] as i64);
+            hash = add_to_hash(hash, game_state.tile_happiness[i][j].to_bits() as i64);
+        }
+    }
+    for cash in game_state.players_cash.iter() {
+        hash = add_to_hash(hash, *cash);
+    }
+    for loan in game_state.players_loan.iter() {
+        hash = add_to_hash(hash, *loan);
+    }
+    for is_eliminated in game_state.players_eliminated.iter() {
+        hash = add_to_hash(hash, *is_eliminated as i64);
+    }
+    for research in game_state.players_research.iter() {
+        hash = add_to_hash(hash, research.researched.len() as i64);
+        for technology in research.researched.iter() {
+            hash = add_to_hash(hash, *technology as i64);
+        }
+        hash = add_to_hash(
+            hash,
+            research.current.map_or(-1, |technology| technology as i64),
+        );
+        hash = add_to_hash(hash, research.progress_ticks as i64);
+    }
+    for states in game_state.diplomacy.iter() {
+        for state in states.iter() {
+            hash = add_to_hash(hash, *state as i64);
+        }
+    }
+    hash = add_to_hash(hash, game_state.active_events.len() as i64);
+    for active_event in game_state.active_events.iter() {
+        hash = add_to_hash(hash, active_event.kind as i64);
+        hash = add_to_hash(hash, active_event.player as i64);
+        hash = add_to_hash(hash, active_event.expires_at_tick as i64);
+    }
+    return hash;
+}
+
+pub fn get_state_dump(game_state: &GameState) -> StateDump {
+    return StateDump {
+        tick: game_state.tick,
+        checksum: get_state_checksum(game_state),
+        tile_owned_by: game_state.tile_owned_by.clone(),
+        tile_population_number: game_state.tile_population_number.clone(),
+        tile_improvement_level: game_state.tile_improvement_level.clone(),
+        tile_happiness_bits: game_state
+            .tile_happiness
+            .iter()
+            .map(|column| column.iter().map(|happiness| happiness.to_bits()).collect())
+            .collect(),
+        players_cash: game_state.players_cash.clone(),
+        players_loan: game_state.players_loan.clone(),
+        players_eliminated: game_state.players_eliminated.clone(),
+        players_research: game_state.players_research.clone(),
+        diplomacy: game_state.diplomacy.clone(),
+        active_events: game_state.active_events.clone(),
+    };
+}
+
+fn add_grid_differences<T: PartialEq + Debug>(
+    differences: &mut Vec<String>,
+    name: &str,
+    grid: &Vec<Vec<T>>,
+    other_grid: &Vec<Vec<T>>,
+) {
+    if grid.len() != other_grid.len() {
+        differences.push(name.to_string() + " has a different size");
+        return;
+    }
+    for i in 0..grid.len() {
+        for j in 0..grid[i].len().min(other_grid[i].len()) {
+            if grid[i][j] != other_grid[i][j] {
+                differences.push(format!(
+                    "{}[{}][{}]: {:?} != {:?}",
+                    name, i, j, grid[i][j], other_grid[i][j]
+                ));
+            }
+        }
+    }
+}
+
+fn add_player_differences<T: PartialEq + Debug>(
+    differences: &mut Vec<String>,
+    name: &str,
+    values: &Vec<T>,
+    other_values: &Vec<T>,
+) {
+    for player in 0..values.len() {
+        if other_values.get(player) != Some(&values[player]) {
+            differences.push(format!(
+                "{}[{}]: {:?} != {:?}",
+                name,
+                player,
+                values[player],
+                other_values.get(player)
+            ));
+        }
+    }
+}
+
+pub fn get_differences(state_dump: &StateDump, other_state_dump: &StateDump) -> Vec<String> {
+    let mut differences: Vec<String> = Vec::new();
+    if state_dump.tick != other_state_dump.tick {
+        differences.push(format!(
+            "tick: {} != {}",
+            state_dump.tick, other_state_dump.tick
+        ));
+    }
+    add_grid_differences(
+        &mut differences,
+        "tile_owned_by",
+        &state_dump.tile_owned_by,
+        &other_state_dump.tile_owned_by,
+    );
+    add_grid_differences(
+        &mut differences,
+        "tile_population_number",
+        &state_dump.tile_population_number,
+        &other_state_dump.tile_population_number,
+    );
+    add_grid_differences(
+        &mut differences,
+        "tile_improvement_level",
+        &state_dump.tile_improvement_level,
+        &other_state_dump.tile_improvement_level,
+    );
+    add_grid_differences(
+        &mut differences,
+        "tile_happiness_bits",
+        &state_dump.tile_happiness_bits,
+        &other_state_dump.tile_happiness_bits,
+    );
+    add_player_differences(
+        &mut differences,
+        "players_cash",
+        &state_dump.players_cash,
+        &other_state_dump.players_cash,
+    );
+    add_player_differences(
+        &mut differences,
+        "players_loan",
+        &state_dump.players_loan,
+        &other_state_dump.players_loan,
+    );
+    add_player_differences(
+        &mut differences,
+        "players_eliminated",
+        &state_dump.players_eliminated,
+        &other_state_dump.players_eliminated,
+    );
+    add_player_differences(
+        &mut differences,
+        "players_research",
+        &state_dump.players_research,
+        &other_state_dump.players_research,
+    );
+    add_grid_differences(
+        &mut differences,
+        "diplomacy",
+        &state_dump.diplomacy,
+        &other_state_dump.diplomacy,
+    );
+    if state_dump.active_events != other_state_dump.active_events {
+        differences.push(format!(
+            "active_events: {:?} != {:?}",
+            state_dump.active_events, other_state_dump.active_events
+        ));
+    }
+    return differences;
+}
+
+// Writes both states and every value that differs between them as JSON.
+pub fn write_desync_report(
+    path: &str,
+    local_name: &str,
+    local: &StateDump,
+    remote_name: &str,
+    remote: &StateDump,
+) -> io::Result<()> {
+    let desync_report: DesyncReport = DesyncReport {
+        tick: local.tick,
+        differences: get_differences(local, remote),
+        local_name,
+        local,
+        remote_name,
+        remote,
+    };
+    let mut file: File = File::create(path)?;
+    file.write_all(serde_json::to_string_pretty(&desync_report)?.as_bytes())?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility;
+
+    #[test]
+    fn same_match_gives_the_same_checksum() {
+        let game_state: GameState = test_utility::play_match(7, 0, 900);
+        let other_game_state: GameState = test_utility::play_match(7, 0, 900);
+        assert_eq!(
+            get_state_checksum(&game_state),
+            get_state_checksum(&other_game_state)
+        );
+        assert_eq!(
+            get_state_dump(&game_state),
+            get_state_dump(&other_game_state)
+        );
+    }
+
+    #[test]
+    fn checksum_covers_cash_and_loans() {
+        let mut game_state: GameState = test_utility::play_match(7, 0, 300);
+        let checksum: u64 = get_state_checksum(&game_state);
+        game_state.players_cash[0] += 1;
+        let cash_checksum: u64 = get_state_checksum(&game_state);
+        assert_ne!(checksum, cash_checksum);
+        game_state.players_loan[1] += 1;
+        assert_ne!(cash_checksum, get_state_checksum(&game_state));
+    }
+
+    #[test]
+    fn checksum_covers_improvements_happiness_elimination_and_research() {
+        let mut game_state: GameState = test_utility::play_match(7, 0, 300);
+        let mut checksums: Vec<u64> = vec![get_state_checksum(&game_state)];
+        game_state.tile_improvement_level[0][0] += 1;
+        checksums.push(get_state_checksum(&game_state));
+        game_state.tile_happiness[0][0] -= 0.001;
+        checksums.push(get_state_checksum(&game_state));
+        game_state.players_eliminated[1] = !game_state.players_eliminated[1];
+        checksums.push(get_state_checksum(&game_state));
+        game_state.players_research[0].progress_ticks += 1;
+        checksums.push(get_state_checksum(&game_state));
+        game_state.diplomacy[0][1] = DiplomaticState::Alliance;
+        game_state.diplomacy[1][0] = DiplomaticState::Alliance;
+        checksums.push(get_state_checksum(&game_state));
+        for k in 1..checksums.len() {
+            assert_ne!(checksums[k - 1], checksums[k]);
+        }
+    }
+
+    #[test]
+    fn differences_list_every_changed_value() {
+        let state_dump: StateDump = get_state_dump(&test_utility::play_match(7, 0, 300));
+        assert!(get_differences(&state_dump, &state_dump).is_empty());
+
+        let mut other_state_dump: StateDump = state_dump.clone();
+        other_state_dump.tile_owned_by[1][2] += 1;
+        other_state_dump.players_loan[0] += 100;
+        let differences: Vec<String> = get_differences(&state_dump, &other_state_dump);
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].starts_with("tile_owned_by[1][2]: "));
+        assert!(differences[1].starts_with("players_loan[0]: "));
+    }
+}
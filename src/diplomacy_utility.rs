@@ -0,0 +1,193 @@
+use crate::tile_utility;
+use serde::{Deserialize, Serialize};
+
+pub const PROPOSAL_DURATION_TICKS: u64 = 1200;
+pub const NON_AGGRESSION_ACCEPTANCE_SCORE: f64 = 0.6;
+pub const ALLIANCE_ACCEPTANCE_SCORE: f64 = 0.9;
+pub const GOODWILL_PER_SCORE_POINT: f64 = 2000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DiplomaticState {
+    War,
+    NonAggression,
+    Alliance,
+}
+
+impl DiplomaticState {
+    pub fn name(&self) -> &str {
+        match self {
+            DiplomaticState::War => "War",
+            DiplomaticState::NonAggression => "Non-aggression",
+            DiplomaticState::Alliance => "Alliance",
+        }
+    }
+}
+
+pub struct Proposal {
+    pub from: usize,
+    pub to: usize,
+    pub state: DiplomaticState,
+    pub expires_at_tick: u64,
+}
+
+pub fn new_diplomacy(number_of_players: usize) -> Vec<Vec<DiplomaticState>> {
+    return vec![vec![DiplomaticState::War; number_of_players]; number_of_players];
+}
+
+pub fn set_diplomatic_state(
+    diplomacy: &mut Vec<Vec<DiplomaticState>>,
+    player: usize,
+    other_player: usize,
+    state: DiplomaticState,
+) {
+    diplomacy[player][other_player] = state;
+    diplomacy[other_player][player] = state;
+}
+
+pub fn get_friendly_players(diplomacy: &Vec<Vec<DiplomaticState>>, player: usize) -> Vec<i32> {
+    let mut friendly_players: Vec<i32> = vec![player as i32];
+    for other_player in 0..diplomacy.len() {
+        if other_player != player && diplomacy[player][other_player] == DiplomaticState::Alliance {
+            friendly_players.push(other_player as i32);
+        }
+    }
+    return friendly_players;
+}
+
+pub fn can_attack(diplomacy: &Vec<Vec<DiplomaticState>>, attacker: usize, defender: i32) -> bool {
+    if defender == tile_utility::NEUTRAL_OWNER {
+        return true;
+    }
+    return defender as usize != attacker
+        && diplomacy[attacker][defender as usize] == DiplomaticState::War;
+}
+
+pub fn get_acceptance_score(own_strength: f64, proposer_strength: f64, goodwill: f64) -> f64 {
+    return proposer_strength / own_strength.max(1.0) + goodwill / GOODWILL_PER_SCORE_POINT;
+}
+
+pub fn evaluate_proposal(
+    current_state: DiplomaticState,
+    proposed_state: DiplomaticState,
+    own_strength: f64,
+    proposer_strength: f64,
+    goodwill: f64,
+    number_of_active_players: usize,
+) -> bool {
+    let acceptance_score: f64 = get_acceptance_score(own_strength, proposer_strength, goodwill);
+    match proposed_state {
+        DiplomaticState::War => return true,
+        DiplomaticState::NonAggression => {
+            return current_state == DiplomaticState::War
+                && acceptance_score >= NON_AGGRESSION_ACCEPTANCE_SCORE;
+        }
+        DiplomaticState::Alliance => {
+            return current_state == DiplomaticState::NonAggression
+                && number_of_active_players > 2
+                && acceptance_score >= ALLIANCE_ACCEPTANCE_SCORE;
+        }
+    }
+}
+
+pub fn get_desired_state(
+    current_state: DiplomaticState,
+    own_strength: f64,
+    other_strength: f64,
+    number_of_active_players: usize,
+) -> Option<DiplomaticState> {
+    if current_state != DiplomaticState::War && own_strength > other_strength * 3.0 {
+        return Some(DiplomaticState::War);
+    }
+    match current_state {
+        DiplomaticState::War if other_strength >= own_strength * 0.8 => {
+            return Some(DiplomaticState::NonAggression);
+        }
+        DiplomaticState::NonAggression if number_of_active_players > 2 => {
+            return Some(DiplomaticState::Alliance);
+        }
+        _ => return None,
+    }
+}
+
+pub fn remove_expired_proposals(proposals: &mut Vec<Proposal>, tick: u64) {
+    proposals.retain(|proposal| proposal.expires_at_tick > tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy_utility;
+    use crate::game_settings;
+    use crate::game_state::{GameState, TRIBUTE_AMOUNT};
+
+    #[test]
+    fn only_players_at_war_can_be_attacked() {
+        let mut diplomacy: Vec<Vec<DiplomaticState>> = new_diplomacy(3);
+        set_diplomatic_state(&mut diplomacy, 0, 1, DiplomaticState::NonAggression);
+        set_diplomatic_state(&mut diplomacy, 0, 2, DiplomaticState::Alliance);
+        assert!(!can_attack(&diplomacy, 0, 1));
+        assert!(!can_attack(&diplomacy, 1, 0));
+        assert!(!can_attack(&diplomacy, 0, 2));
+        assert!(!can_attack(&diplomacy, 0, 0));
+        assert!(can_attack(&diplomacy, 1, 2));
+        assert!(can_attack(&diplomacy, 0, tile_utility::NEUTRAL_OWNER));
+
+        set_diplomatic_state(&mut diplomacy, 0, 1, DiplomaticState::War);
+        assert!(can_attack(&diplomacy, 0, 1));
+    }
+
+    #[test]
+    fn allied_tiles_count_as_adjacent() {
+        // Player 2 holds the target at (1, 1), player 1 the tile diagonal to it and player 0
+        // only a far corner.
+        let mut tile_owned_by: Vec<Vec<i32>> = vec![vec![tile_utility::NEUTRAL_OWNER; 4]; 4];
+        tile_owned_by[0][0] = 1;
+        tile_owned_by[1][1] = 2;
+        tile_owned_by[3][3] = 0;
+        let mut diplomacy: Vec<Vec<DiplomaticState>> = new_diplomacy(3);
+        let is_adjacent = |diplomacy: &Vec<Vec<DiplomaticState>>| -> bool {
+            return tile_utility::has_adjacent_friendly_tile(
+                &tile_owned_by,
+                1,
+                1,
+                4,
+                4,
+                &get_friendly_players(diplomacy, 0),
+            );
+        };
+        assert!(!is_adjacent(&diplomacy));
+
+        set_diplomatic_state(&mut diplomacy, 0, 1, DiplomaticState::NonAggression);
+        assert!(!is_adjacent(&diplomacy));
+
+        set_diplomatic_state(&mut diplomacy, 0, 1, DiplomaticState::Alliance);
+        assert_eq!(get_friendly_players(&diplomacy, 0), vec![0, 1]);
+        assert!(is_adjacent(&diplomacy));
+    }
+
+    #[test]
+    fn tribute_goodwill_wins_a_rejected_pact() {
+        let mut game_settings: game_settings::GameSettings =
+            game_settings::parse_arguments(Vec::new());
+        game_settings.seed = Some(1);
+        let mut game_state: GameState = GameState::new(game_settings);
+        assert!(!game_state.players_human[1]);
+        for i in 0..game_state.game_area_width {
+            for j in 0..game_state.game_area_height {
+                game_state.tile_population_number[i][j] = 0;
+            }
+        }
+        game_state.players_cash[0] = economy_utility::to_cash_units(4000.0);
+        game_state.players_cash[1] = economy_utility::to_cash_units(10000.0);
+
+        game_state.propose(0, 1, DiplomaticState::NonAggression);
+        assert_eq!(game_state.diplomacy[0][1], DiplomaticState::War);
+
+        assert!(game_state.send_tribute(0, 1, TRIBUTE_AMOUNT));
+        assert_eq!(game_state.players_goodwill[1][0], TRIBUTE_AMOUNT);
+        assert_eq!(game_state.players_goodwill[0][1], 0.0);
+        game_state.propose(0, 1, DiplomaticState::NonAggression);
+        assert_eq!(game_state.diplomacy[0][1], DiplomaticState::NonAggression);
+        assert_eq!(game_state.diplomacy[1][0], DiplomaticState::NonAggression);
+    }
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DISCOVERY_PROTOCOL_VERSION: u32 = 1;
+// Hosts announce to this UDP port, browsers listen on it.
+pub const DISCOVERY_PORT: u16 = 7879;
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+// A game that has not been announced for this long has closed.
+pub const GAME_TIMEOUT: Duration = Duration::from_secs(5);
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_DATAGRAM_LENGTH: usize = 2048;
+
+// What a host tells the local network about its game.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GameAnnouncement {
+    pub name: String,
+    pub port: u16,
+    pub game_area_width: usize,
+    pub game_area_height: usize,
+    pub number_of_players: usize,
+    pub max_number_of_players: usize,
+    pub is_in_match: bool,
+}
+
+// One JSON object per datagram. Browsers answer every announcement with a `Ping` to measure
+// the round trip.
+#[derive(Serialize, Deserialize, Debug)]
+enum DiscoveryMessage {
+    Announce {
+        version: u32,
+        game_id: u64,
+        announcement: GameAnnouncement,
+    },
+    Ping {
+        game_id: u64,
+        sent_at: u64,
+    },
+    Pong {
+        game_id: u64,
+        sent_at: u64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct DiscoveredGame {
+    // Address to pass to `LockstepSession::join`.
+    pub address: String,
+    pub announcement: GameAnnouncement,
+    pub ping_milliseconds: Option<u64>,
+    game_id: u64,
+    last_seen: Instant,
+}
+
+// Broadcasts the announcement every second until it is dropped.
+pub struct DiscoveryAnnouncer {
+    announcement: Arc<Mutex<GameAnnouncement>>,
+}
+
+// Collects the games announced on the local network until it is dropped.
+pub struct DiscoveryBrowser {
+    games: Arc<Mutex<Vec<DiscoveredGame>>>,
+}
+
+fn send_message(socket: &UdpSocket, address: SocketAddr, message: &DiscoveryMessage) {
+    if let Ok(datagram) = serde_json::to_vec(message) {
+        let _ = socket.send_to(&datagram, address);
+    }
+}
+
+fn receive_message(socket: &UdpSocket) -> Option<(DiscoveryMessage, SocketAddr)> {
+    let mut buffer: [u8; MAX_DATAGRAM_LENGTH] = [0; MAX_DATAGRAM_LENGTH];
+    let (length, address): (usize, SocketAddr) = socket.recv_from(&mut buffer).ok()?;
+    let message: DiscoveryMessage = serde_json::from_slice(&buffer[..length]).ok()?;
+    return Some((message, address));
+}
+
+impl DiscoveryAnnouncer {
+    pub fn start(announcement: GameAnnouncement) -> io::Result<DiscoveryAnnouncer> {
+        let socket: UdpSocket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+        let announcement: Arc<Mutex<GameAnnouncement>> = Arc::new(Mutex::new(announcement));
+        let shared_announcement: Weak<Mutex<GameAnnouncement>> = Arc::downgrade(&announcement);
+        let game_id: u64 = rand::random();
+
+        thread::spawn(move || {
+            // The loopback copy reaches browsers on this machine when broadcasts do not.
+            let addresses: [SocketAddr; 2] = [
+                SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT)),
+                SocketAddr::from((Ipv4Addr::LOCALHOST, DISCOVERY_PORT)),
+            ];
+            let mut last_announced_at: Option<Instant> = None;
+            loop {
+                let announcement: GameAnnouncement = match shared_announcement.upgrade() {
+                    Some(announcement) => announcement.lock().unwrap().clone(),
+                    None => return,
+                };
+                if last_announced_at.is_none_or(|last_announced_at| {
+                    last_announced_at.elapsed() >= ANNOUNCE_INTERVAL
+                }) {
+                    let message: DiscoveryMessage = DiscoveryMessage::Announce {
+                        version: DISCOVERY_PROTOCOL_VERSION,
+                        game_id,
+                        announcement,
+                    };
+                    for address in addresses.iter() {
+                        send_message(&socket, *address, &message);
+                    }
+                    last_announced_at = Some(Instant::now());
+                }
+                if let Some((DiscoveryMessage::Ping { game_id, sent_at }, address)) =
+                    receive_message(&socket)
+                {
+                    send_message(
+                        &socket,
+                        address,
+                        &DiscoveryMessage::Pong { game_id, sent_at },
+                    );
+                }
+            }
+        });
+        return Ok(DiscoveryAnnouncer { announcement });
+    }
+
+    pub fn update(&self, announcement: GameAnnouncement) {
+        *self.announcement.lock().unwrap() = announcement;
+    }
+}
+
+impl DiscoveryBrowser {
+    pub fn start() -> io::Result<DiscoveryBrowser> {
+        let socket: UdpSocket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))?;
+        socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+        let games: Arc<Mutex<Vec<DiscoveredGame>>> = Arc::new(Mutex::new(Vec::new()));
+        let shared_games: Weak<Mutex<Vec<DiscoveredGame>>> = Arc::downgrade(&games);
+
+        thread::spawn(move || {
+            let started_at: Instant = Instant::now();
+            loop {
+                let received: Option<(DiscoveryMessage, SocketAddr)> = receive_message(&socket);
+                let games: Arc<Mutex<Vec<DiscoveredGame>>> = match shared_games.upgrade() {
+                    Some(games) => games,
+                    None => return,
+                };
+                let mut games = games.lock().unwrap();
+                games.retain(|game| game.last_seen.elapsed() < GAME_TIMEOUT);
+                match received {
+                    Some((
+                        DiscoveryMessage::Announce {
+                            version,
+                            game_id,
+                            announcement,
+                        },
+                        announced_from,
+                    )) if version == DISCOVERY_PROTOCOL_VERSION => {
+                        // The broadcast and the loopback copy of one announcement are one game,
+                        // joined at the address it was first seen from.
+                        match games.iter_mut().find(|game| game.game_id == game_id) {
+                            Some(game) => {
+                                game.announcement = announcement;
+                                game.last_seen = Instant::now();
+                            }
+                            None => games.push(DiscoveredGame {
+                                address: announced_from.ip().to_string()
+                                    + ":"
+                                    + &announcement.port.to_string(),
+                                announcement,
+                                ping_milliseconds: None,
+                                game_id,
+                                last_seen: Instant::now(),
+                            }),
+                        }
+                        send_message(
+                            &socket,
+                            announced_from,
+                            &DiscoveryMessage::Ping {
+                                game_id,
+                                sent_at: started_at.elapsed().as_millis() as u64,
+                            },
+                        );
+                    }
+                    Some((DiscoveryMessage::Pong { game_id, sent_at }, _)) => {
+                        let now: u64 = started_at.elapsed().as_millis() as u64;
+                        if let Some(game) = games.iter_mut().find(|game| game.game_id == game_id) {
+                            game.ping_milliseconds = Some(now.saturating_sub(sent_at));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        return Ok(DiscoveryBrowser { games });
+    }
+
+    // Games in the order they were first seen, so the list does not jump around.
+    pub fn get_games(&self) -> Vec<DiscoveredGame> {
+        let games = self.games.lock().unwrap();
+        return games
+            .iter()
+            .filter(|game| game.last_seen.elapsed() < GAME_TIMEOUT)
+            .cloned()
+            .collect();
+    }
+}
@@ -0,0 +1,150 @@
+// Cash is kept as a whole number of hundredths, so adding up income every tick gives exactly
+// the same result on every machine.
+pub const CASH_UNITS_PER_COIN: i64 = 100;
+// Interest rates and shares are whole parts per million, so loans and deposits stay in cash units too.
+pub const RATE_PARTS: i64 = 1_000_000;
+
+pub fn get_upkeep(
+    player: i32,
+    tile_improvement_level: &Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    upkeep_per_level: i64,
+) -> i64 {
+    let mut upkeep: i64 = 0;
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                upkeep += upkeep_per_level * tile_improvement_level[i][j] as i64;
+            }
+        }
+    }
+    return upkeep;
+}
+
+pub fn shrink_largest_tiles(
+    player: i32,
+    tile_population_number: &mut Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    number_of_tiles: usize,
+    shrink_ratio: f64,
+) {
+    let mut owned_tiles: Vec<(usize, usize)> = Vec::new();
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                owned_tiles.push((i, j));
+            }
+        }
+    }
+    owned_tiles
+        .sort_by(|a, b| tile_population_number[b.0][b.1].cmp(&tile_population_number[a.0][a.1]));
+
+    for &(i, j) in owned_tiles.iter().take(number_of_tiles) {
+        let loss: i32 = ((tile_population_number[i][j] as f64 * shrink_ratio) as i32).max(1);
+        tile_population_number[i][j] = (tile_population_number[i][j] - loss).max(1);
+    }
+}
+
+// Rounds half away from zero, so small balances still earn or owe a whole unit.
+pub fn apply_rate(amount: i64, rate: i64) -> i64 {
+    let product: i128 = amount as i128 * rate as i128;
+    let half: i128 = RATE_PARTS as i128 / 2;
+    if product >= 0 {
+        return ((product + half) / RATE_PARTS as i128) as i64;
+    } else {
+        return ((product - half) / RATE_PARTS as i128) as i64;
+    }
+}
+
+pub fn is_player_bankrupt(cash: i64, bankruptcy_limit: i64) -> bool {
+    return cash < bankruptcy_limit;
+}
+
+pub fn get_loan_limit(total_population: i32, loan_limit_per_citizen: i64) -> i64 {
+    return total_population as i64 * loan_limit_per_citizen;
+}
+
+pub fn get_available_loan(loan: i64, loan_limit: i64) -> i64 {
+    return (loan_limit - loan).max(0);
+}
+
+pub fn get_deposit_interest(cash: i64, deposit_interest_rate: i64) -> i64 {
+    if cash > 0 {
+        return apply_rate(cash, deposit_interest_rate);
+    } else {
+        return 0;
+    }
+}
+
+pub fn get_loan_interest(loan: i64, loan_interest_rate: i64) -> i64 {
+    return apply_rate(loan, loan_interest_rate);
+}
+
+pub fn get_loan_repayment(loan: i64, income: i64, repayment_share: i64) -> i64 {
+    return apply_rate(income, repayment_share).min(loan).max(0);
+}
+
+// The loan that brings `cash` up to `cost`, or nothing when the loan limit does not cover it.
+pub fn get_loan_for_purchase(cash: i64, cost: i64, loan: i64, loan_limit: i64) -> i64 {
+    let shortfall: i64 = cost - cash;
+    if shortfall > 0 && shortfall <= get_available_loan(loan, loan_limit) {
+        return shortfall;
+    } else {
+        return 0;
+    }
+}
+
+pub fn to_cash_units(amount: f64) -> i64 {
+    return (amount * CASH_UNITS_PER_COIN as f64).round() as i64;
+}
+
+pub fn from_cash_units(cash_units: i64) -> f64 {
+    return cash_units as f64 / CASH_UNITS_PER_COIN as f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loan_for_purchase_covers_the_shortfall() {
+        assert_eq!(get_loan_for_purchase(300, 1000, 0, 5000), 700);
+        assert_eq!(get_loan_for_purchase(1000, 1000, 0, 5000), 0);
+        assert_eq!(get_loan_for_purchase(300, 1000, 4500, 5000), 0);
+    }
+
+    #[test]
+    fn loan_for_purchase_with_negative_cash_covers_the_debt_too() {
+        let cash: i64 = -400;
+        let cost: i64 = 1000;
+        let loan: i64 = get_loan_for_purchase(cash, cost, 0, 5000);
+        assert_eq!(loan, 1400);
+        assert!(cash + loan >= cost);
+        assert_eq!(get_loan_for_purchase(cash, cost, 0, 1200), 0);
+    }
+
+    #[test]
+    fn cash_units_round_to_the_nearest_hundredth() {
+        assert_eq!(to_cash_units(12.345), 1235);
+        assert_eq!(to_cash_units(12.344), 1234);
+        assert_eq!(to_cash_units(-0.005), -1);
+        assert_eq!(to_cash_units(0.1 + 0.2), 30);
+        assert_eq!(from_cash_units(1235), 12.35);
+        assert_eq!(from_cash_units(-250), -2.5);
+        for cash_units in [-100001, -1, 0, 1, 99, 123456789].iter() {
+            assert_eq!(to_cash_units(from_cash_units(*cash_units)), *cash_units);
+        }
+    }
+
+    #[test]
+    fn rates_round_half_away_from_zero() {
+        assert_eq!(apply_rate(25000, 20), 1);
+        assert_eq!(apply_rate(24999, 20), 0);
+        assert_eq!(apply_rate(-25000, 20), -1);
+        assert_eq!(apply_rate(100000, 500_000), 50000);
+    }
+}
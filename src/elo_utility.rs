@@ -0,0 +1,139 @@
+use rand::Rng;
+
+pub const INITIAL_RATING: f64 = 1500.0;
+pub const FIT_ITERATIONS: usize = 500;
+pub const FIT_STEP: f64 = 64.0;
+pub const BOOTSTRAP_RESAMPLES: usize = 200;
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+// One game between two rated players, `score` is 1 for a win, 0.5 for a draw and 0 for a loss.
+#[derive(Clone, Copy, Debug)]
+pub struct GameRecord {
+    pub player: usize,
+    pub opponent: usize,
+    pub score: f64,
+}
+
+pub fn get_expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    return 1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0));
+}
+
+// Fits ratings to every game at once, so the result does not depend on the order of the games.
+// Each player also gets a virtual draw against an initial rating, which keeps the ratings of
+// players that won or lost every game finite.
+pub fn fit_ratings(games: &Vec<GameRecord>, number_of_players: usize) -> Vec<f64> {
+    let mut ratings: Vec<f64> = vec![INITIAL_RATING; number_of_players];
+    let mut number_of_games: Vec<f64> = vec![1.0; number_of_players];
+    for game in games {
+        number_of_games[game.player] += 1.0;
+        number_of_games[game.opponent] += 1.0;
+    }
+
+    for _ in 0..FIT_ITERATIONS {
+        let mut residuals: Vec<f64> = (0..number_of_players)
+            .map(|player| 0.5 - get_expected_score(ratings[player], INITIAL_RATING))
+            .collect();
+        for game in games {
+            let residual: f64 =
+                game.score - get_expected_score(ratings[game.player], ratings[game.opponent]);
+            residuals[game.player] += residual;
+            residuals[game.opponent] -= residual;
+        }
+        for player in 0..number_of_players {
+            ratings[player] += FIT_STEP * residuals[player] / number_of_games[player];
+        }
+    }
+
+    if number_of_players > 0 {
+        let mean_rating: f64 = ratings.iter().sum::<f64>() / number_of_players as f64;
+        for rating in ratings.iter_mut() {
+            *rating += INITIAL_RATING - mean_rating;
+        }
+    }
+    return ratings;
+}
+
+// Bootstrap interval: refits the ratings on games drawn with replacement and takes the
+// percentiles of every player's rating.
+pub fn get_confidence_intervals<R: Rng>(
+    games: &Vec<GameRecord>,
+    number_of_players: usize,
+    rng: &mut R,
+) -> Vec<(f64, f64)> {
+    if games.is_empty() {
+        return vec![(INITIAL_RATING, INITIAL_RATING); number_of_players];
+    }
+
+    let mut resampled_ratings: Vec<Vec<f64>> = vec![Vec::new(); number_of_players];
+    for _ in 0..BOOTSTRAP_RESAMPLES {
+        let resampled_games: Vec<GameRecord> = (0..games.len())
+            .map(|_| games[rng.gen_range(0, games.len())])
+            .collect();
+        let ratings: Vec<f64> = fit_ratings(&resampled_games, number_of_players);
+        for player in 0..number_of_players {
+            resampled_ratings[player].push(ratings[player]);
+        }
+    }
+
+    let lower_index: usize = ((1.0 - CONFIDENCE_LEVEL) / 2.0 * BOOTSTRAP_RESAMPLES as f64) as usize;
+    let upper_index: usize = BOOTSTRAP_RESAMPLES - 1 - lower_index;
+    return resampled_ratings
+        .into_iter()
+        .map(|mut ratings| {
+            ratings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            (ratings[lower_index], ratings[upper_index])
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // Player 0 wins `wins` games against player 1 and loses `losses`.
+    fn get_games(wins: usize, losses: usize) -> Vec<GameRecord> {
+        let mut games: Vec<GameRecord> = Vec::new();
+        for game in 0..wins + losses {
+            games.push(GameRecord {
+                player: 0,
+                opponent: 1,
+                score: if game < wins { 1.0 } else { 0.0 },
+            });
+        }
+        return games;
+    }
+
+    #[test]
+    fn win_ratio_gives_the_expected_rating_gap() {
+        // Winning three games in four is worth 400 * log10(3), about 191 points.
+        let ratings: Vec<f64> = fit_ratings(&get_games(300, 100), 2);
+        let expected_gap: f64 = 400.0 * 3f64.log10();
+        assert!((ratings[0] - ratings[1] - expected_gap).abs() < 5.0);
+        assert!((ratings[0] + ratings[1] - 2.0 * INITIAL_RATING).abs() < 1e-6);
+    }
+
+    #[test]
+    fn even_results_give_equal_ratings() {
+        let ratings: Vec<f64> = fit_ratings(&get_games(50, 50), 2);
+        assert!((ratings[0] - INITIAL_RATING).abs() < 1e-6);
+        assert!((ratings[1] - INITIAL_RATING).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bootstrap_bounds_bracket_the_estimate() {
+        let games: Vec<GameRecord> = get_games(60, 40);
+        let ratings: Vec<f64> = fit_ratings(&games, 2);
+        let mut rng: StdRng = StdRng::seed_from_u64(3);
+        let confidence_intervals: Vec<(f64, f64)> = get_confidence_intervals(&games, 2, &mut rng);
+        for player in 0..2 {
+            let (lower, upper) = confidence_intervals[player];
+            assert!(lower < ratings[player] && ratings[player] < upper);
+        }
+
+        let mut same_rng: StdRng = StdRng::seed_from_u64(3);
+        let same_confidence_intervals: Vec<(f64, f64)> =
+            get_confidence_intervals(&games, 2, &mut same_rng);
+        assert_eq!(confidence_intervals, same_confidence_intervals);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::game_state::GameState;
+use crate::tile_utility;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const PLAGUE_POPULATION_LOSS_RATIO: f64 = 0.3;
+pub const FAMINE_POPULATION_LOSS_RATIO: f64 = 0.1;
+pub const FIRE_POPULATION_LOSS_RATIO: f64 = 0.15;
+pub const BOOM_INCOME_BONUS: f64 = 0.5;
+pub const FIRE_COST_MULTIPLIER: f64 = 1.5;
+pub const EVENT_DURATION_TICKS: u64 = 1800;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WorldEventKind {
+    Plague,
+    Famine,
+    Boom,
+    Fire,
+}
+
+impl WorldEventKind {
+    pub fn name(&self) -> &str {
+        match self {
+            WorldEventKind::Plague => "Plague",
+            WorldEventKind::Famine => "Famine",
+            WorldEventKind::Boom => "Boom",
+            WorldEventKind::Fire => "Fire",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct EventWeights {
+    pub plague: u32,
+    pub famine: u32,
+    pub boom: u32,
+    pub fire: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct EventSettings {
+    pub enabled: bool,
+    pub interval_ticks: u64,
+    pub chance: f64,
+    pub weights: EventWeights,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ActiveEvent {
+    pub kind: WorldEventKind,
+    pub player: i32,
+    pub expires_at_tick: u64,
+}
+
+pub fn choose_event<R: Rng>(weights: &EventWeights, rng: &mut R) -> Option<WorldEventKind> {
+    let weighted_events: [(WorldEventKind, u32); 4] = [
+        (WorldEventKind::Plague, weights.plague),
+        (WorldEventKind::Famine, weights.famine),
+        (WorldEventKind::Boom, weights.boom),
+        (WorldEventKind::Fire, weights.fire),
+    ];
+    let total_weight: u32 = weighted_events.iter().map(|&(_, weight)| weight).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut roll: u32 = rng.gen_range(0, total_weight);
+    for &(kind, weight) in weighted_events.iter() {
+        if roll < weight {
+            return Some(kind);
+        }
+        roll -= weight;
+    }
+    return None;
+}
+
+pub fn get_random_owned_tile<R: Rng>(
+    player: i32,
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    rng: &mut R,
+) -> Option<(usize, usize)> {
+    let mut owned_tiles: Vec<(usize, usize)> = Vec::new();
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                owned_tiles.push((i, j));
+            }
+        }
+    }
+    if owned_tiles.is_empty() {
+        return None;
+    }
+    return Some(owned_tiles[rng.gen_range(0, owned_tiles.len())]);
+}
+
+fn reduce_population(tile_population_number: &mut Vec<Vec<i32>>, x: usize, y: usize, ratio: f64) {
+    let loss: i32 = (tile_population_number[x][y] as f64 * ratio) as i32;
+    tile_population_number[x][y] = (tile_population_number[x][y] - loss).max(1);
+}
+
+pub fn fire_event(game_state: &mut GameState, kind: WorldEventKind, player: i32) -> String {
+    let game_area_width: usize = game_state.game_area_width;
+    let game_area_height: usize = game_state.game_area_height;
+    let tick: u64 = game_state.tick;
+    let tile_population_number: &mut Vec<Vec<i32>> = &mut game_state.tile_population_number;
+    let tile_owned_by: &Vec<Vec<i32>> = &game_state.tile_owned_by;
+    let active_events: &mut Vec<ActiveEvent> = &mut game_state.active_events;
+    let target_tile: Option<(usize, usize)> = get_random_owned_tile(
+        player,
+        tile_owned_by,
+        game_area_width,
+        game_area_height,
+        &mut game_state.event_rng,
+    );
+
+    match kind {
+        WorldEventKind::Plague => {
+            if let Some((x, y)) = target_tile {
+                reduce_population(tile_population_number, x, y, PLAGUE_POPULATION_LOSS_RATIO);
+                for (i, j) in
+                    tile_utility::get_adjacent_tiles(x, y, game_area_width, game_area_height)
+                {
+                    if tile_owned_by[i][j] == player {
+                        reduce_population(
+                            tile_population_number,
+                            i,
+                            j,
+                            PLAGUE_POPULATION_LOSS_RATIO / 2.0,
+                        );
+                    }
+                }
+            }
+        }
+        WorldEventKind::Famine => {
+            for i in 0..game_area_width {
+                for j in 0..game_area_height {
+                    if tile_owned_by[i][j] == player {
+                        reduce_population(
+                            tile_population_number,
+                            i,
+                            j,
+                            FAMINE_POPULATION_LOSS_RATIO,
+                        );
+                    }
+                }
+            }
+        }
+        WorldEventKind::Boom => {
+            active_events.push(ActiveEvent {
+                kind,
+                player,
+                expires_at_tick: tick + EVENT_DURATION_TICKS,
+            });
+        }
+        WorldEventKind::Fire => {
+            if let Some((x, y)) = target_tile {
+                reduce_population(tile_population_number, x, y, FIRE_POPULATION_LOSS_RATIO);
+            }
+            active_events.push(ActiveEvent {
+                kind,
+                player,
+                expires_at_tick: tick + EVENT_DURATION_TICKS,
+            });
+        }
+    }
+
+    let mut message: String = kind.name().to_string() + " strikes Player " + &player.to_string();
+    if let Some((x, y)) = target_tile {
+        if kind == WorldEventKind::Plague || kind == WorldEventKind::Fire {
+            message = message + " at (" + &x.to_string() + ", " + &y.to_string() + ")";
+        }
+    }
+    return message;
+}
+
+pub fn remove_expired_events(active_events: &mut Vec<ActiveEvent>, tick: u64) {
+    active_events.retain(|active_event| active_event.expires_at_tick > tick);
+}
+
+pub fn get_income_multiplier(player: i32, active_events: &Vec<ActiveEvent>) -> f64 {
+    let mut income_multiplier: f64 = 1.0;
+    for active_event in active_events.iter() {
+        if active_event.player == player && active_event.kind == WorldEventKind::Boom {
+            income_multiplier += BOOM_INCOME_BONUS;
+        }
+    }
+    return income_multiplier;
+}
+
+pub fn get_cost_multiplier(player: i32, active_events: &Vec<ActiveEvent>) -> f64 {
+    let mut cost_multiplier: f64 = 1.0;
+    for active_event in active_events.iter() {
+        if active_event.player == player && active_event.kind == WorldEventKind::Fire {
+            cost_multiplier *= FIRE_COST_MULTIPLIER;
+        }
+    }
+    return cost_multiplier;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{self, Ai};
+    use crate::game_settings::{self, GameMode, GameSettings};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn get_weights(plague: u32, famine: u32, boom: u32, fire: u32) -> EventWeights {
+        return EventWeights {
+            plague,
+            famine,
+            boom,
+            fire,
+        };
+    }
+
+    fn get_active_event(kind: WorldEventKind, player: i32, expires_at_tick: u64) -> ActiveEvent {
+        return ActiveEvent {
+            kind,
+            player,
+            expires_at_tick,
+        };
+    }
+
+    // Number of events announced in a seeded match between AIs.
+    fn count_events(game_mode: GameMode, ticks: u64) -> usize {
+        let mut game_settings: GameSettings = game_settings::parse_arguments(Vec::new());
+        game_settings.seed = Some(4);
+        game_settings.game_mode = game_mode;
+        game_settings.number_of_humans = 0;
+        let mut game_state: GameState = GameState::new(game_settings);
+        let mut ais: Vec<Option<Box<dyn Ai>>> = ai::get_opponent_ais(&game_state).unwrap();
+        while game_state.tick < ticks && !game_state.is_win {
+            game_state.step(&mut ais);
+        }
+        return game_state
+            .match_log
+            .iter()
+            .filter(|entry| entry.message.contains(" strikes Player "))
+            .count();
+    }
+
+    #[test]
+    fn events_are_chosen_by_weight() {
+        let mut rng: StdRng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            assert_eq!(choose_event(&get_weights(0, 0, 0, 0), &mut rng), None);
+            assert_eq!(
+                choose_event(&get_weights(0, 0, 5, 0), &mut rng),
+                Some(WorldEventKind::Boom)
+            );
+            assert_eq!(
+                choose_event(&get_weights(0, 0, 0, 1), &mut rng),
+                Some(WorldEventKind::Fire)
+            );
+        }
+    }
+
+    #[test]
+    fn overlapping_events_stack() {
+        let active_events: Vec<ActiveEvent> = vec![
+            get_active_event(WorldEventKind::Boom, 0, 100),
+            get_active_event(WorldEventKind::Boom, 0, 200),
+            get_active_event(WorldEventKind::Fire, 0, 100),
+            get_active_event(WorldEventKind::Fire, 0, 200),
+            get_active_event(WorldEventKind::Fire, 1, 200),
+        ];
+        assert_eq!(
+            get_income_multiplier(0, &active_events),
+            1.0 + 2.0 * BOOM_INCOME_BONUS
+        );
+        assert_eq!(
+            get_cost_multiplier(0, &active_events),
+            FIRE_COST_MULTIPLIER * FIRE_COST_MULTIPLIER
+        );
+        assert_eq!(get_income_multiplier(1, &active_events), 1.0);
+        assert_eq!(get_cost_multiplier(1, &active_events), FIRE_COST_MULTIPLIER);
+        assert_eq!(get_income_multiplier(2, &active_events), 1.0);
+        assert_eq!(get_cost_multiplier(2, &active_events), 1.0);
+    }
+
+    #[test]
+    fn events_expire_at_their_tick() {
+        let mut active_events: Vec<ActiveEvent> = vec![
+            get_active_event(WorldEventKind::Boom, 0, 100),
+            get_active_event(WorldEventKind::Fire, 1, 101),
+        ];
+        remove_expired_events(&mut active_events, 99);
+        assert_eq!(active_events.len(), 2);
+        remove_expired_events(&mut active_events, 100);
+        assert_eq!(
+            active_events,
+            vec![get_active_event(WorldEventKind::Fire, 1, 101)]
+        );
+        remove_expired_events(&mut active_events, 101);
+        assert!(active_events.is_empty());
+    }
+
+    #[test]
+    fn classic_matches_have_no_events() {
+        assert!(!GameMode::Classic.get_event_settings().enabled);
+        assert_eq!(count_events(GameMode::Classic, 6000), 0);
+        assert!(count_events(GameMode::Chaos, 6000) > 0);
+    }
+}
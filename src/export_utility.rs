@@ -0,0 +1,239 @@
+use crate::match_log::ActionLogEntry;
+use crate::statistics_utility::{PlayerSample, PlayerStatistics, SAMPLE_INTERVAL_TICKS};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+
+// Bump when a column or field is renamed, removed or changes meaning. See docs/export_schema.md.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct PlayerExport {
+    pub player: usize,
+    pub team: usize,
+    pub faction: String,
+    pub eliminated: bool,
+    pub score: f64,
+    pub statistics: PlayerStatistics,
+}
+
+#[derive(Serialize)]
+pub struct SampleExport {
+    pub tick: u64,
+    pub player: usize,
+    #[serde(flatten)]
+    pub sample: PlayerSample,
+}
+
+#[derive(Serialize)]
+pub struct MatchExport {
+    pub schema_version: u32,
+    pub seed: u64,
+    pub game_mode: String,
+    pub team_mode: String,
+    pub victory_condition: String,
+    pub ticks: u64,
+    pub winner_team: usize,
+    pub players: Vec<PlayerExport>,
+    pub samples: Vec<SampleExport>,
+    pub actions: Vec<ActionLogEntry>,
+}
+
+pub fn get_sample_exports(samples: &Vec<Vec<PlayerSample>>) -> Vec<SampleExport> {
+    let mut sample_exports: Vec<SampleExport> = Vec::new();
+    for (k, sample) in samples.iter().enumerate() {
+        for (player, player_sample) in sample.iter().enumerate() {
+            sample_exports.push(SampleExport {
+                tick: k as u64 * SAMPLE_INTERVAL_TICKS,
+                player,
+                sample: *player_sample,
+            });
+        }
+    }
+    return sample_exports;
+}
+
+fn get_optional_field(value: Option<usize>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+pub fn write_samples_csv(path: &str, match_export: &MatchExport) -> io::Result<()> {
+    let mut file: File = File::create(path)?;
+    writeln!(file, "tick,player,population,cash,number_of_tiles")?;
+    for sample_export in match_export.samples.iter() {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            sample_export.tick,
+            sample_export.player,
+            sample_export.sample.population,
+            sample_export.sample.cash,
+            sample_export.sample.number_of_tiles
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_actions_csv(path: &str, match_export: &MatchExport) -> io::Result<()> {
+    let mut file: File = File::create(path)?;
+    writeln!(file, "tick,player,action,detail,x,y,target_player,amount")?;
+    for action in match_export.actions.iter() {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            action.tick,
+            action.player,
+            action.action,
+            action.detail,
+            get_optional_field(action.x),
+            get_optional_field(action.y),
+            get_optional_field(action.target_player),
+            action.amount
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_json(path: &str, match_export: &MatchExport) -> io::Result<()> {
+    let file: File = File::create(path)?;
+    serde_json::to_writer_pretty(file, match_export)?;
+    Ok(())
+}
+
+// Writes `<prefix>_samples.csv`, `<prefix>_actions.csv` and `<prefix>.json`.
+pub fn export_match(prefix: &str, match_export: &MatchExport) -> io::Result<Vec<String>> {
+    let samples_path: String = prefix.to_string() + "_samples.csv";
+    let actions_path: String = prefix.to_string() + "_actions.csv";
+    let json_path: String = prefix.to_string() + ".json";
+    write_samples_csv(&samples_path, match_export)?;
+    write_actions_csv(&actions_path, match_export)?;
+    write_json(&json_path, match_export)?;
+    return Ok(vec![samples_path, actions_path, json_path]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const EXPORT_SCHEMA: &str = include_str!("../docs/export_schema.md");
+
+    // The column names in the first table after the heading of `file_name`.
+    fn get_documented_columns(file_name: &str) -> String {
+        let heading: String = "## `<prefix>".to_string() + file_name + "`";
+        let section: &str = EXPORT_SCHEMA.split(heading.as_str()).nth(1).unwrap();
+        let mut columns: Vec<String> = Vec::new();
+        for line in section.lines().skip_while(|line| !line.starts_with('|')) {
+            if !line.starts_with('|') {
+                break;
+            }
+            let cell: &str = line.split('|').nth(1).unwrap().trim();
+            if cell.starts_with('`') {
+                for column in cell.split(", ") {
+                    columns.push(column.trim_matches('`').to_string());
+                }
+            }
+        }
+        return columns.join(",");
+    }
+
+    fn get_action(tick: u64, x: Option<usize>, target_player: Option<usize>) -> ActionLogEntry {
+        return ActionLogEntry {
+            tick,
+            player: 0,
+            action: "attack".to_string(),
+            detail: String::new(),
+            x,
+            y: x,
+            target_player,
+            amount: 150.0,
+        };
+    }
+
+    fn get_match_export() -> MatchExport {
+        let sample: PlayerSample = PlayerSample {
+            population: 16,
+            cash: 2.5,
+            number_of_tiles: 4,
+        };
+        return MatchExport {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            seed: 42,
+            game_mode: "Standard".to_string(),
+            team_mode: "Free-for-all".to_string(),
+            victory_condition: "Conquest".to_string(),
+            ticks: 600,
+            winner_team: 0,
+            players: Vec::new(),
+            samples: get_sample_exports(&vec![vec![sample; 2]; 3]),
+            actions: vec![
+                get_action(120, Some(2), Some(1)),
+                get_action(240, None, None),
+            ],
+        };
+    }
+
+    #[test]
+    fn samples_are_taken_every_interval() {
+        let match_export: MatchExport = get_match_export();
+        let ticks_and_players: Vec<(u64, usize)> = match_export
+            .samples
+            .iter()
+            .map(|sample_export| (sample_export.tick, sample_export.player))
+            .collect();
+        assert_eq!(
+            ticks_and_players,
+            vec![
+                (0, 0),
+                (0, 1),
+                (SAMPLE_INTERVAL_TICKS, 0),
+                (SAMPLE_INTERVAL_TICKS, 1),
+                (2 * SAMPLE_INTERVAL_TICKS, 0),
+                (2 * SAMPLE_INTERVAL_TICKS, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_follow_the_documented_schema() {
+        assert!(EXPORT_SCHEMA.contains(
+            &("The current schema version is `".to_string()
+                + &EXPORT_SCHEMA_VERSION.to_string()
+                + "`")
+        ));
+        let prefix: String = std::env::temp_dir()
+            .join("bitter_boundaries_export_test")
+            .to_string_lossy()
+            .to_string();
+        let paths: Vec<String> = export_match(&prefix, &get_match_export()).unwrap();
+        let samples_csv: String = fs::read_to_string(&paths[0]).unwrap();
+        let actions_csv: String = fs::read_to_string(&paths[1]).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&paths[2]).unwrap()).unwrap();
+        for path in paths.iter() {
+            let _ = fs::remove_file(path);
+        }
+
+        let samples_lines: Vec<&str> = samples_csv.lines().collect();
+        assert_eq!(samples_lines[0], get_documented_columns("_samples.csv"));
+        assert_eq!(samples_lines[1], "0,0,16,2.5,4");
+        assert_eq!(samples_lines.len(), 7);
+
+        let actions_lines: Vec<&str> = actions_csv.lines().collect();
+        assert_eq!(actions_lines[0], get_documented_columns("_actions.csv"));
+        assert_eq!(actions_lines[1], "120,0,attack,,2,2,1,150");
+        assert_eq!(actions_lines[2], "240,0,attack,,,,,150");
+
+        assert_eq!(json["schema_version"], EXPORT_SCHEMA_VERSION);
+        assert_eq!(json["actions"][0]["x"], 2);
+        assert_eq!(json["actions"][0]["target_player"], 1);
+        for field in ["x", "y", "target_player"].iter() {
+            assert!(json["actions"][1][field].is_null());
+        }
+        assert_eq!(json["samples"][2]["tick"], SAMPLE_INTERVAL_TICKS);
+        assert_eq!(json["samples"][2]["number_of_tiles"], 4);
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Faction {
+    Kingdom,
+    Horde,
+    Republic,
+    Merchants,
+}
+
+pub const FACTIONS: [Faction; 4] = [
+    Faction::Kingdom,
+    Faction::Horde,
+    Faction::Republic,
+    Faction::Merchants,
+];
+
+impl Faction {
+    pub fn name(&self) -> &str {
+        match self {
+            Faction::Kingdom => "Kingdom",
+            Faction::Horde => "Horde",
+            Faction::Republic => "Republic",
+            Faction::Merchants => "Merchants",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            Faction::Kingdom => "improvements 10% cheaper",
+            Faction::Horde => "captures 30% cheaper, 10% less income",
+            Faction::Republic => "improvements grow 30% more, captures 20% dearer",
+            Faction::Merchants => "income +5% per settlement tier",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Faction> {
+        return FACTIONS
+            .iter()
+            .find(|faction| faction.name().to_lowercase() == name.to_lowercase())
+            .copied();
+    }
+
+    pub fn index(&self) -> usize {
+        return FACTIONS
+            .iter()
+            .position(|faction| faction == self)
+            .unwrap_or(0);
+    }
+
+    pub fn next(&self) -> Faction {
+        return FACTIONS[(self.index() + 1) % FACTIONS.len()];
+    }
+
+    pub fn get_settlement_sprite_directory(&self) -> &str {
+        match self {
+            Faction::Kingdom => "sprites/settlements",
+            Faction::Horde => "sprites/factions/horde",
+            Faction::Republic => "sprites/factions/republic",
+            Faction::Merchants => "sprites/factions/merchants",
+        }
+    }
+
+    pub fn get_improvement_cost_modifier(&self) -> f64 {
+        match self {
+            Faction::Kingdom => 0.9,
+            _ => 1.0,
+        }
+    }
+
+    pub fn get_owner_change_cost_modifier(&self) -> f64 {
+        match self {
+            Faction::Horde => 0.7,
+            Faction::Republic => 1.2,
+            _ => 1.0,
+        }
+    }
+
+    pub fn get_population_growth_modifier(&self) -> f64 {
+        match self {
+            Faction::Republic => 1.3,
+            _ => 1.0,
+        }
+    }
+
+    pub fn get_income_modifier(&self, level_of_settlement: i32) -> f64 {
+        match self {
+            Faction::Horde => 0.9,
+            Faction::Merchants => 1.0 + 0.05 * level_of_settlement as f64,
+            _ => 1.0,
+        }
+    }
+
+    // Chance that the AI looks for a tile to capture instead of improving its own.
+    pub fn get_ai_capture_preference(&self) -> f64 {
+        match self {
+            Faction::Horde => 0.6,
+            Faction::Republic => 0.1,
+            _ => 0.25,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy_utility;
+    use crate::game_settings;
+    use crate::game_state::{GameState, TILE_IMPROVEMENT_BASE_COST, TILE_OWNER_CHANGE_PRICE};
+
+    #[test]
+    fn names_round_trip() {
+        for faction in FACTIONS.iter() {
+            assert_eq!(Faction::from_name(faction.name()), Some(*faction));
+            assert_eq!(
+                Faction::from_name(&faction.name().to_uppercase()),
+                Some(*faction)
+            );
+        }
+        assert_eq!(Faction::from_name("empire"), None);
+    }
+
+    #[test]
+    fn costs_apply_the_faction_modifiers() {
+        let mut game_state: GameState = GameState::new(game_settings::parse_arguments(Vec::new()));
+        for faction in FACTIONS.iter() {
+            game_state.players_faction[0] = *faction;
+            assert_eq!(
+                economy_utility::to_cash_units(game_state.get_improvement_cost(0, 2)),
+                economy_utility::to_cash_units(
+                    TILE_IMPROVEMENT_BASE_COST * 3.0 * faction.get_improvement_cost_modifier()
+                )
+            );
+            assert_eq!(
+                economy_utility::to_cash_units(game_state.get_owner_change_cost(0, 2)),
+                economy_utility::to_cash_units(
+                    TILE_OWNER_CHANGE_PRICE * 3.0 * faction.get_owner_change_cost_modifier()
+                )
+            );
+        }
+
+        game_state.players_faction[0] = Faction::Kingdom;
+        assert_eq!(
+            economy_utility::to_cash_units(game_state.get_improvement_cost(0, 0)),
+            economy_utility::to_cash_units(900.0)
+        );
+        game_state.players_faction[0] = Faction::Horde;
+        assert_eq!(
+            economy_utility::to_cash_units(game_state.get_owner_change_cost(0, 0)),
+            economy_utility::to_cash_units(1050.0)
+        );
+        game_state.players_faction[0] = Faction::Republic;
+        assert_eq!(
+            economy_utility::to_cash_units(game_state.get_owner_change_cost(0, 0)),
+            economy_utility::to_cash_units(1800.0)
+        );
+    }
+
+    #[test]
+    fn income_modifiers_follow_the_descriptions() {
+        assert_eq!(Faction::Kingdom.get_income_modifier(10), 1.0);
+        assert_eq!(Faction::Horde.get_income_modifier(10), 0.9);
+        assert_eq!(Faction::Republic.get_income_modifier(10), 1.0);
+        assert_eq!(Faction::Merchants.get_income_modifier(0), 1.0);
+        assert_eq!(Faction::Merchants.get_income_modifier(10), 1.5);
+        assert_eq!(Faction::Republic.get_population_growth_modifier(), 1.3);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::event_utility::{EventSettings, EventWeights};
+use crate::faction_utility::Faction;
+use crate::victory_utility::VictoryCondition;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    Standard,
+    Chaos,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Standard => "Standard",
+            GameMode::Chaos => "Chaos",
+        }
+    }
+
+    pub fn next(&self) -> GameMode {
+        match self {
+            GameMode::Classic => GameMode::Standard,
+            GameMode::Standard => GameMode::Chaos,
+            GameMode::Chaos => GameMode::Classic,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name.to_lowercase().as_str() {
+            "classic" => Some(GameMode::Classic),
+            "standard" => Some(GameMode::Standard),
+            "chaos" => Some(GameMode::Chaos),
+            _ => None,
+        }
+    }
+
+    pub fn get_event_settings(&self) -> EventSettings {
+        match self {
+            GameMode::Classic => EventSettings {
+                enabled: false,
+                interval_ticks: 0,
+                chance: 0.0,
+                weights: EventWeights {
+                    plague: 0,
+                    famine: 0,
+                    boom: 0,
+                    fire: 0,
+                },
+            },
+            GameMode::Standard => EventSettings {
+                enabled: true,
+                interval_ticks: 1800,
+                chance: 0.5,
+                weights: EventWeights {
+                    plague: 2,
+                    famine: 2,
+                    boom: 3,
+                    fire: 3,
+                },
+            },
+            GameMode::Chaos => EventSettings {
+                enabled: true,
+                interval_ticks: 600,
+                chance: 0.8,
+                weights: EventWeights {
+                    plague: 3,
+                    famine: 3,
+                    boom: 2,
+                    fire: 2,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TeamMode {
+    FreeForAll,
+    TwoVsTwo,
+    ThreeVsThree,
+}
+
+impl TeamMode {
+    pub fn name(&self) -> &str {
+        match self {
+            TeamMode::FreeForAll => "Free-for-all",
+            TeamMode::TwoVsTwo => "2v2",
+            TeamMode::ThreeVsThree => "3v3",
+        }
+    }
+
+    pub fn next(&self) -> TeamMode {
+        match self {
+            TeamMode::FreeForAll => TeamMode::TwoVsTwo,
+            TeamMode::TwoVsTwo => TeamMode::ThreeVsThree,
+            TeamMode::ThreeVsThree => TeamMode::FreeForAll,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TeamMode> {
+        match name.to_lowercase().as_str() {
+            "ffa" => Some(TeamMode::FreeForAll),
+            "2v2" => Some(TeamMode::TwoVsTwo),
+            "3v3" => Some(TeamMode::ThreeVsThree),
+            _ => None,
+        }
+    }
+
+    pub fn get_team_size(&self) -> usize {
+        match self {
+            TeamMode::FreeForAll => 1,
+            TeamMode::TwoVsTwo => 2,
+            TeamMode::ThreeVsThree => 3,
+        }
+    }
+
+    // Team games are always played by two full teams, free-for-all keeps the chosen count.
+    pub fn get_number_of_players(&self, number_of_players: usize) -> usize {
+        match self {
+            TeamMode::FreeForAll => number_of_players,
+            _ => self.get_team_size() * 2,
+        }
+    }
+
+    pub fn get_team(&self, player: usize) -> usize {
+        return player / self.get_team_size();
+    }
+}
+
+pub const MIN_NUMBER_OF_PLAYERS: usize = 2;
+pub const MAX_NUMBER_OF_PLAYERS: usize = 8;
+
+pub const DEFAULT_GAME_AREA_WIDTH: usize = 8;
+pub const DEFAULT_GAME_AREA_HEIGHT: usize = 4;
+pub const MIN_GAME_AREA_SIZE: usize = 3;
+pub const MAX_GAME_AREA_SIZE: usize = 32;
+// Map sizes the host can cycle through in the lobby.
+pub const MAP_SIZES: [(usize, usize); 5] = [(8, 4), (12, 6), (16, 8), (24, 12), (32, 16)];
+
+pub const DEFAULT_PLAYER_NAME: &str = "Player";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub game_mode: GameMode,
+    pub team_mode: TeamMode,
+    pub faction: Faction,
+    pub victory_condition: VictoryCondition,
+    pub number_of_players: usize,
+    // Players below this index are controlled by people, the rest by the AI.
+    pub number_of_humans: usize,
+    // AI name as accepted by `ai::get_ai` for every AI controlled player.
+    pub opponent: String,
+    pub game_area_width: usize,
+    pub game_area_height: usize,
+    pub seed: Option<u64>,
+    // Factions picked in a network lobby, by seat. They replace `faction` and the random
+    // factions of the first seats.
+    #[serde(default)]
+    pub human_factions: Vec<Faction>,
+    // Options below only concern this machine and are not sent to other players.
+    #[serde(skip)]
+    pub api_port: Option<u16>,
+    #[serde(skip)]
+    pub export_prefix: Option<String>,
+    #[serde(skip)]
+    pub host_port: Option<u16>,
+    #[serde(skip)]
+    pub join_address: Option<String>,
+    #[serde(skip)]
+    pub player_name: String,
+    #[serde(skip)]
+    pub room: String,
+    #[serde(skip)]
+    pub rejoin_token: Option<u64>,
+}
+
+pub fn get_next_map_size(map_size: (usize, usize)) -> (usize, usize) {
+    match MAP_SIZES
+        .iter()
+        .position(|&other_map_size| other_map_size == map_size)
+    {
+        Some(index) => MAP_SIZES[(index + 1) % MAP_SIZES.len()],
+        None => MAP_SIZES[0],
+    }
+}
+
+pub fn get_next_number_of_players(number_of_players: usize) -> usize {
+    if number_of_players >= MAX_NUMBER_OF_PLAYERS {
+        return MIN_NUMBER_OF_PLAYERS;
+    } else {
+        return number_of_players + 1;
+    }
+}
+
+// Parses a map size written as WIDTHxHEIGHT, e.g. "8x4".
+pub fn parse_map_size(map_size: &str) -> Option<(usize, usize)> {
+    let map_size: String = map_size.to_lowercase();
+    let mut dimensions = map_size
+        .split('x')
+        .map(|dimension| dimension.parse::<usize>());
+    match (dimensions.next(), dimensions.next(), dimensions.next()) {
+        (Some(Ok(game_area_width)), Some(Ok(game_area_height)), None) => Some((
+            game_area_width.clamp(MIN_GAME_AREA_SIZE, MAX_GAME_AREA_SIZE),
+            game_area_height.clamp(MIN_GAME_AREA_SIZE, MAX_GAME_AREA_SIZE),
+        )),
+        _ => None,
+    }
+}
+
+pub fn parse_arguments(arguments: Vec<String>) -> GameSettings {
+    let mut game_settings: GameSettings = GameSettings {
+        game_mode: GameMode::Standard,
+        team_mode: TeamMode::FreeForAll,
+        faction: Faction::Kingdom,
+        victory_condition: VictoryCondition::Conquest,
+        number_of_players: MIN_NUMBER_OF_PLAYERS,
+        number_of_humans: 1,
+        opponent: "random".to_string(),
+        game_area_width: DEFAULT_GAME_AREA_WIDTH,
+        game_area_height: DEFAULT_GAME_AREA_HEIGHT,
+        seed: None,
+        human_factions: Vec::new(),
+        api_port: None,
+        export_prefix: None,
+        host_port: None,
+        join_address: None,
+        player_name: DEFAULT_PLAYER_NAME.to_string(),
+        room: String::new(),
+        rejoin_token: None,
+    };
+
+    let mut i: usize = 1;
+    while i < arguments.len() {
+        match arguments[i].as_str() {
+            "--seed" if i + 1 < arguments.len() => {
+                game_settings.seed = arguments[i + 1].parse::<u64>().ok();
+                i += 1;
+            }
+            "--players" if i + 1 < arguments.len() => {
+                if let Ok(number_of_players) = arguments[i + 1].parse::<usize>() {
+                    game_settings.number_of_players =
+                        number_of_players.clamp(MIN_NUMBER_OF_PLAYERS, MAX_NUMBER_OF_PLAYERS);
+                }
+                i += 1;
+            }
+            "--mode" if i + 1 < arguments.len() => {
+                if let Some(game_mode) = GameMode::from_name(&arguments[i + 1]) {
+                    game_settings.game_mode = game_mode;
+                }
+                i += 1;
+            }
+            "--teams" if i + 1 < arguments.len() => {
+                if let Some(team_mode) = TeamMode::from_name(&arguments[i + 1]) {
+                    game_settings.team_mode = team_mode;
+                }
+                i += 1;
+            }
+            "--faction" if i + 1 < arguments.len() => {
+                if let Some(faction) = Faction::from_name(&arguments[i + 1]) {
+                    game_settings.faction = faction;
+                }
+                i += 1;
+            }
+            "--victory" if i + 1 < arguments.len() => {
+                if let Some(victory_condition) = VictoryCondition::from_name(&arguments[i + 1]) {
+                    game_settings.victory_condition = victory_condition;
+                }
+                i += 1;
+            }
+            "--map" if i + 1 < arguments.len() => {
+                if let Some((game_area_width, game_area_height)) = parse_map_size(&arguments[i + 1])
+                {
+                    game_settings.game_area_width = game_area_width;
+                    game_settings.game_area_height = game_area_height;
+                }
+                i += 1;
+            }
+            "--opponent" if i + 1 < arguments.len() => {
+                game_settings.opponent = arguments[i + 1].clone();
+                i += 1;
+            }
+            "--api" if i + 1 < arguments.len() => {
+                game_settings.api_port = arguments[i + 1].parse::<u16>().ok();
+                i += 1;
+            }
+            "--export" if i + 1 < arguments.len() => {
+                game_settings.export_prefix = Some(arguments[i + 1].clone());
+                i += 1;
+            }
+            "--host" if i + 1 < arguments.len() => {
+                game_settings.host_port = arguments[i + 1].parse::<u16>().ok();
+                i += 1;
+            }
+            "--join" if i + 1 < arguments.len() => {
+                game_settings.join_address = Some(arguments[i + 1].clone());
+                i += 1;
+            }
+            "--name" if i + 1 < arguments.len() => {
+                game_settings.player_name = arguments[i + 1].clone();
+                i += 1;
+            }
+            "--room" if i + 1 < arguments.len() => {
+                game_settings.room = arguments[i + 1].clone();
+                i += 1;
+            }
+            "--rejoin" if i + 1 < arguments.len() => {
+                game_settings.rejoin_token = u64::from_str_radix(&arguments[i + 1], 16).ok();
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    game_settings.number_of_players = game_settings
+        .team_mode
+        .get_number_of_players(game_settings.number_of_players);
+    return game_settings;
+}
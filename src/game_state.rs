@@ -0,0 +1,1235 @@
+use crate::ai::{self, Action, Ai};
+use crate::desync_utility;
+use crate::diplomacy_utility;
+use crate::economy_utility;
+use crate::event_utility;
+use crate::export_utility;
+use crate::faction_utility;
+use crate::game_settings::{GameSettings, TeamMode};
+use crate::happiness_utility;
+use crate::match_log;
+use crate::migration_utility;
+use crate::population_utility;
+use crate::replay;
+use crate::statistics_utility;
+use crate::tech_utility;
+use crate::tile_utility;
+use crate::victory_utility;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub const TILE_OWNER_CHANGE_PRICE: f64 = 1500.0;
+pub const TILE_IMPROVEMENT_BASE_COST: f64 = 1000.0;
+pub const TILE_POPULATION_CHANGE_BASE: f64 = 100.0;
+
+// Upkeep, loan amounts and the bankruptcy limit are in cash units, rates in parts per million.
+pub const TILE_UPKEEP_PER_LEVEL: i64 = economy_utility::CASH_UNITS_PER_COIN / 4;
+pub const BANKRUPTCY_CASH_LIMIT: i64 = -10000 * economy_utility::CASH_UNITS_PER_COIN;
+pub const DEBT_SHRINKING_TILES: usize = 3;
+pub const DEBT_POPULATION_SHRINK_RATIO: f64 = 0.002;
+
+pub const LOAN_STEP: i64 = 1000 * economy_utility::CASH_UNITS_PER_COIN;
+pub const LOAN_LIMIT_PER_CITIZEN: i64 = 2 * economy_utility::CASH_UNITS_PER_COIN;
+pub const LOAN_INTEREST_RATE: i64 = 50;
+pub const LOAN_REPAYMENT_SHARE: i64 = 500_000;
+pub const DEPOSIT_INTEREST_RATE: i64 = 20;
+
+pub const MIGRATION_INTERVAL_TICKS: u64 = 60;
+pub const MIGRATION_RATIO: f64 = 0.02;
+pub const REFUGEE_RATIO: f64 = 0.25;
+
+pub const HAPPINESS_INTERVAL_TICKS: u64 = 60;
+pub const HAPPINESS_RECOVERY_RATE: f64 = 2.0;
+pub const CONQUEST_HAPPINESS: f64 = 20.0;
+pub const OVERCROWDING_LEVEL: i32 = 7;
+pub const OVERCROWDING_PENALTY: f64 = 8.0;
+pub const DISTANCE_FROM_CAPITAL_PENALTY: f64 = 4.0;
+pub const REVOLT_HAPPINESS_THRESHOLD: f64 = 25.0;
+pub const REVOLT_CHANCE: f64 = 0.05;
+
+pub const ANNOUNCEMENT_DURATION_TICKS: u64 = 300;
+
+pub const AI_RESEARCH_CASH_RESERVE: f64 = 2.0;
+
+pub const DIPLOMACY_INTERVAL_TICKS: u64 = 600;
+pub const TRIBUTE_AMOUNT: f64 = 1000.0;
+pub const AI_TRIBUTE_STRENGTH_RATIO: f64 = 0.5;
+
+pub const TEAM_TRANSFER_AMOUNT: f64 = 1000.0;
+pub const AI_TEAM_TRANSFER_CASH_RESERVE: f64 = 5000.0;
+
+pub struct GameState {
+    pub game_settings: GameSettings,
+    pub game_area_width: usize,
+    pub game_area_height: usize,
+    pub tile_owned_by: Vec<Vec<i32>>,
+    pub tile_improvement_cost: Vec<Vec<f64>>,
+    pub tile_improvement_level: Vec<Vec<i32>>,
+    pub tile_population_number: Vec<Vec<i32>>,
+    pub tile_happiness: Vec<Vec<f64>>,
+    previous_tile_owned_by: Vec<Vec<i32>>,
+    // In hundredths, see `economy_utility::CASH_UNITS_PER_COIN`. Read it with `get_cash`.
+    pub players_cash: Vec<i64>,
+    pub players_upkeep: Vec<i64>,
+    pub players_loan: Vec<i64>,
+    pub players_loan_limit: Vec<i64>,
+    pub players_research: Vec<tech_utility::Research>,
+    pub players_faction: Vec<faction_utility::Faction>,
+    pub players_human: Vec<bool>,
+    pub players_eliminated: Vec<bool>,
+    pub players_statistics: Vec<statistics_utility::PlayerStatistics>,
+    pub samples: Vec<Vec<statistics_utility::PlayerSample>>,
+    pub team_hold_ticks: Vec<u64>,
+    pub players_goodwill: Vec<Vec<f64>>,
+    pub diplomacy: Vec<Vec<diplomacy_utility::DiplomaticState>>,
+    pub diplomatic_proposals: Vec<diplomacy_utility::Proposal>,
+    pub is_win: bool,
+    pub winner_team: usize,
+    pub tick: u64,
+    pub seed: u64,
+    pub(crate) event_rng: StdRng,
+    ai_rng: StdRng,
+    pub active_events: Vec<event_utility::ActiveEvent>,
+    pub match_log: Vec<match_log::MatchLogEntry>,
+    pub action_log: Vec<match_log::ActionLogEntry>,
+    pub command_log: Vec<replay::ReplayCommand>,
+    pub seat_changes: Vec<replay::SeatChange>,
+    pub chat_log: Vec<replay::ChatMessage>,
+    pub checkpoints: Vec<desync_utility::StateDump>,
+    pub announcement: String,
+    pub announcement_expires_at_tick: u64,
+}
+
+impl GameState {
+    pub fn new(game_settings: GameSettings) -> GameState {
+        let game_area_width: usize = game_settings.game_area_width;
+        let game_area_height: usize = game_settings.game_area_height;
+        let mut game_state = GameState {
+            game_settings,
+            game_area_width,
+            game_area_height,
+            tile_owned_by: vec![vec![0; game_area_height]; game_area_width],
+            tile_improvement_cost: vec![vec![0.0; game_area_height]; game_area_width],
+            tile_improvement_level: vec![vec![0; game_area_height]; game_area_width],
+            tile_population_number: vec![vec![0; game_area_height]; game_area_width],
+            tile_happiness: vec![vec![0.0; game_area_height]; game_area_width],
+            previous_tile_owned_by: Vec::new(),
+            players_cash: Vec::new(),
+            players_upkeep: Vec::new(),
+            players_loan: Vec::new(),
+            players_loan_limit: Vec::new(),
+            players_research: Vec::new(),
+            players_faction: Vec::new(),
+            players_human: Vec::new(),
+            players_eliminated: Vec::new(),
+            players_statistics: Vec::new(),
+            samples: Vec::new(),
+            team_hold_ticks: Vec::new(),
+            players_goodwill: Vec::new(),
+            diplomacy: Vec::new(),
+            diplomatic_proposals: Vec::new(),
+            is_win: false,
+            winner_team: 0,
+            tick: 0,
+            seed: 0,
+            event_rng: StdRng::seed_from_u64(0),
+            ai_rng: StdRng::seed_from_u64(0),
+            active_events: Vec::new(),
+            match_log: Vec::new(),
+            action_log: Vec::new(),
+            command_log: Vec::new(),
+            seat_changes: Vec::new(),
+            chat_log: Vec::new(),
+            checkpoints: Vec::new(),
+            announcement: String::new(),
+            announcement_expires_at_tick: 0,
+        };
+        game_state.reset();
+        return game_state;
+    }
+
+    pub fn reset(&mut self) {
+        let number_of_players: usize = self.game_settings.number_of_players;
+
+        for i in 0..self.game_area_width {
+            for j in 0..self.game_area_height {
+                self.tile_owned_by[i][j] = tile_utility::get_starting_owner(
+                    i,
+                    j,
+                    self.game_area_width,
+                    self.game_area_height,
+                    number_of_players,
+                );
+                self.tile_improvement_cost[i][j] = TILE_IMPROVEMENT_BASE_COST;
+                self.tile_improvement_level[i][j] = 0;
+                self.tile_population_number[i][j] = 1;
+                self.tile_happiness[i][j] = happiness_utility::HAPPINESS_MAX;
+            }
+        }
+        self.previous_tile_owned_by = self.tile_owned_by.clone();
+
+        self.players_human = (0..number_of_players)
+            .map(|player| player < self.game_settings.number_of_humans)
+            .collect();
+        self.players_cash = vec![0; number_of_players];
+        self.players_upkeep = vec![0; number_of_players];
+        self.players_loan = vec![0; number_of_players];
+        self.players_loan_limit = vec![0; number_of_players];
+        self.players_research = (0..number_of_players)
+            .map(|_| tech_utility::new_research())
+            .collect();
+        self.players_eliminated = vec![false; number_of_players];
+        self.players_statistics = statistics_utility::new_statistics(number_of_players);
+        self.team_hold_ticks =
+            vec![0; number_of_players / self.game_settings.team_mode.get_team_size()];
+        self.players_goodwill = vec![vec![0.0; number_of_players]; number_of_players];
+        self.diplomacy = diplomacy_utility::new_diplomacy(number_of_players);
+        for player in 0..number_of_players {
+            for other_player in 0..number_of_players {
+                if player != other_player && self.is_teammate(player, other_player) {
+                    diplomacy_utility::set_diplomatic_state(
+                        &mut self.diplomacy,
+                        player,
+                        other_player,
+                        diplomacy_utility::DiplomaticState::Alliance,
+                    );
+                }
+            }
+        }
+        self.diplomatic_proposals.clear();
+
+        let seed: u64 = match self.game_settings.seed {
+            Some(seed) => seed,
+            None => rand::random(),
+        };
+        self.seed = seed;
+        self.event_rng = StdRng::seed_from_u64(seed);
+        self.ai_rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+        self.players_faction = vec![self.game_settings.faction];
+        for _ in 1..number_of_players {
+            self.players_faction.push(
+                faction_utility::FACTIONS
+                    [self.event_rng.gen_range(0, faction_utility::FACTIONS.len())],
+            );
+        }
+        // Drawn above anyway, so the rest of the random sequence does not depend on the lobby.
+        for (player, faction) in self.game_settings.human_factions.iter().enumerate() {
+            if player < number_of_players {
+                self.players_faction[player] = *faction;
+            }
+        }
+        self.active_events.clear();
+        self.match_log.clear();
+        self.action_log.clear();
+        self.command_log.clear();
+        self.seat_changes.clear();
+        self.chat_log.clear();
+        self.checkpoints.clear();
+        self.announcement = String::new();
+        self.announcement_expires_at_tick = 0;
+
+        self.is_win = false;
+        self.winner_team = number_of_players;
+        self.tick = 0;
+        self.samples.clear();
+        self.record_sample();
+
+        match_log::add_entry(
+            &mut self.match_log,
+            self.tick,
+            self.game_settings.game_mode.name().to_string()
+                + " match started with seed "
+                + &seed.to_string(),
+        );
+    }
+
+    pub fn get_improvement_cost(&self, player: i32, improvement_level: i32) -> f64 {
+        let mut improvement_cost: f64 = TILE_IMPROVEMENT_BASE_COST
+            * (improvement_level + 1) as f64
+            * event_utility::get_cost_multiplier(player, &self.active_events);
+        if player >= 0 {
+            improvement_cost *= tech_utility::get_improvement_cost_modifier(
+                &self.players_research[player as usize],
+            ) * self.players_faction[player as usize]
+                .get_improvement_cost_modifier();
+        }
+        return improvement_cost;
+    }
+
+    pub fn get_owner_change_cost(&self, player: i32, improvement_level: i32) -> f64 {
+        return TILE_OWNER_CHANGE_PRICE
+            * (improvement_level + 1) as f64
+            * event_utility::get_cost_multiplier(player, &self.active_events)
+            * tech_utility::get_owner_change_cost_modifier(
+                &self.players_research[player as usize],
+            )
+            * self.players_faction[player as usize].get_owner_change_cost_modifier();
+    }
+
+    pub fn get_max_settlement_level(&self, player: i32) -> i32 {
+        if player >= 0 {
+            return tech_utility::get_max_settlement_level(&self.players_research[player as usize]);
+        } else {
+            return tech_utility::BASE_MAX_SETTLEMENT_LEVEL;
+        }
+    }
+
+    pub fn get_team(&self, player: usize) -> usize {
+        return self.game_settings.team_mode.get_team(player);
+    }
+
+    pub fn is_teammate(&self, player: usize, other_player: usize) -> bool {
+        return self.get_team(player) == self.get_team(other_player);
+    }
+
+    pub fn get_team_players(&self, team: usize) -> Vec<i32> {
+        return (0..self.players_cash.len())
+            .filter(|&player| self.get_team(player) == team)
+            .map(|player| player as i32)
+            .collect();
+    }
+
+    pub fn get_team_name(&self, team: usize) -> String {
+        if self.game_settings.team_mode == TeamMode::FreeForAll {
+            return "Player ".to_string() + &team.to_string();
+        } else {
+            return "Team ".to_string() + &team.to_string();
+        }
+    }
+
+    pub fn get_active_players(&self) -> Vec<usize> {
+        return (0..self.players_eliminated.len())
+            .filter(|&player| !self.players_eliminated[player])
+            .collect();
+    }
+
+    pub fn get_active_teams(&self) -> Vec<usize> {
+        let mut active_teams: Vec<usize> = self
+            .get_active_players()
+            .into_iter()
+            .map(|player| self.get_team(player))
+            .collect();
+        active_teams.dedup();
+        return active_teams;
+    }
+
+    pub fn get_total_population(&self, player: usize) -> i32 {
+        return population_utility::get_total_population(
+            player as i32,
+            &self.tile_population_number,
+            &self.tile_owned_by,
+            self.game_area_width,
+            self.game_area_height,
+        );
+    }
+
+    pub fn get_number_of_tiles(&self, player: usize) -> usize {
+        return tile_utility::get_number_of_tiles(
+            &self.tile_owned_by,
+            self.game_area_width,
+            self.game_area_height,
+            player as i32,
+        );
+    }
+
+    pub fn get_team_statistics(&self, team: usize) -> (i32, f64, usize) {
+        let mut total_population: i32 = 0;
+        let mut cash: f64 = 0.0;
+        let mut number_of_tiles: usize = 0;
+        for player in self.get_team_players(team) {
+            let player: usize = player as usize;
+            if self.players_eliminated[player] {
+                continue;
+            }
+            total_population += self.get_total_population(player);
+            cash += self.get_cash(player);
+            number_of_tiles += self.get_number_of_tiles(player);
+        }
+        return (total_population, cash, number_of_tiles);
+    }
+
+    pub fn get_score(&self, player: usize) -> f64 {
+        if self.players_eliminated[player] {
+            return 0.0;
+        }
+        return victory_utility::get_score(
+            self.get_total_population(player),
+            self.get_cash(player),
+            self.get_number_of_tiles(player),
+        );
+    }
+
+    pub fn get_victory_progress(&self, team: usize) -> f64 {
+        let (total_population, cash, number_of_tiles) = self.get_team_statistics(team);
+        return victory_utility::get_progress(
+            self.game_settings.victory_condition,
+            total_population,
+            cash,
+            number_of_tiles,
+            self.game_area_width * self.game_area_height,
+            self.team_hold_ticks[team],
+            self.tick,
+        );
+    }
+
+    pub(crate) fn check_victory(&mut self) {
+        let victory_condition: victory_utility::VictoryCondition =
+            self.game_settings.victory_condition;
+        let (hill_x, hill_y) =
+            victory_utility::get_center_tile(self.game_area_width, self.game_area_height);
+        for team in 0..self.team_hold_ticks.len() {
+            let is_holding: bool = match victory_condition {
+                victory_utility::VictoryCondition::Territory => {
+                    let (_, _, number_of_tiles) = self.get_team_statistics(team);
+                    number_of_tiles as f64 / (self.game_area_width * self.game_area_height) as f64
+                        >= victory_utility::TERRITORY_SHARE
+                }
+                victory_utility::VictoryCondition::KingOfTheHill => self
+                    .get_team_players(team)
+                    .contains(&self.tile_owned_by[hill_x][hill_y]),
+                _ => false,
+            };
+            self.team_hold_ticks[team] =
+                victory_utility::update_hold_ticks(self.team_hold_ticks[team], is_holding);
+        }
+
+        let active_teams: Vec<usize> = self.get_active_teams();
+        if victory_condition == victory_utility::VictoryCondition::TimeLimit {
+            if self.tick >= victory_utility::TIME_LIMIT_TICKS {
+                let mut best_score: f64 = -1.0;
+                for team in active_teams {
+                    let (total_population, cash, number_of_tiles) = self.get_team_statistics(team);
+                    let score: f64 =
+                        victory_utility::get_score(total_population, cash, number_of_tiles);
+                    if score > best_score {
+                        best_score = score;
+                        self.winner_team = team;
+                    }
+                }
+                self.is_win = true;
+            }
+            return;
+        }
+
+        for team in active_teams {
+            if self.get_victory_progress(team) >= 1.0 {
+                self.is_win = true;
+                self.winner_team = team;
+                return;
+            }
+        }
+    }
+
+    pub fn get_cash(&self, player: usize) -> f64 {
+        return economy_utility::from_cash_units(self.players_cash[player]);
+    }
+
+    pub fn can_afford(&self, player: usize, amount: f64) -> bool {
+        return self.players_cash[player] >= economy_utility::to_cash_units(amount);
+    }
+
+    // Every change is rounded to whole units, so no error builds up over a match.
+    fn add_cash(&mut self, player: usize, amount: f64) {
+        self.players_cash[player] += economy_utility::to_cash_units(amount);
+    }
+
+    pub fn get_strength(&self, player: usize) -> f64 {
+        return self.get_total_population(player) as f64 + self.get_cash(player).max(0.0);
+    }
+
+    pub fn can_attack_tile(&self, player: usize, tile_x: usize, tile_y: usize) -> bool {
+        return diplomacy_utility::can_attack(
+            &self.diplomacy,
+            player,
+            self.tile_owned_by[tile_x][tile_y],
+        ) && tile_utility::has_adjacent_friendly_tile(
+            &self.tile_owned_by,
+            tile_x as i32,
+            tile_y as i32,
+            self.game_area_width,
+            self.game_area_height,
+            &diplomacy_utility::get_friendly_players(&self.diplomacy, player),
+        );
+    }
+
+    pub fn get_action_cost(&self, player: usize, action: Action) -> f64 {
+        match action {
+            Action::Improve(tile_x, tile_y) => self
+                .get_improvement_cost(player as i32, self.tile_improvement_level[tile_x][tile_y]),
+            Action::Capture(tile_x, tile_y) => self
+                .get_owner_change_cost(player as i32, self.tile_improvement_level[tile_x][tile_y]),
+            Action::Pass => 0.0,
+        }
+    }
+
+    pub fn is_legal_action(&self, player: usize, action: Action) -> bool {
+        match action {
+            Action::Improve(tile_x, tile_y) => {
+                tile_x < self.game_area_width
+                    && tile_y < self.game_area_height
+                    && self.tile_owned_by[tile_x][tile_y] == player as i32
+            }
+            Action::Capture(tile_x, tile_y) => {
+                tile_x < self.game_area_width
+                    && tile_y < self.game_area_height
+                    && self.can_attack_tile(player, tile_x, tile_y)
+            }
+            Action::Pass => true,
+        }
+    }
+
+    pub fn apply_action(&mut self, player: usize, action: Action) -> bool {
+        if !self.is_legal_action(player, action) {
+            return false;
+        }
+        match action {
+            Action::Improve(tile_x, tile_y) => return self.improve_tile(player, tile_x, tile_y),
+            Action::Capture(tile_x, tile_y) => return self.attack_tile(player, tile_x, tile_y),
+            Action::Pass => return true,
+        }
+    }
+
+    pub fn improve_tile(&mut self, player: usize, tile_x: usize, tile_y: usize) -> bool {
+        let improvement_cost: f64 =
+            self.get_improvement_cost(player as i32, self.tile_improvement_level[tile_x][tile_y]);
+        if self.tile_owned_by[tile_x][tile_y] != player as i32
+            || !self.can_afford(player, improvement_cost)
+        {
+            return false;
+        }
+
+        self.add_cash(player, -improvement_cost);
+        self.players_statistics[player].total_spending += improvement_cost;
+        self.log_action(
+            player,
+            "improve",
+            "",
+            Some((tile_x, tile_y)),
+            None,
+            improvement_cost,
+        );
+        self.tile_population_number[tile_x][tile_y] += (TILE_POPULATION_CHANGE_BASE
+            * (self.tile_improvement_level[tile_x][tile_y] + 1) as f64
+            * self.players_faction[player].get_population_growth_modifier())
+            as i32;
+        return true;
+    }
+
+    pub fn attack_tile(&mut self, player: usize, tile_x: usize, tile_y: usize) -> bool {
+        let owner_change_cost: f64 =
+            self.get_owner_change_cost(player as i32, self.tile_improvement_level[tile_x][tile_y]);
+        if !self.can_attack_tile(player, tile_x, tile_y)
+            || !self.can_afford(player, owner_change_cost)
+        {
+            return false;
+        }
+
+        self.add_cash(player, -owner_change_cost);
+        self.players_statistics[player].total_spending += owner_change_cost;
+        let defender: i32 = self.tile_owned_by[tile_x][tile_y];
+        self.log_action(
+            player,
+            "attack",
+            "",
+            Some((tile_x, tile_y)),
+            if defender == tile_utility::NEUTRAL_OWNER {
+                None
+            } else {
+                Some(defender as usize)
+            },
+            owner_change_cost,
+        );
+        let population_loss: i32 =
+            TILE_POPULATION_CHANGE_BASE as i32 * (self.tile_improvement_level[tile_x][tile_y] + 1);
+        let refugees: i32 = (self.tile_population_number[tile_x][tile_y].min(population_loss)
+            as f64
+            * REFUGEE_RATIO) as i32;
+        migration_utility::flee_population(
+            &mut self.tile_population_number,
+            &self.tile_owned_by,
+            tile_x,
+            tile_y,
+            self.game_area_width,
+            self.game_area_height,
+            refugees,
+        );
+        self.tile_population_number[tile_x][tile_y] -= population_loss;
+        if self.tile_population_number[tile_x][tile_y] < 0 {
+            self.tile_owned_by[tile_x][tile_y] = player as i32;
+            self.tile_population_number[tile_x][tile_y] = 1;
+            self.tile_happiness[tile_x][tile_y] = CONQUEST_HAPPINESS;
+        }
+        return true;
+    }
+
+    pub fn take_loan(&mut self, player: usize) -> bool {
+        let loan: i64 = economy_utility::get_available_loan(
+            self.players_loan[player],
+            self.players_loan_limit[player],
+        )
+        .min(LOAN_STEP);
+        if loan <= 0 {
+            return false;
+        }
+        self.players_loan[player] += loan;
+        self.players_cash[player] += loan;
+        self.log_action(
+            player,
+            "loan",
+            "",
+            None,
+            None,
+            economy_utility::from_cash_units(loan),
+        );
+        return true;
+    }
+
+    pub fn start_research(&mut self, player: usize, technology: tech_utility::Technology) -> bool {
+        let cash: f64 = self.get_cash(player);
+        if tech_utility::start_research(&mut self.players_research[player], technology, cash) {
+            self.add_cash(player, -technology.research_cost());
+            self.players_statistics[player].total_spending += technology.research_cost();
+            self.log_action(
+                player,
+                "research",
+                technology.name(),
+                None,
+                None,
+                technology.research_cost(),
+            );
+            return true;
+        }
+        return false;
+    }
+
+    pub fn transfer_cash(&mut self, from: usize, to: usize, amount: f64) -> bool {
+        if from == to
+            || !self.is_teammate(from, to)
+            || self.players_eliminated[to]
+            || !self.can_afford(from, amount)
+        {
+            return false;
+        }
+        self.add_cash(from, -amount);
+        self.add_cash(to, amount);
+        self.log_action(from, "transfer", "", None, Some(to), amount);
+        self.announce(
+            "Player ".to_string()
+                + &from.to_string()
+                + " transfers "
+                + &amount.to_string()
+                + " to teammate Player "
+                + &to.to_string(),
+        );
+        return true;
+    }
+
+    pub fn send_tribute(&mut self, from: usize, to: usize, amount: f64) -> bool {
+        if !self.can_afford(from, amount) || self.players_eliminated[to] {
+            return false;
+        }
+        self.add_cash(from, -amount);
+        self.add_cash(to, amount);
+        self.players_goodwill[to][from] += amount;
+        self.players_statistics[from].total_spending += amount;
+        self.log_action(from, "tribute", "", None, Some(to), amount);
+        self.announce(
+            "Player ".to_string()
+                + &from.to_string()
+                + " sends "
+                + &amount.to_string()
+                + " tribute to Player "
+                + &to.to_string(),
+        );
+        return true;
+    }
+
+    pub fn set_diplomatic_state(
+        &mut self,
+        player: usize,
+        other_player: usize,
+        state: diplomacy_utility::DiplomaticState,
+    ) {
+        diplomacy_utility::set_diplomatic_state(&mut self.diplomacy, player, other_player, state);
+        self.log_action(
+            player,
+            "diplomacy",
+            state.name(),
+            None,
+            Some(other_player),
+            0.0,
+        );
+        self.diplomatic_proposals.retain(|proposal| {
+            !(proposal.from == player && proposal.to == other_player
+                || proposal.from == other_player && proposal.to == player)
+        });
+        self.announce(
+            "Player ".to_string()
+                + &player.to_string()
+                + " and Player "
+                + &other_player.to_string()
+                + ": "
+                + state.name(),
+        );
+    }
+
+    pub fn propose(&mut self, from: usize, to: usize, state: diplomacy_utility::DiplomaticState) {
+        if from == to
+            || self.players_eliminated[to]
+            || self.is_teammate(from, to)
+            || self.diplomacy[from][to] == state
+        {
+            return;
+        }
+        if state == diplomacy_utility::DiplomaticState::War {
+            self.set_diplomatic_state(from, to, state);
+        } else if self.players_human[to] {
+            self.diplomatic_proposals.push(diplomacy_utility::Proposal {
+                from,
+                to,
+                state,
+                expires_at_tick: self.tick + diplomacy_utility::PROPOSAL_DURATION_TICKS,
+            });
+            self.announce(
+                "Player ".to_string()
+                    + &from.to_string()
+                    + " proposes "
+                    + state.name()
+                    + " (Y: accept, N: reject)",
+            );
+        } else if diplomacy_utility::evaluate_proposal(
+            self.diplomacy[from][to],
+            state,
+            self.get_strength(to),
+            self.get_strength(from),
+            self.players_goodwill[to][from],
+            self.get_active_players().len(),
+        ) {
+            self.set_diplomatic_state(from, to, state);
+        } else {
+            self.announce(
+                "Player ".to_string()
+                    + &to.to_string()
+                    + " rejects "
+                    + state.name()
+                    + " with Player "
+                    + &from.to_string(),
+            );
+        }
+    }
+
+    pub fn answer_proposal(&mut self, player: usize, is_accepted: bool) {
+        if let Some(index) = self
+            .diplomatic_proposals
+            .iter()
+            .position(|proposal| proposal.to == player)
+        {
+            let proposal: diplomacy_utility::Proposal = self.diplomatic_proposals.remove(index);
+            if is_accepted {
+                self.set_diplomatic_state(proposal.from, proposal.to, proposal.state);
+            }
+        }
+    }
+
+    fn eliminate_player(&mut self, player: usize, reason: &str) {
+        self.players_eliminated[player] = true;
+        for i in 0..self.game_area_width {
+            for j in 0..self.game_area_height {
+                if self.tile_owned_by[i][j] == player as i32 {
+                    self.tile_owned_by[i][j] = tile_utility::NEUTRAL_OWNER;
+                }
+            }
+        }
+        self.diplomatic_proposals
+            .retain(|proposal| proposal.from != player && proposal.to != player);
+        self.announce("Player ".to_string() + &player.to_string() + " " + reason);
+    }
+
+    fn take_ai_diplomacy_turn(&mut self, player: usize) {
+        let other_players: Vec<usize> = self
+            .get_active_players()
+            .into_iter()
+            .filter(|&other_player| other_player != player)
+            .collect();
+        if other_players.is_empty() {
+            return;
+        }
+
+        let other_player: usize = other_players[self.ai_rng.gen_range(0, other_players.len())];
+        let own_strength: f64 = self.get_strength(player);
+        let other_strength: f64 = self.get_strength(other_player);
+        if let Some(state) = diplomacy_utility::get_desired_state(
+            self.diplomacy[player][other_player],
+            own_strength,
+            other_strength,
+            other_players.len() + 1,
+        ) {
+            if state != diplomacy_utility::DiplomaticState::War
+                && own_strength < other_strength * AI_TRIBUTE_STRENGTH_RATIO
+            {
+                self.send_tribute(player, other_player, TRIBUTE_AMOUNT);
+            }
+            self.propose(player, other_player, state);
+        }
+    }
+
+    // The economy decisions are shared by every AI, the tile action comes from `ai`.
+    fn take_ai_turn(&mut self, player: usize, ai: &mut dyn Ai) -> bool {
+        if self.get_cash(player) > AI_TEAM_TRANSFER_CASH_RESERVE {
+            let teammate_in_debt: Option<usize> =
+                self.get_active_players().into_iter().find(|&other_player| {
+                    other_player != player
+                        && self.is_teammate(player, other_player)
+                        && self.players_cash[other_player] < 0
+                });
+            if let Some(teammate) = teammate_in_debt {
+                self.transfer_cash(player, teammate, TEAM_TRANSFER_AMOUNT);
+            }
+        }
+
+        let ai_technology: tech_utility::Technology =
+            tech_utility::TECHNOLOGIES[self.ai_rng.gen_range(0, tech_utility::TECHNOLOGIES.len())];
+        if self.get_cash(player) >= ai_technology.research_cost() * AI_RESEARCH_CASH_RESERVE {
+            self.start_research(player, ai_technology);
+        }
+
+        let action: Action = ai.choose_action(self, player);
+        if !self.is_legal_action(player, action) {
+            return false;
+        }
+        let ai_loan: i64 = economy_utility::get_loan_for_purchase(
+            self.players_cash[player],
+            economy_utility::to_cash_units(self.get_action_cost(player, action)),
+            self.players_loan[player],
+            self.players_loan_limit[player],
+        );
+        // The loan is only booked when the purchase it pays for goes through.
+        self.players_cash[player] += ai_loan;
+        let is_applied: bool = self.apply_action(player, action);
+        if !is_applied {
+            self.players_cash[player] -= ai_loan;
+        } else if ai_loan > 0 {
+            self.players_loan[player] += ai_loan;
+            self.log_action(
+                player,
+                "loan",
+                "",
+                None,
+                None,
+                economy_utility::from_cash_units(ai_loan),
+            );
+        }
+        let is_improvement: bool = matches!(action, Action::Improve(_, _));
+        return is_applied && is_improvement;
+    }
+
+    pub fn update_tile_levels(&mut self) {
+        for i in 0..self.game_area_width {
+            for j in 0..self.game_area_height {
+                self.tile_improvement_level[i][j] =
+                    population_utility::get_level_of_settlement(self.tile_population_number[i][j])
+                        .min(self.get_max_settlement_level(self.tile_owned_by[i][j]));
+                self.tile_improvement_cost[i][j] = self.get_improvement_cost(
+                    self.tile_owned_by[i][j],
+                    self.tile_improvement_level[i][j],
+                );
+            }
+        }
+    }
+
+    fn update_economy(&mut self) {
+        let mut last_eliminated_player: Option<usize> = None;
+        for i in 0..self.players_cash.len() {
+            if self.players_eliminated[i] {
+                continue;
+            }
+
+            let total_population: i32 = self.get_total_population(i);
+            statistics_utility::record_population(
+                &mut self.players_statistics[i],
+                total_population,
+            );
+            let max_level_of_settlement: i32 = self.get_max_settlement_level(i as i32);
+            let income: f64 = population_utility::get_cash(
+                happiness_utility::get_content_population(
+                    i as i32,
+                    &self.tile_population_number,
+                    &self.tile_owned_by,
+                    &self.tile_happiness,
+                    self.game_area_width,
+                    self.game_area_height,
+                ),
+                max_level_of_settlement,
+            );
+            let income: f64 = income
+                * event_utility::get_income_multiplier(i as i32, &self.active_events)
+                * tech_utility::get_income_modifier(&self.players_research[i])
+                * self.players_faction[i].get_income_modifier(
+                    population_utility::get_level_of_settlement(total_population)
+                        .min(max_level_of_settlement),
+                );
+            let income: i64 = economy_utility::to_cash_units(income);
+            let loan_repayment: i64 = economy_utility::get_loan_repayment(
+                self.players_loan[i],
+                income,
+                LOAN_REPAYMENT_SHARE,
+            );
+            self.players_cash[i] += income - loan_repayment;
+            self.players_loan[i] -= loan_repayment;
+            self.players_loan[i] +=
+                economy_utility::get_loan_interest(self.players_loan[i], LOAN_INTEREST_RATE);
+            self.players_cash[i] +=
+                economy_utility::get_deposit_interest(self.players_cash[i], DEPOSIT_INTEREST_RATE);
+            self.players_loan_limit[i] =
+                economy_utility::get_loan_limit(total_population, LOAN_LIMIT_PER_CITIZEN);
+
+            self.players_upkeep[i] = economy_utility::get_upkeep(
+                i as i32,
+                &self.tile_improvement_level,
+                &self.tile_owned_by,
+                self.game_area_width,
+                self.game_area_height,
+                TILE_UPKEEP_PER_LEVEL,
+            );
+            self.players_cash[i] -= self.players_upkeep[i];
+
+            if self.players_cash[i] < 0 {
+                economy_utility::shrink_largest_tiles(
+                    i as i32,
+                    &mut self.tile_population_number,
+                    &self.tile_owned_by,
+                    self.game_area_width,
+                    self.game_area_height,
+                    DEBT_SHRINKING_TILES,
+                    DEBT_POPULATION_SHRINK_RATIO,
+                );
+            }
+
+            if economy_utility::is_player_bankrupt(self.players_cash[i], BANKRUPTCY_CASH_LIMIT) {
+                self.eliminate_player(i, "went bankrupt");
+                last_eliminated_player = Some(i);
+            } else if self.get_number_of_tiles(i) == 0 {
+                self.eliminate_player(i, "lost every tile");
+                last_eliminated_player = Some(i);
+            }
+
+            let team: usize = self.get_team(i);
+            if tile_utility::is_team_wins(
+                &self.tile_owned_by,
+                self.game_area_width,
+                self.game_area_height,
+                &self.get_team_players(team),
+            ) {
+                self.is_win = true;
+                self.winner_team = team;
+            }
+        }
+
+        let active_teams: Vec<usize> = self.get_active_teams();
+        if active_teams.len() == 1 {
+            self.is_win = true;
+            self.winner_team = active_teams[0];
+        }
+        // When the last players fall in the same pass, the team eliminated last wins.
+        if let Some(player) = last_eliminated_player {
+            if active_teams.is_empty() && !self.is_win {
+                self.is_win = true;
+                self.winner_team = self.get_team(player);
+            }
+        }
+        if !self.is_win {
+            self.check_victory();
+        }
+    }
+
+    fn update_world(&mut self) {
+        if self.tick.is_multiple_of(MIGRATION_INTERVAL_TICKS) {
+            migration_utility::migrate_population(
+                &mut self.tile_population_number,
+                &self.tile_owned_by,
+                &self.tile_improvement_level,
+                self.game_area_width,
+                self.game_area_height,
+                MIGRATION_RATIO,
+            );
+        }
+
+        let event_settings: event_utility::EventSettings =
+            self.game_settings.game_mode.get_event_settings();
+        event_utility::remove_expired_events(&mut self.active_events, self.tick);
+        if event_settings.enabled
+            && self.tick.is_multiple_of(event_settings.interval_ticks)
+            && self.event_rng.gen_bool(event_settings.chance)
+        {
+            if let Some(kind) =
+                event_utility::choose_event(&event_settings.weights, &mut self.event_rng)
+            {
+                let active_players: Vec<usize> = self.get_active_players();
+                if !active_players.is_empty() {
+                    let player: i32 =
+                        active_players[self.event_rng.gen_range(0, active_players.len())] as i32;
+                    let message: String = event_utility::fire_event(self, kind, player);
+                    self.announce(message);
+                }
+            }
+        }
+
+        if self.tick.is_multiple_of(HAPPINESS_INTERVAL_TICKS) {
+            let happiness_rules: happiness_utility::HappinessRules =
+                happiness_utility::HappinessRules {
+                    overcrowding_level: OVERCROWDING_LEVEL,
+                    overcrowding_penalty: OVERCROWDING_PENALTY,
+                    distance_penalty: DISTANCE_FROM_CAPITAL_PENALTY,
+                    recovery_rate: HAPPINESS_RECOVERY_RATE,
+                };
+            happiness_utility::update_happiness(
+                &mut self.tile_happiness,
+                &self.tile_population_number,
+                &self.tile_owned_by,
+                &self.tile_improvement_level,
+                self.game_area_width,
+                self.game_area_height,
+                &happiness_rules,
+            );
+            happiness_utility::revolt(
+                &mut self.tile_owned_by,
+                &mut self.tile_happiness,
+                self.game_area_width,
+                self.game_area_height,
+                REVOLT_HAPPINESS_THRESHOLD,
+                REVOLT_CHANCE,
+                &mut self.event_rng,
+            );
+        }
+
+        for i in 0..self.players_research.len() {
+            if let Some(technology) = tech_utility::advance_research(&mut self.players_research[i])
+            {
+                self.announce(
+                    "Player ".to_string() + &i.to_string() + " researched " + technology.name(),
+                );
+            }
+        }
+
+        diplomacy_utility::remove_expired_proposals(&mut self.diplomatic_proposals, self.tick);
+    }
+
+    // Hands a human seat to the opponent AI or back, for players who lost their connection.
+    // The change is recorded for the replay like a command.
+    pub fn set_ai_controlled(
+        &mut self,
+        ais: &mut Vec<Option<Box<dyn Ai>>>,
+        player: usize,
+        is_ai: bool,
+    ) -> Result<(), String> {
+        if player >= ais.len() || ais[player].is_some() == is_ai {
+            return Ok(());
+        }
+        let ai: Option<Box<dyn Ai>> = if is_ai {
+            Some(ai::get_opponent_ai(self, player)?)
+        } else {
+            None
+        };
+        self.seat_changes.push(replay::SeatChange {
+            tick: self.tick,
+            player,
+            is_ai,
+        });
+        ais[player] = ai;
+        return Ok(());
+    }
+
+    // Runs one tick of the simulation. `ais` holds the controller of every player slot, `None`
+    // for humans whose actions are applied between steps. Returns whether an AI improved a tile.
+    pub fn step(&mut self, ais: &mut Vec<Option<Box<dyn Ai>>>) -> bool {
+        if self.is_win {
+            return false;
+        }
+
+        self.tick += 1;
+        self.update_economy();
+        self.update_world();
+        self.update_tile_levels();
+
+        let mut is_ai_improved: bool = false;
+        for player in 0..self.players_cash.len() {
+            if self.players_eliminated[player] {
+                continue;
+            }
+            if let Some(ai) = ais[player].as_mut() {
+                is_ai_improved |= self.take_ai_turn(player, ai.as_mut());
+                if self.tick.is_multiple_of(DIPLOMACY_INTERVAL_TICKS) {
+                    self.take_ai_diplomacy_turn(player);
+                }
+            }
+        }
+
+        if self
+            .tick
+            .is_multiple_of(statistics_utility::SAMPLE_INTERVAL_TICKS)
+        {
+            self.record_sample();
+        }
+        if self
+            .tick
+            .is_multiple_of(desync_utility::CHECKPOINT_INTERVAL_TICKS)
+        {
+            self.checkpoints.push(desync_utility::get_state_dump(self));
+        }
+
+        statistics_utility::record_ownership_changes(
+            &mut self.players_statistics,
+            &self.previous_tile_owned_by,
+            &self.tile_owned_by,
+            self.game_area_width,
+            self.game_area_height,
+        );
+        self.previous_tile_owned_by = self.tile_owned_by.clone();
+        return is_ai_improved;
+    }
+
+    fn record_sample(&mut self) {
+        let sample: Vec<statistics_utility::PlayerSample> = (0..self.players_cash.len())
+            .map(|player| statistics_utility::PlayerSample {
+                population: self.get_total_population(player),
+                cash: self.get_cash(player),
+                number_of_tiles: self.get_number_of_tiles(player),
+            })
+            .collect();
+        self.samples.push(sample);
+    }
+
+    fn log_action(
+        &mut self,
+        player: usize,
+        action: &str,
+        detail: &str,
+        tile: Option<(usize, usize)>,
+        target_player: Option<usize>,
+        amount: f64,
+    ) {
+        self.action_log.push(match_log::ActionLogEntry {
+            tick: self.tick,
+            player,
+            action: action.to_string(),
+            detail: detail.to_string(),
+            x: tile.map(|(tile_x, _)| tile_x),
+            y: tile.map(|(_, tile_y)| tile_y),
+            target_player,
+            amount,
+        });
+    }
+
+    pub fn get_match_export(&self) -> export_utility::MatchExport {
+        return export_utility::MatchExport {
+            schema_version: export_utility::EXPORT_SCHEMA_VERSION,
+            seed: self.seed,
+            game_mode: self.game_settings.game_mode.name().to_string(),
+            team_mode: self.game_settings.team_mode.name().to_string(),
+            victory_condition: self.game_settings.victory_condition.name().to_string(),
+            ticks: self.tick,
+            winner_team: self.winner_team,
+            players: (0..self.players_cash.len())
+                .map(|player| export_utility::PlayerExport {
+                    player,
+                    team: self.get_team(player),
+                    faction: self.players_faction[player].name().to_string(),
+                    eliminated: self.players_eliminated[player],
+                    score: self.get_score(player),
+                    statistics: self.players_statistics[player],
+                })
+                .collect(),
+            samples: export_utility::get_sample_exports(&self.samples),
+            actions: self.action_log.clone(),
+        };
+    }
+
+    pub fn announce(&mut self, message: String) {
+        match_log::add_entry(&mut self.match_log, self.tick, message.clone());
+        self.announcement = message;
+        self.announcement_expires_at_tick = self.tick + ANNOUNCEMENT_DURATION_TICKS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_settings;
+    use crate::test_utility;
+
+    #[test]
+    fn match_ends_when_the_last_players_fall_together() {
+        let mut game_state: GameState = GameState::new(game_settings::parse_arguments(Vec::new()));
+        let number_of_players: usize = game_state.players_cash.len();
+        for player in 0..number_of_players {
+            game_state.players_cash[player] = BANKRUPTCY_CASH_LIMIT * 2;
+        }
+        game_state.update_economy();
+
+        assert!(game_state.get_active_teams().is_empty());
+        assert!(game_state.is_win);
+        assert_eq!(
+            game_state.winner_team,
+            game_state.get_team(number_of_players - 1)
+        );
+    }
+
+    // Player 0's cash after one economy update with a population of level 13 and the given
+    // technologies.
+    fn get_cash_after_income(technologies: &[tech_utility::Technology]) -> i64 {
+        let mut game_settings: GameSettings = game_settings::parse_arguments(Vec::new());
+        game_settings.seed = Some(1);
+        let mut game_state: GameState = GameState::new(game_settings);
+        game_state.players_research[0].researched = technologies.to_vec();
+        for i in 0..game_state.game_area_width {
+            for j in 0..game_state.game_area_height {
+                if game_state.tile_owned_by[i][j] == 0 {
+                    game_state.tile_population_number[i][j] = 0;
+                }
+            }
+        }
+        let (tile_x, tile_y) = test_utility::get_own_tile(&game_state, 0).unwrap();
+        game_state.tile_population_number[tile_x][tile_y] = 60000000;
+        game_state.update_economy();
+        return game_state.players_cash[0];
+    }
+
+    #[test]
+    fn technologies_change_costs_and_income() {
+        let mut game_state: GameState = GameState::new(game_settings::parse_arguments(Vec::new()));
+        // Costs are compared in cash units, which is how they are paid.
+        let get_costs = |game_state: &GameState| -> (i64, i64) {
+            return (
+                economy_utility::to_cash_units(game_state.get_improvement_cost(0, 2)),
+                economy_utility::to_cash_units(game_state.get_owner_change_cost(0, 2)),
+            );
+        };
+        let (improvement_cost, owner_change_cost) = get_costs(&game_state);
+        assert_eq!(
+            game_state.get_max_settlement_level(0),
+            tech_utility::BASE_MAX_SETTLEMENT_LEVEL
+        );
+
+        game_state.players_research[0].researched = vec![tech_utility::Technology::Masonry];
+        assert_eq!(
+            get_costs(&game_state),
+            (improvement_cost * 3 / 4, owner_change_cost)
+        );
+
+        game_state.players_research[0].researched = vec![tech_utility::Technology::Siegecraft];
+        assert_eq!(
+            get_costs(&game_state),
+            (improvement_cost, owner_change_cost * 3 / 4)
+        );
+
+        game_state.players_research[0].researched = vec![tech_utility::Technology::UrbanPlanning];
+        assert_eq!(
+            game_state.get_max_settlement_level(0),
+            tech_utility::URBAN_PLANNING_MAX_SETTLEMENT_LEVEL
+        );
+
+        // Without Urban Planning the level 13 population earns the income of level 12.
+        let base_cash: i64 = get_cash_after_income(&[]);
+        assert!(get_cash_after_income(&[tech_utility::Technology::Commerce]) > base_cash);
+        assert!(get_cash_after_income(&[tech_utility::Technology::UrbanPlanning]) > base_cash);
+    }
+}
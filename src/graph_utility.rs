@@ -0,0 +1,84 @@
+use crate::statistics_utility::PlayerSample;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GraphKind {
+    Population,
+    Cash,
+    Territory,
+}
+
+pub const GRAPH_KINDS: [GraphKind; 3] =
+    [GraphKind::Population, GraphKind::Cash, GraphKind::Territory];
+
+impl GraphKind {
+    pub fn name(&self) -> &str {
+        match self {
+            GraphKind::Population => "Population",
+            GraphKind::Cash => "Cash",
+            GraphKind::Territory => "Tiles",
+        }
+    }
+
+    pub fn get_value(&self, sample: &PlayerSample) -> f64 {
+        match self {
+            GraphKind::Population => sample.population as f64,
+            GraphKind::Cash => sample.cash,
+            GraphKind::Territory => sample.number_of_tiles as f64,
+        }
+    }
+}
+
+// Cycles through the graphs and then hides the panel.
+pub fn get_next_graph_kind(graph_kind: Option<GraphKind>) -> Option<GraphKind> {
+    match graph_kind {
+        None => Some(GraphKind::Population),
+        Some(GraphKind::Population) => Some(GraphKind::Cash),
+        Some(GraphKind::Cash) => Some(GraphKind::Territory),
+        Some(GraphKind::Territory) => None,
+    }
+}
+
+pub fn get_series(
+    samples: &Vec<Vec<PlayerSample>>,
+    player: usize,
+    graph_kind: GraphKind,
+) -> Vec<f64> {
+    return samples
+        .iter()
+        .map(|sample| graph_kind.get_value(&sample[player]))
+        .collect();
+}
+
+pub fn get_value_range(samples: &Vec<Vec<PlayerSample>>, graph_kind: GraphKind) -> (f64, f64) {
+    let mut minimum: f64 = 0.0;
+    let mut maximum: f64 = 1.0;
+    for sample in samples {
+        for player_sample in sample {
+            let value: f64 = graph_kind.get_value(player_sample);
+            minimum = minimum.min(value);
+            maximum = maximum.max(value);
+        }
+    }
+    return (minimum, maximum);
+}
+
+pub fn get_graph_points(
+    values: &Vec<f64>,
+    value_range: (f64, f64),
+    origin: (f32, f32),
+    size: (f32, f32),
+) -> Vec<(f32, f32)> {
+    let (minimum, maximum) = value_range;
+    let steps: f32 = (values.len().max(2) - 1) as f32;
+    return values
+        .iter()
+        .enumerate()
+        .map(|(k, value)| {
+            let ratio: f32 = ((value - minimum) / (maximum - minimum).max(1.0)) as f32;
+            (
+                origin.0 + size.0 * k as f32 / steps,
+                origin.1 + size.1 * (1.0 - ratio),
+            )
+        })
+        .collect();
+}
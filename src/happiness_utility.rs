@@ -0,0 +1,228 @@
+use crate::tile_utility;
+use rand::Rng;
+
+pub const HAPPINESS_MAX: f64 = 100.0;
+
+// How quickly tiles get unhappy from overcrowding and distance, and how quickly they recover.
+pub struct HappinessRules {
+    pub overcrowding_level: i32,
+    pub overcrowding_penalty: f64,
+    pub distance_penalty: f64,
+    pub recovery_rate: f64,
+}
+
+pub fn get_capital(
+    player: i32,
+    tile_population_number: &Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+) -> Option<(usize, usize)> {
+    let mut capital: Option<(usize, usize)> = None;
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                match capital {
+                    Some((x, y))
+                        if tile_population_number[x][y] >= tile_population_number[i][j] => {}
+                    _ => capital = Some((i, j)),
+                }
+            }
+        }
+    }
+    return capital;
+}
+
+pub fn get_target_happiness(
+    improvement_level: i32,
+    distance_from_capital: usize,
+    happiness_rules: &HappinessRules,
+) -> f64 {
+    let overcrowding: f64 = (improvement_level - happiness_rules.overcrowding_level).max(0) as f64;
+    let happiness: f64 = HAPPINESS_MAX
+        - overcrowding * happiness_rules.overcrowding_penalty
+        - distance_from_capital as f64 * happiness_rules.distance_penalty;
+    return happiness.clamp(0.0, HAPPINESS_MAX);
+}
+
+pub fn update_happiness(
+    tile_happiness: &mut Vec<Vec<f64>>,
+    tile_population_number: &Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    tile_improvement_level: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    happiness_rules: &HappinessRules,
+) {
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == tile_utility::NEUTRAL_OWNER {
+                tile_happiness[i][j] = HAPPINESS_MAX;
+                continue;
+            }
+
+            let distance_from_capital: usize = match get_capital(
+                tile_owned_by[i][j],
+                tile_population_number,
+                tile_owned_by,
+                game_area_width,
+                game_area_height,
+            ) {
+                Some((x, y)) => {
+                    (x as i32 - i as i32).unsigned_abs() as usize
+                        + (y as i32 - j as i32).unsigned_abs() as usize
+                }
+                None => 0,
+            };
+            let target_happiness: f64 = get_target_happiness(
+                tile_improvement_level[i][j],
+                distance_from_capital,
+                happiness_rules,
+            );
+
+            if tile_happiness[i][j] < target_happiness {
+                tile_happiness[i][j] =
+                    (tile_happiness[i][j] + happiness_rules.recovery_rate).min(target_happiness);
+            } else {
+                tile_happiness[i][j] =
+                    (tile_happiness[i][j] - happiness_rules.recovery_rate).max(target_happiness);
+            }
+        }
+    }
+}
+
+pub fn get_content_population(
+    player: i32,
+    tile_population_number: &Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    tile_happiness: &Vec<Vec<f64>>,
+    game_area_width: usize,
+    game_area_height: usize,
+) -> i32 {
+    let mut content_population: f64 = 0.0;
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                content_population +=
+                    tile_population_number[i][j] as f64 * tile_happiness[i][j] / HAPPINESS_MAX;
+            }
+        }
+    }
+    return content_population as i32;
+}
+
+pub fn get_revolt_owner<R: Rng>(
+    tile_owned_by: &Vec<Vec<i32>>,
+    tile_x: usize,
+    tile_y: usize,
+    game_area_width: usize,
+    game_area_height: usize,
+    rng: &mut R,
+) -> i32 {
+    let neighbor_owners: Vec<i32> =
+        tile_utility::get_adjacent_tiles(tile_x, tile_y, game_area_width, game_area_height)
+            .into_iter()
+            .map(|(x, y)| tile_owned_by[x][y])
+            .filter(|&owner| {
+                owner != tile_utility::NEUTRAL_OWNER && owner != tile_owned_by[tile_x][tile_y]
+            })
+            .collect();
+    if neighbor_owners.is_empty() || rng.gen_bool(0.5) {
+        return tile_utility::NEUTRAL_OWNER;
+    } else {
+        return neighbor_owners[rng.gen_range(0, neighbor_owners.len())];
+    }
+}
+
+pub fn revolt<R: Rng>(
+    tile_owned_by: &mut Vec<Vec<i32>>,
+    tile_happiness: &mut Vec<Vec<f64>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    revolt_threshold: f64,
+    revolt_chance: f64,
+    rng: &mut R,
+) {
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] != tile_utility::NEUTRAL_OWNER
+                && tile_happiness[i][j] < revolt_threshold
+                && rng.gen_bool(revolt_chance)
+            {
+                tile_owned_by[i][j] =
+                    get_revolt_owner(tile_owned_by, i, j, game_area_width, game_area_height, rng);
+                tile_happiness[i][j] = HAPPINESS_MAX / 2.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{
+        CONQUEST_HAPPINESS, DISTANCE_FROM_CAPITAL_PENALTY, HAPPINESS_RECOVERY_RATE,
+        OVERCROWDING_LEVEL, OVERCROWDING_PENALTY, REVOLT_CHANCE, REVOLT_HAPPINESS_THRESHOLD,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const GAME_AREA_WIDTH: usize = 8;
+    const GAME_AREA_HEIGHT: usize = 1;
+
+    #[test]
+    fn crowded_conquered_tile_revolts_and_content_tile_does_not() {
+        let happiness_rules: HappinessRules = HappinessRules {
+            overcrowding_level: OVERCROWDING_LEVEL,
+            overcrowding_penalty: OVERCROWDING_PENALTY,
+            distance_penalty: DISTANCE_FROM_CAPITAL_PENALTY,
+            recovery_rate: HAPPINESS_RECOVERY_RATE,
+        };
+        // Player 0 holds its capital at 0, a small tile next to it at 1 and a crowded,
+        // just conquered tile at the far end of the row.
+        let mut tile_owned_by: Vec<Vec<i32>> =
+            vec![vec![tile_utility::NEUTRAL_OWNER]; GAME_AREA_WIDTH];
+        tile_owned_by[0][0] = 0;
+        tile_owned_by[1][0] = 0;
+        tile_owned_by[7][0] = 0;
+        let mut tile_population_number: Vec<Vec<i32>> = vec![vec![10]; GAME_AREA_WIDTH];
+        tile_population_number[0][0] = 1000;
+        let mut tile_improvement_level: Vec<Vec<i32>> = vec![vec![0]; GAME_AREA_WIDTH];
+        tile_improvement_level[7][0] = 14;
+        let mut tile_happiness: Vec<Vec<f64>> = vec![vec![HAPPINESS_MAX]; GAME_AREA_WIDTH];
+        tile_happiness[7][0] = CONQUEST_HAPPINESS;
+
+        for _ in 0..50 {
+            update_happiness(
+                &mut tile_happiness,
+                &tile_population_number,
+                &tile_owned_by,
+                &tile_improvement_level,
+                GAME_AREA_WIDTH,
+                GAME_AREA_HEIGHT,
+                &happiness_rules,
+            );
+        }
+        assert!(tile_happiness[7][0] < REVOLT_HAPPINESS_THRESHOLD);
+        assert!(tile_happiness[1][0] >= REVOLT_HAPPINESS_THRESHOLD);
+
+        let mut rng: StdRng = StdRng::seed_from_u64(1);
+        let mut revolts: usize = 0;
+        while tile_owned_by[7][0] == 0 {
+            assert!(revolts < 1000);
+            revolt(
+                &mut tile_owned_by,
+                &mut tile_happiness,
+                GAME_AREA_WIDTH,
+                GAME_AREA_HEIGHT,
+                REVOLT_HAPPINESS_THRESHOLD,
+                REVOLT_CHANCE,
+                &mut rng,
+            );
+            revolts += 1;
+            assert_eq!(tile_owned_by[0][0], 0);
+            assert_eq!(tile_owned_by[1][0], 0);
+        }
+        assert_eq!(tile_owned_by[7][0], tile_utility::NEUTRAL_OWNER);
+        assert_eq!(tile_happiness[7][0], HAPPINESS_MAX / 2.0);
+    }
+}
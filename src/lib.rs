@@ -0,0 +1,33 @@
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+
+pub mod ai;
+pub mod api_server;
+pub mod bot_protocol;
+pub mod desync_utility;
+pub mod diplomacy_utility;
+pub mod discovery;
+pub mod economy_utility;
+pub mod elo_utility;
+pub mod event_utility;
+pub mod export_utility;
+pub mod faction_utility;
+pub mod game_settings;
+pub mod game_state;
+pub mod graph_utility;
+pub mod happiness_utility;
+pub mod lockstep;
+pub mod match_log;
+pub mod migration_utility;
+pub mod player_command;
+pub mod population_utility;
+pub mod replay;
+pub mod rl_environment;
+pub mod simulation_utility;
+pub mod statistics_utility;
+pub mod tech_utility;
+#[cfg(test)]
+mod test_utility;
+pub mod tile_utility;
+pub mod victory_utility;
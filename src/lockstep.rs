@@ -0,0 +1,1543 @@
+use crate::ai::{self, Ai};
+use crate::desync_utility::{self, StateDump};
+use crate::faction_utility::Faction;
+use crate::game_settings::{self, GameSettings, TeamMode};
+use crate::game_state::GameState;
+use crate::player_command::{self, PlayerCommand};
+use crate::replay::{self, ChatMessage, ReplayCommand, SeatChange};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const LOCKSTEP_PROTOCOL_VERSION: u32 = 4;
+// Commands are played this many ticks after they are given, which hides the round trip to
+// the host on a LAN.
+pub const INPUT_DELAY_TICKS: u64 = 6;
+// Own state dumps kept to compare with checksums that arrive late.
+pub const CHECKPOINT_HISTORY: usize = 32;
+const MAX_HANDSHAKE_LENGTH: usize = 4096;
+// A resync carries every command of the match so far, so this is generous.
+const MAX_MESSAGE_LENGTH: u64 = 16 * 1024 * 1024;
+// Further commands of a player wait for the next tick, or are dropped when a peer sends them.
+pub const MAX_COMMANDS_PER_FRAME: usize = 10;
+pub const MAX_CHAT_LENGTH: usize = 120;
+// Chat lines kept for the lobby screen, the chat of a match is also kept in its replay.
+pub const CHAT_HISTORY: usize = 50;
+// A disconnected player's seat is handed to the AI after 15 seconds.
+pub const DISCONNECT_GRACE_TICKS: u64 = 15 * 60;
+// How often a peer that lost the host during a match tries to connect again.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+// A connection that has not said `Hello` by then is closed.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A seat in the lobby, in the order the seats are given out when the match starts.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LobbyPlayer {
+    pub name: String,
+    pub faction: Faction,
+    pub is_ready: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+}
+
+// One JSON object per line, see docs/multiplayer.md.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum LockstepMessage {
+    Hello {
+        name: String,
+        version: u32,
+        // Only used by the dedicated server, which hosts a separate match for every room.
+        #[serde(default)]
+        room: String,
+        // The token from `Start`, to get the seat back during a match.
+        #[serde(default)]
+        rejoin_token: Option<u64>,
+    },
+    Reject {
+        reason: String,
+    },
+    // The large payloads are boxed to keep every message small.
+    Lobby {
+        players: Vec<LobbyPlayer>,
+        game_settings: Option<Box<GameSettings>>,
+    },
+    Choice {
+        faction: Faction,
+        is_ready: bool,
+    },
+    Chat {
+        name: String,
+        text: String,
+    },
+    Start {
+        match_id: u64,
+        player: usize,
+        game_settings: Box<GameSettings>,
+        rejoin_token: u64,
+    },
+    Commands {
+        match_id: u64,
+        tick: u64,
+        commands: Vec<PlayerCommand>,
+    },
+    Frame {
+        match_id: u64,
+        tick: u64,
+        // Seats handed to the AI or back to their player, applied before the commands.
+        #[serde(default)]
+        seat_changes: Vec<(usize, bool)>,
+        commands: Vec<(usize, PlayerCommand)>,
+        // Chat during a match goes out with the frames, so every peer logs it at the same tick.
+        #[serde(default)]
+        chat: Vec<ChatLine>,
+    },
+    Resync {
+        match_id: u64,
+        player: usize,
+        game_settings: Box<GameSettings>,
+        tick: u64,
+        commands: Vec<ReplayCommand>,
+        seat_changes: Vec<SeatChange>,
+        chat: Vec<ChatMessage>,
+    },
+    Rejected {
+        match_id: u64,
+        tick: u64,
+        command: PlayerCommand,
+        reason: String,
+    },
+    Left {
+        player: usize,
+    },
+    Rejoined {
+        player: usize,
+    },
+    Checksum {
+        match_id: u64,
+        tick: u64,
+        checksum: u64,
+    },
+    Desync {
+        match_id: u64,
+        state: Box<StateDump>,
+    },
+    DesyncState {
+        match_id: u64,
+        state: Box<StateDump>,
+    },
+}
+
+enum LockstepEvent {
+    Connected(TcpStream),
+    Message(usize, LockstepMessage),
+    Disconnected(usize),
+}
+
+struct Peer {
+    connection: usize,
+    name: Option<String>,
+    stream: TcpStream,
+    player: usize,
+    faction: Faction,
+    is_ready: bool,
+    is_connected: bool,
+    is_desynced: bool,
+    connected_at: Instant,
+    // Host only: when the seat lost its player, whether the AI plays it now and the first tick
+    // the player's own commands are waited for again after a rejoin.
+    disconnected_at_tick: u64,
+    is_ai: bool,
+    commands_from_tick: u64,
+    // Random per match, only the player who got it in `Start` can take the seat back.
+    rejoin_token: u64,
+}
+
+struct Frame {
+    seat_changes: Vec<(usize, bool)>,
+    commands: Vec<(usize, PlayerCommand)>,
+    chat: Vec<ChatLine>,
+}
+
+// The history of a running match a peer plays through once after it rejoined.
+struct Resync {
+    game_settings: GameSettings,
+    tick: u64,
+    commands: Vec<ReplayCommand>,
+    seat_changes: Vec<SeatChange>,
+    chat: Vec<ChatMessage>,
+}
+
+// A LAN match where every peer runs the same seeded simulation. Peers only send the commands
+// of their own player for a future tick to the host, the host collects them from every human,
+// drops the ones that break the rules and sends the complete frame back, and a peer steps a
+// tick once it has that tick's frame. A dedicated host has no player of its own. A player who
+// loses the connection can rejoin under the same name and gets the match history to catch up,
+// their seat is played by the AI if they stay away for longer than `DISCONNECT_GRACE_TICKS`.
+pub struct LockstepSession {
+    pub is_host: bool,
+    pub is_dedicated: bool,
+    // Host only: the port the listener is bound to, also when it was started on port 0.
+    pub port: u16,
+    pub local_player: usize,
+    pub local_name: String,
+    pub lobby_players: Vec<LobbyPlayer>,
+    // The host's match settings, shown in the lobby.
+    pub lobby_settings: Option<GameSettings>,
+    pub chat_lines: Vec<ChatLine>,
+    // Host only: chat lines of the match that go out with the next frame.
+    match_chat_lines: Vec<ChatLine>,
+    pub status: String,
+    pub is_in_match: bool,
+    peers: Vec<Peer>,
+    host_stream: Option<TcpStream>,
+    // Peers only: where to reconnect to during a match, cleared when the host rejects us.
+    host_address: Option<String>,
+    room: String,
+    host_connection: usize,
+    reconnected_at: Option<Instant>,
+    rejoin_token: Option<u64>,
+    events: Receiver<LockstepEvent>,
+    event_sender: Sender<LockstepEvent>,
+    next_connection: usize,
+    match_id: u64,
+    started_settings: Option<GameSettings>,
+    match_settings: Option<GameSettings>,
+    tick: u64,
+    rejoining_players: Vec<usize>,
+    resync: Option<Resync>,
+    local_faction: Faction,
+    is_local_ready: bool,
+    local_commands: Vec<PlayerCommand>,
+    last_sent_tick: u64,
+    number_of_humans: usize,
+    pending_commands: BTreeMap<u64, Vec<Option<Vec<PlayerCommand>>>>,
+    frames: BTreeMap<u64, Frame>,
+    checkpoints: BTreeMap<u64, StateDump>,
+    reported_checksums: Vec<(usize, u64, u64)>,
+}
+
+impl LockstepSession {
+    fn new(is_host: bool, local_name: &str) -> LockstepSession {
+        let (event_sender, events) = mpsc::channel();
+        return LockstepSession {
+            is_host,
+            is_dedicated: false,
+            port: 0,
+            local_player: 0,
+            local_name: local_name.to_string(),
+            lobby_players: Vec::new(),
+            lobby_settings: None,
+            chat_lines: Vec::new(),
+            match_chat_lines: Vec::new(),
+            status: String::new(),
+            is_in_match: false,
+            peers: Vec::new(),
+            host_stream: None,
+            host_address: None,
+            room: String::new(),
+            host_connection: 0,
+            reconnected_at: None,
+            rejoin_token: None,
+            events,
+            event_sender,
+            next_connection: 0,
+            match_id: 0,
+            started_settings: None,
+            match_settings: None,
+            tick: 0,
+            rejoining_players: Vec::new(),
+            resync: None,
+            local_faction: Faction::Kingdom,
+            is_local_ready: false,
+            local_commands: Vec::new(),
+            last_sent_tick: 0,
+            number_of_humans: 1,
+            pending_commands: BTreeMap::new(),
+            frames: BTreeMap::new(),
+            checkpoints: BTreeMap::new(),
+            reported_checksums: Vec::new(),
+        };
+    }
+
+    pub fn host(port: u16, local_name: &str) -> io::Result<LockstepSession> {
+        let listener: TcpListener = TcpListener::bind(("0.0.0.0", port))?;
+        let mut session: LockstepSession = LockstepSession::new(true, local_name);
+        session.port = listener.local_addr()?.port();
+        session.status = "Hosting on port ".to_string() + &session.port.to_string();
+        session.update_lobby_players();
+
+        let sender: Sender<LockstepEvent> = session.event_sender.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if sender.send(LockstepEvent::Connected(stream)).is_err() {
+                    break;
+                }
+            }
+        });
+        return Ok(session);
+    }
+
+    // A host without a listener or a seat, peers are handed to it with `add_peer`.
+    pub fn dedicated(local_name: &str) -> LockstepSession {
+        let mut session: LockstepSession = LockstepSession::new(true, local_name);
+        session.is_dedicated = true;
+        return session;
+    }
+
+    // `rejoin_token` gets the seat back in a running match after the game was restarted.
+    pub fn join(
+        address: &str,
+        local_name: &str,
+        room: &str,
+        rejoin_token: Option<u64>,
+    ) -> io::Result<LockstepSession> {
+        let mut session: LockstepSession = LockstepSession::new(false, local_name);
+        session.room = room.to_string();
+        session.rejoin_token = rejoin_token;
+        session.connect(address)?;
+        session.host_address = Some(address.to_string());
+        session.status = "Connected to ".to_string() + address + ", waiting for the host";
+        return Ok(session);
+    }
+
+    fn connect(&mut self, address: &str) -> io::Result<()> {
+        let socket_address: SocketAddr = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "address could not be resolved")
+        })?;
+        let mut stream: TcpStream = TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        write_message(
+            &mut stream,
+            &LockstepMessage::Hello {
+                name: self.local_name.clone(),
+                version: LOCKSTEP_PROTOCOL_VERSION,
+                room: self.room.clone(),
+                rejoin_token: self.rejoin_token,
+            },
+        )?;
+        spawn_reader(
+            stream.try_clone()?,
+            self.next_connection,
+            self.event_sender.clone(),
+            MAX_MESSAGE_LENGTH,
+        );
+        self.host_connection = self.next_connection;
+        self.next_connection += 1;
+        self.host_stream = Some(stream);
+        return Ok(());
+    }
+
+    pub fn get_number_of_connected_players(&self) -> usize {
+        let number_of_peers: usize = self.peers.iter().filter(|peer| peer.is_connected).count();
+        if self.is_dedicated {
+            return number_of_peers;
+        }
+        return 1 + number_of_peers;
+    }
+
+    // Adds a connection whose `Hello` was already read, e.g. by the dedicated server.
+    pub fn add_peer(
+        &mut self,
+        stream: TcpStream,
+        name: String,
+        version: u32,
+        rejoin_token: Option<u64>,
+    ) {
+        if let Some(connection) = self.accept_peer(stream) {
+            self.handle_peer_message(
+                connection,
+                LockstepMessage::Hello {
+                    name,
+                    version,
+                    room: String::new(),
+                    rejoin_token,
+                },
+            );
+        }
+    }
+
+    // Handles everything that arrived since the last call.
+    pub fn poll(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                LockstepEvent::Connected(stream) => {
+                    self.accept_peer(stream);
+                }
+                LockstepEvent::Message(connection, message) => {
+                    if self.is_host {
+                        self.handle_peer_message(connection, message);
+                    } else {
+                        self.handle_host_message(message);
+                    }
+                }
+                LockstepEvent::Disconnected(connection) => {
+                    if self.is_host {
+                        self.remove_peer(connection);
+                    } else if connection == self.host_connection {
+                        self.host_stream = None;
+                        self.status = "Lost the connection to the host".to_string();
+                    }
+                }
+            }
+        }
+        self.expire_handshakes();
+        self.reconnect();
+    }
+
+    // Host only: closes the connections that never sent their `Hello`.
+    fn expire_handshakes(&mut self) {
+        let mut index: usize = 0;
+        while index < self.peers.len() {
+            if self.peers[index].name.is_none()
+                && self.peers[index].connected_at.elapsed() > HANDSHAKE_TIMEOUT
+            {
+                self.drop_peer(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    // Shut down rather than only dropped, the reader thread holds its own handle, see `Drop`.
+    fn drop_peer(&mut self, index: usize) {
+        let peer: Peer = self.peers.remove(index);
+        let _ = peer.stream.shutdown(Shutdown::Both);
+    }
+
+    fn drop_disconnected_peers(&mut self) {
+        let mut index: usize = 0;
+        while index < self.peers.len() {
+            if self.peers[index].is_connected {
+                index += 1;
+            } else {
+                self.drop_peer(index);
+            }
+        }
+    }
+
+    // Peers only: keeps trying to get back into a running match.
+    fn reconnect(&mut self) {
+        if self.is_host || !self.is_in_match || self.host_stream.is_some() {
+            return;
+        }
+        let address: String = match &self.host_address {
+            Some(address) => address.clone(),
+            None => return,
+        };
+        if self
+            .reconnected_at
+            .is_some_and(|reconnected_at| reconnected_at.elapsed() < RECONNECT_INTERVAL)
+        {
+            return;
+        }
+        self.reconnected_at = Some(Instant::now());
+        self.status = match self.connect(&address) {
+            Ok(()) => "Reconnected, waiting for the host".to_string(),
+            Err(error) => {
+                "Lost the connection to the host, reconnecting: ".to_string() + &error.to_string()
+            }
+        };
+    }
+
+    fn accept_peer(&mut self, mut stream: TcpStream) -> Option<usize> {
+        // During a match only players who lost their connection get back in, which is decided
+        // by the rejoin token in their `Hello`.
+        if !self.is_in_match
+            && self.get_number_of_connected_players() >= game_settings::MAX_NUMBER_OF_PLAYERS
+        {
+            let _ = write_message(
+                &mut stream,
+                &LockstepMessage::Reject {
+                    reason: "The match is full".to_string(),
+                },
+            );
+            let _ = stream.shutdown(Shutdown::Both);
+            return None;
+        }
+        let reader_stream: TcpStream = stream.try_clone().ok()?;
+        let _ = stream.set_nodelay(true);
+        // Nothing but a small `Hello` is read from a stranger.
+        spawn_reader(
+            reader_stream,
+            self.next_connection,
+            self.event_sender.clone(),
+            MAX_HANDSHAKE_LENGTH as u64,
+        );
+        self.peers.push(Peer {
+            connection: self.next_connection,
+            name: None,
+            stream,
+            player: 0,
+            faction: Faction::Kingdom,
+            is_ready: false,
+            is_connected: false,
+            is_desynced: false,
+            connected_at: Instant::now(),
+            disconnected_at_tick: 0,
+            is_ai: false,
+            commands_from_tick: 0,
+            rejoin_token: 0,
+        });
+        self.next_connection += 1;
+        return Some(self.next_connection - 1);
+    }
+
+    fn handle_peer_message(&mut self, connection: usize, message: LockstepMessage) {
+        let index: usize = match self
+            .peers
+            .iter()
+            .position(|peer| peer.connection == connection)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        match message {
+            LockstepMessage::Hello {
+                name,
+                version,
+                rejoin_token,
+                ..
+            } => {
+                if version != LOCKSTEP_PROTOCOL_VERSION {
+                    let _ = write_message(
+                        &mut self.peers[index].stream,
+                        &LockstepMessage::Reject {
+                            reason: "Protocol version ".to_string()
+                                + &LOCKSTEP_PROTOCOL_VERSION.to_string()
+                                + " is required",
+                        },
+                    );
+                    self.drop_peer(index);
+                    return;
+                }
+                if self.is_in_match {
+                    self.rejoin_peer(index, rejoin_token);
+                    return;
+                }
+                let is_name_taken: bool = (!self.is_dedicated && name == self.local_name)
+                    || self
+                        .peers
+                        .iter()
+                        .any(|peer| peer.is_connected && peer.name.as_deref() == Some(&name));
+                if is_name_taken {
+                    let _ = write_message(
+                        &mut self.peers[index].stream,
+                        &LockstepMessage::Reject {
+                            reason: "The name ".to_string() + &name + " is already taken",
+                        },
+                    );
+                    self.drop_peer(index);
+                    return;
+                }
+                self.peers[index].name = Some(name);
+                self.peers[index].is_connected = true;
+                self.broadcast_lobby();
+            }
+            LockstepMessage::Choice { faction, is_ready } if !self.is_in_match => {
+                self.peers[index].faction = faction;
+                self.peers[index].is_ready = is_ready;
+                self.broadcast_lobby();
+            }
+            LockstepMessage::Chat { text, .. } => {
+                if let Some(name) = self.peers[index].name.clone() {
+                    self.add_chat_line(name, &text);
+                }
+            }
+            LockstepMessage::Commands {
+                match_id,
+                tick,
+                commands,
+            } if match_id == self.match_id
+                && self.is_in_match
+                && self.peers[index].is_connected =>
+            {
+                let player: usize = self.peers[index].player;
+                self.add_commands(tick, player, commands);
+            }
+            LockstepMessage::Checksum {
+                match_id,
+                tick,
+                checksum,
+            } if match_id == self.match_id && self.is_in_match => {
+                let player: usize = self.peers[index].player;
+                if self.checkpoints.contains_key(&tick) {
+                    self.compare_checksum(player, tick, checksum);
+                } else if self.checkpoints.keys().next_back() < Some(&tick) {
+                    self.reported_checksums.push((player, tick, checksum));
+                }
+            }
+            LockstepMessage::DesyncState { match_id, state } if match_id == self.match_id => {
+                let player: usize = self.peers[index].player;
+                let path: String = get_desync_report_path(state.tick, player) + "_host.json";
+                self.write_desync_report(
+                    &path,
+                    &state,
+                    &("Player ".to_string() + &player.to_string()),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // Host only: a new connection with the rejoin token of a disconnected player takes over
+    // their seat. The match history is sent with the next `advance`, which has the state.
+    fn rejoin_peer(&mut self, index: usize, rejoin_token: Option<u64>) {
+        let seat: usize = match self.peers.iter().position(|peer| {
+            !peer.is_connected && peer.name.is_some() && Some(peer.rejoin_token) == rejoin_token
+        }) {
+            Some(seat) => seat,
+            None => {
+                let _ = write_message(
+                    &mut self.peers[index].stream,
+                    &LockstepMessage::Reject {
+                        reason: "The match has already started".to_string(),
+                    },
+                );
+                self.drop_peer(index);
+                return;
+            }
+        };
+        let peer: Peer = self.peers.remove(index);
+        let seat: usize = if seat > index { seat - 1 } else { seat };
+        self.peers[seat].connection = peer.connection;
+        let _ = self.peers[seat].stream.shutdown(Shutdown::Both);
+        self.peers[seat].stream = peer.stream;
+        let player: usize = self.peers[seat].player;
+        if !self.rejoining_players.contains(&player) {
+            self.rejoining_players.push(player);
+        }
+    }
+
+    // Host only: sends a rejoining player everything needed to rebuild the match up to the
+    // current tick, their commands are waited for again once the input delay has passed.
+    fn resync_peer(&mut self, game_state: &GameState, player: usize) {
+        let match_settings: GameSettings = match &self.match_settings {
+            Some(match_settings) => match_settings.clone(),
+            None => return,
+        };
+        let match_id: u64 = self.match_id;
+        let peer: &mut Peer = match self
+            .peers
+            .iter_mut()
+            .find(|peer| peer.player == player && !peer.is_connected && peer.name.is_some())
+        {
+            Some(peer) => peer,
+            None => return,
+        };
+        let message: LockstepMessage = LockstepMessage::Resync {
+            match_id,
+            player,
+            game_settings: Box::new(match_settings),
+            tick: game_state.tick,
+            commands: game_state.command_log.clone(),
+            seat_changes: game_state.seat_changes.clone(),
+            chat: game_state.chat_log.clone(),
+        };
+        if write_message(&mut peer.stream, &message).is_err() {
+            return;
+        }
+        peer.is_connected = true;
+        peer.is_desynced = false;
+        peer.commands_from_tick = game_state.tick + 1 + INPUT_DELAY_TICKS;
+        self.status = "Player ".to_string() + &player.to_string() + " rejoined the match";
+        self.broadcast(&LockstepMessage::Rejoined { player });
+    }
+
+    fn handle_host_message(&mut self, message: LockstepMessage) {
+        match message {
+            LockstepMessage::Reject { reason } => {
+                self.host_address = None;
+                self.status = reason;
+            }
+            LockstepMessage::Lobby {
+                players,
+                game_settings,
+            } => {
+                self.lobby_players = players;
+                self.lobby_settings = game_settings.map(|game_settings| *game_settings);
+            }
+            LockstepMessage::Chat { name, text } => {
+                self.push_chat_line(ChatLine { name, text });
+            }
+            LockstepMessage::Start {
+                match_id,
+                player,
+                game_settings,
+                rejoin_token,
+            } => {
+                self.start_match(match_id, game_settings.number_of_humans);
+                self.local_player = player;
+                self.started_settings = Some(*game_settings);
+                self.rejoin_token = Some(rejoin_token);
+                self.status = format!(
+                    "Playing as Player {}, after a restart rejoin with --rejoin {:016x}",
+                    player, rejoin_token
+                );
+            }
+            LockstepMessage::Resync {
+                match_id,
+                player,
+                game_settings,
+                tick,
+                commands,
+                seat_changes,
+                chat,
+            } => {
+                self.start_match(match_id, game_settings.number_of_humans);
+                self.local_player = player;
+                self.last_sent_tick = tick + INPUT_DELAY_TICKS;
+                // Starts the match from the menu after a restart, `advance` catches up with the
+                // host.
+                self.started_settings = Some((*game_settings).clone());
+                self.resync = Some(Resync {
+                    game_settings: *game_settings,
+                    tick,
+                    commands,
+                    seat_changes,
+                    chat,
+                });
+                self.status = "Rejoined as Player ".to_string() + &player.to_string();
+            }
+            LockstepMessage::Frame {
+                match_id,
+                tick,
+                seat_changes,
+                commands,
+                chat,
+            } if match_id == self.match_id => {
+                self.frames.insert(
+                    tick,
+                    Frame {
+                        seat_changes,
+                        commands,
+                        chat,
+                    },
+                );
+            }
+            LockstepMessage::Rejected {
+                match_id,
+                command,
+                reason,
+                ..
+            } if match_id == self.match_id => {
+                self.status = format!("{:?} was rejected: {}", command, reason);
+            }
+            LockstepMessage::Left { player } => {
+                self.status = "Player ".to_string() + &player.to_string() + " left the match";
+            }
+            LockstepMessage::Rejoined { player } => {
+                self.status = "Player ".to_string() + &player.to_string() + " rejoined the match";
+            }
+            LockstepMessage::Desync { match_id, state } => {
+                if match_id != self.match_id {
+                    return;
+                }
+                let path: String = get_desync_report_path(state.tick, self.local_player) + ".json";
+                self.write_desync_report(&path, &state, "Host");
+                if let Some(checkpoint) = self.checkpoints.get(&state.tick) {
+                    let message: LockstepMessage = LockstepMessage::DesyncState {
+                        match_id,
+                        state: Box::new(checkpoint.clone()),
+                    };
+                    if let Some(stream) = self.host_stream.as_mut() {
+                        let _ = write_message(stream, &message);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remove_peer(&mut self, connection: usize) {
+        let index: usize = match self
+            .peers
+            .iter()
+            .position(|peer| peer.connection == connection)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        if !self.is_in_match {
+            self.drop_peer(index);
+            self.broadcast_lobby();
+            return;
+        }
+        let player: usize = self.peers[index].player;
+        if self.peers[index].name.is_none() {
+            self.drop_peer(index);
+            return;
+        } else if !self.peers[index].is_connected {
+            // Gone again before the match history was sent.
+            self.rejoining_players
+                .retain(|&rejoining_player| rejoining_player != player);
+            return;
+        }
+
+        // The seat stays in the match without input, the others stop waiting for it until the
+        // player rejoins or the AI takes over.
+        self.peers[index].is_connected = false;
+        self.peers[index].disconnected_at_tick = self.tick;
+        self.status = "Player ".to_string() + &player.to_string() + " left the match";
+        self.broadcast(&LockstepMessage::Left { player });
+    }
+
+    fn broadcast(&mut self, message: &LockstepMessage) {
+        for peer in self.peers.iter_mut().filter(|peer| peer.is_connected) {
+            let _ = write_message(&mut peer.stream, message);
+        }
+    }
+
+    // Host only: the host itself is always ready, it starts the match.
+    fn update_lobby_players(&mut self) {
+        let mut lobby_players: Vec<LobbyPlayer> = Vec::new();
+        if !self.is_dedicated {
+            lobby_players.push(LobbyPlayer {
+                name: self.local_name.clone(),
+                faction: self.local_faction,
+                is_ready: true,
+            });
+        }
+        for peer in self.peers.iter().filter(|peer| peer.is_connected) {
+            lobby_players.push(LobbyPlayer {
+                name: peer.name.clone().unwrap_or_default(),
+                faction: peer.faction,
+                is_ready: peer.is_ready,
+            });
+        }
+        self.lobby_players = lobby_players;
+    }
+
+    fn broadcast_lobby(&mut self) {
+        self.update_lobby_players();
+        self.broadcast(&LockstepMessage::Lobby {
+            players: self.lobby_players.clone(),
+            game_settings: self.lobby_settings.clone().map(Box::new),
+        });
+    }
+
+    pub fn get_player_names(&self) -> Vec<String> {
+        return self
+            .lobby_players
+            .iter()
+            .map(|lobby_player| lobby_player.name.clone())
+            .collect();
+    }
+
+    // Host only: everyone who has to be ready before `start` works.
+    pub fn get_unready_players(&self) -> Vec<String> {
+        return self
+            .lobby_players
+            .iter()
+            .filter(|lobby_player| !lobby_player.is_ready)
+            .map(|lobby_player| lobby_player.name.clone())
+            .collect();
+    }
+
+    // Host only: the settings the match will be started with, sent to everyone in the lobby.
+    pub fn set_lobby_settings(&mut self, game_settings: &GameSettings) {
+        self.lobby_settings = Some(game_settings.clone());
+        self.broadcast_lobby();
+    }
+
+    pub fn is_ready(&self) -> bool {
+        return self.is_local_ready;
+    }
+
+    pub fn set_choice(&mut self, faction: Faction, is_ready: bool) {
+        self.local_faction = faction;
+        self.is_local_ready = is_ready;
+        if self.is_host {
+            self.broadcast_lobby();
+        } else if let Some(stream) = self.host_stream.as_mut() {
+            let _ = write_message(stream, &LockstepMessage::Choice { faction, is_ready });
+        }
+    }
+
+    pub fn send_chat(&mut self, text: &str) {
+        if self.is_host {
+            self.add_chat_line(self.local_name.clone(), text);
+        } else if let Some(stream) = self.host_stream.as_mut() {
+            let _ = write_message(
+                stream,
+                &LockstepMessage::Chat {
+                    name: self.local_name.clone(),
+                    text: text.to_string(),
+                },
+            );
+        }
+    }
+
+    // Host only: names every line after its sender, so a peer cannot speak for someone else.
+    fn add_chat_line(&mut self, name: String, text: &str) {
+        let text: String = text.trim().chars().take(MAX_CHAT_LENGTH).collect();
+        if text.is_empty() {
+            return;
+        }
+        let chat_line: ChatLine = ChatLine { name, text };
+        if self.is_in_match {
+            self.match_chat_lines.push(chat_line);
+            return;
+        }
+        self.broadcast(&LockstepMessage::Chat {
+            name: chat_line.name.clone(),
+            text: chat_line.text.clone(),
+        });
+        self.push_chat_line(chat_line);
+    }
+
+    fn push_chat_line(&mut self, chat_line: ChatLine) {
+        self.chat_lines.push(chat_line);
+        if self.chat_lines.len() > CHAT_HISTORY {
+            self.chat_lines.remove(0);
+        }
+    }
+
+    fn start_match(&mut self, match_id: u64, number_of_humans: usize) {
+        self.match_id = match_id;
+        self.number_of_humans = number_of_humans;
+        self.is_in_match = true;
+        self.is_local_ready = false;
+        self.local_commands.clear();
+        self.last_sent_tick = INPUT_DELAY_TICKS;
+        self.pending_commands.clear();
+        self.frames.clear();
+        self.checkpoints.clear();
+        self.reported_checksums.clear();
+        self.tick = 0;
+        self.match_chat_lines.clear();
+        self.rejoining_players.clear();
+        self.resync = None;
+        for peer in self.peers.iter_mut() {
+            peer.is_desynced = false;
+            peer.is_ai = false;
+            peer.commands_from_tick = 0;
+        }
+    }
+
+    // Host only: seats the host and every connected peer as the first players and sends the
+    // settings to everyone. Free-for-all grows to fit every human, team games have to fit.
+    pub fn start(&mut self, game_settings: &GameSettings) -> Result<GameSettings, String> {
+        let number_of_humans: usize = self.get_number_of_connected_players();
+        if number_of_humans == 0 {
+            return Err("Nobody has joined yet".to_string());
+        }
+        self.update_lobby_players();
+        let unready_players: Vec<String> = self.get_unready_players();
+        if !unready_players.is_empty() {
+            return Err("Not ready: ".to_string() + &unready_players.join(", "));
+        }
+        if ai::is_bot_name(&game_settings.opponent) {
+            return Err("Bots cannot play network matches, pick a built-in opponent".to_string());
+        }
+        if !ai::is_ai_name(&game_settings.opponent) {
+            return Err("Unknown opponent ".to_string() + &game_settings.opponent);
+        }
+        let mut match_settings: GameSettings = game_settings.clone();
+        if match_settings.team_mode == TeamMode::FreeForAll {
+            match_settings.number_of_players =
+                match_settings.number_of_players.max(number_of_humans);
+        } else if number_of_humans > match_settings.number_of_players {
+            return Err(match_settings.team_mode.name().to_string()
+                + " has only "
+                + &match_settings.number_of_players.to_string()
+                + " seats for "
+                + &number_of_humans.to_string()
+                + " players");
+        }
+        match_settings.number_of_humans = number_of_humans;
+        match_settings.human_factions = self
+            .lobby_players
+            .iter()
+            .map(|lobby_player| lobby_player.faction)
+            .collect();
+        match_settings.seed = Some(match_settings.seed.unwrap_or_else(rand::random));
+
+        self.start_match(self.match_id + 1, number_of_humans);
+        self.match_settings = Some(match_settings.clone());
+        self.local_player = 0;
+        self.drop_disconnected_peers();
+        let first_peer_player: usize = if self.is_dedicated { 0 } else { 1 };
+        for (index, peer) in self.peers.iter_mut().enumerate() {
+            peer.player = first_peer_player + index;
+            peer.rejoin_token = rand::random();
+            let _ = write_message(
+                &mut peer.stream,
+                &LockstepMessage::Start {
+                    match_id: self.match_id,
+                    player: peer.player,
+                    game_settings: Box::new(match_settings.clone()),
+                    rejoin_token: peer.rejoin_token,
+                },
+            );
+            // Everyone readies up again for the next match.
+            peer.is_ready = false;
+        }
+        self.update_lobby_players();
+        self.status = "Hosting ".to_string() + &number_of_humans.to_string() + " players";
+        return Ok(match_settings);
+    }
+
+    // Peers only: the settings of a match the host has started, with the local options of
+    // `local_settings` kept.
+    pub fn take_started_settings(&mut self, local_settings: &GameSettings) -> Option<GameSettings> {
+        let mut game_settings: GameSettings = self.started_settings.take()?;
+        keep_local_settings(&mut game_settings, local_settings);
+        return Some(game_settings);
+    }
+
+    pub fn end_match(&mut self) {
+        // The host may already have started the next match.
+        if self.started_settings.is_some() {
+            return;
+        }
+        self.is_in_match = false;
+        self.pending_commands.clear();
+        self.frames.clear();
+        if self.is_host {
+            self.drop_disconnected_peers();
+            self.broadcast_lobby();
+        }
+    }
+
+    // Keeps a dump of the state and compares its checksum with the host's. Peers send theirs
+    // to the host, the host checks the ones that arrived before it reached the tick.
+    fn record_checkpoint(&mut self, game_state: &GameState) {
+        let state_dump: StateDump = desync_utility::get_state_dump(game_state);
+        let (tick, checksum): (u64, u64) = (state_dump.tick, state_dump.checksum);
+        self.checkpoints.insert(tick, state_dump);
+        while self.checkpoints.len() > CHECKPOINT_HISTORY {
+            let oldest_tick: u64 = *self.checkpoints.keys().next().unwrap();
+            self.checkpoints.remove(&oldest_tick);
+        }
+
+        if self.is_host {
+            let reported_checksums: Vec<(usize, u64, u64)> = self
+                .reported_checksums
+                .iter()
+                .filter(|&&(_, reported_tick, _)| reported_tick == tick)
+                .cloned()
+                .collect();
+            self.reported_checksums
+                .retain(|&(_, reported_tick, _)| reported_tick > tick);
+            for (player, _, reported_checksum) in reported_checksums {
+                self.compare_checksum(player, tick, reported_checksum);
+            }
+        } else if let Some(stream) = self.host_stream.as_mut() {
+            let _ = write_message(
+                stream,
+                &LockstepMessage::Checksum {
+                    match_id: self.match_id,
+                    tick,
+                    checksum,
+                },
+            );
+        }
+    }
+
+    // Host only: on the first difference with a peer both sides write a report with both states.
+    fn compare_checksum(&mut self, player: usize, tick: u64, checksum: u64) {
+        let checkpoint: StateDump = match self.checkpoints.get(&tick) {
+            Some(checkpoint) if checkpoint.checksum != checksum => checkpoint.clone(),
+            _ => return,
+        };
+        let match_id: u64 = self.match_id;
+        let peer: &mut Peer = match self
+            .peers
+            .iter_mut()
+            .find(|peer| peer.player == player && peer.is_connected && !peer.is_desynced)
+        {
+            Some(peer) => peer,
+            None => return,
+        };
+        peer.is_desynced = true;
+        let _ = write_message(
+            &mut peer.stream,
+            &LockstepMessage::Desync {
+                match_id,
+                state: Box::new(checkpoint),
+            },
+        );
+        self.status = "Desync with Player ".to_string()
+            + &player.to_string()
+            + " at tick "
+            + &tick.to_string();
+        eprintln!("{}", self.status);
+    }
+
+    fn write_desync_report(&mut self, path: &str, remote: &StateDump, remote_name: &str) {
+        let local: StateDump = match self.checkpoints.get(&remote.tick) {
+            Some(local) => local.clone(),
+            None => return,
+        };
+        let local_name: String = if self.is_dedicated {
+            self.local_name.clone()
+        } else {
+            "Player ".to_string() + &self.local_player.to_string()
+        };
+        self.status = match desync_utility::write_desync_report(
+            path,
+            &local_name,
+            &local,
+            remote_name,
+            remote,
+        ) {
+            Ok(()) => "Desync at tick ".to_string() + &remote.tick.to_string() + ", wrote " + path,
+            Err(error) => "Could not write ".to_string() + path + ": " + &error.to_string(),
+        };
+        eprintln!("{}", self.status);
+    }
+
+    pub fn queue_command(&mut self, command: PlayerCommand) {
+        self.local_commands.push(command);
+    }
+
+    // Only ticks that are not played yet and within the input delay are accepted.
+    fn add_commands(&mut self, tick: u64, player: usize, commands: Vec<PlayerCommand>) {
+        let next_tick: u64 = self.tick + 1;
+        if player >= self.number_of_humans
+            || tick < next_tick
+            || tick > next_tick + INPUT_DELAY_TICKS
+        {
+            return;
+        }
+        let mut commands: Vec<PlayerCommand> = commands;
+        commands.truncate(MAX_COMMANDS_PER_FRAME);
+        let number_of_humans: usize = self.number_of_humans;
+        self.pending_commands
+            .entry(tick)
+            .or_insert_with(|| vec![None; number_of_humans])[player] = Some(commands);
+    }
+
+    fn is_seat_expected(&self, player: usize, tick: u64) -> bool {
+        if !self.is_dedicated && player == self.local_player {
+            return true;
+        }
+        return self.peers.iter().any(|peer| {
+            peer.player == player && peer.is_connected && tick >= peer.commands_from_tick
+        });
+    }
+
+    // Host only: every human's commands for `tick` once all connected seats have sent theirs.
+    // Commands for earlier ticks that arrived too late are dropped.
+    fn take_frame(&mut self, tick: u64) -> Option<Vec<(usize, PlayerCommand)>> {
+        let is_complete: bool = (0..self.number_of_humans).all(|player| {
+            !self.is_seat_expected(player, tick)
+                || self
+                    .pending_commands
+                    .get(&tick)
+                    .is_some_and(|player_commands| player_commands[player].is_some())
+        });
+        if !is_complete {
+            return None;
+        }
+
+        let player_commands: Vec<Option<Vec<PlayerCommand>>> =
+            self.pending_commands.remove(&tick).unwrap_or_default();
+        self.pending_commands = self.pending_commands.split_off(&tick);
+        let mut commands: Vec<(usize, PlayerCommand)> = Vec::new();
+        for (player, player_commands) in player_commands.into_iter().enumerate() {
+            for command in player_commands.unwrap_or_default() {
+                commands.push((player, command));
+            }
+        }
+        return Some(commands);
+    }
+
+    // Host only: hands the seats of players who have been gone for the grace period to the AI,
+    // and back once a rejoined player's commands arrive again.
+    fn take_seat_changes(&mut self, tick: u64) -> Vec<(usize, bool)> {
+        let mut seat_changes: Vec<(usize, bool)> = Vec::new();
+        for peer in self.peers.iter_mut().filter(|peer| peer.name.is_some()) {
+            if !peer.is_connected
+                && !peer.is_ai
+                && tick >= peer.disconnected_at_tick + DISCONNECT_GRACE_TICKS
+            {
+                peer.is_ai = true;
+                seat_changes.push((peer.player, true));
+            } else if peer.is_connected && peer.is_ai && tick >= peer.commands_from_tick {
+                peer.is_ai = false;
+                seat_changes.push((peer.player, false));
+            }
+        }
+        return seat_changes;
+    }
+
+    fn apply_seat_changes(
+        &mut self,
+        game_state: &mut GameState,
+        ais: &mut Vec<Option<Box<dyn Ai>>>,
+        seat_changes: &Vec<(usize, bool)>,
+    ) {
+        for &(player, is_ai) in seat_changes.iter() {
+            if let Err(error) = game_state.set_ai_controlled(ais, player, is_ai) {
+                self.status = "Player ".to_string() + &player.to_string() + ": " + &error;
+                continue;
+            }
+            self.status = "Player ".to_string()
+                + &player.to_string()
+                + if is_ai {
+                    " is played by the AI"
+                } else {
+                    " is back in control"
+                };
+        }
+    }
+
+    fn reject_command(&mut self, tick: u64, player: usize, command: PlayerCommand, reason: String) {
+        if !self.is_dedicated && player == self.local_player {
+            self.status = format!("{:?} was rejected: {}", command, reason);
+            return;
+        }
+        let message: LockstepMessage = LockstepMessage::Rejected {
+            match_id: self.match_id,
+            tick,
+            command,
+            reason,
+        };
+        for peer in self
+            .peers
+            .iter_mut()
+            .filter(|peer| peer.player == player && peer.is_connected)
+        {
+            let _ = write_message(&mut peer.stream, &message);
+        }
+    }
+
+    fn send_local_commands(&mut self, tick: u64) {
+        let number_of_commands: usize = self.local_commands.len().min(MAX_COMMANDS_PER_FRAME);
+        let commands: Vec<PlayerCommand> =
+            self.local_commands.drain(..number_of_commands).collect();
+        if self.is_dedicated {
+            return;
+        } else if self.is_host {
+            self.add_commands(tick, self.local_player, commands);
+        } else if let Some(stream) = self.host_stream.as_mut() {
+            let _ = write_message(
+                stream,
+                &LockstepMessage::Commands {
+                    match_id: self.match_id,
+                    tick,
+                    commands,
+                },
+            );
+        }
+    }
+
+    // Steps the match by one tick once every human's commands for it are known. Returns `None`
+    // while waiting for other players, otherwise what `GameState::step` returned. The host
+    // checks every command against its own state first and only sends on the legal ones, so a
+    // modified client cannot capture a tile it could not afford or reach.
+    pub fn advance(
+        &mut self,
+        game_state: &mut GameState,
+        ais: &mut Vec<Option<Box<dyn Ai>>>,
+    ) -> Option<bool> {
+        self.poll();
+        if !self.is_in_match {
+            return None;
+        }
+        if let Some(resync) = self.resync.take() {
+            // A peer that was still in the match does not start it again from the menu.
+            self.started_settings = None;
+            let mut game_settings: GameSettings = resync.game_settings;
+            keep_local_settings(&mut game_settings, &game_state.game_settings);
+            let (resynced_state, resynced_ais) = match replay::play_to_tick(
+                game_settings,
+                &resync.commands,
+                &resync.seat_changes,
+                resync.tick,
+            ) {
+                Ok(resynced) => resynced,
+                Err(error) => {
+                    self.status = "Could not rejoin the match: ".to_string() + &error;
+                    self.end_match();
+                    return None;
+                }
+            };
+            *game_state = resynced_state;
+            *ais = resynced_ais;
+            game_state.chat_log = resync.chat;
+        }
+        self.tick = game_state.tick;
+        if self.is_host {
+            for player in std::mem::take(&mut self.rejoining_players) {
+                self.resync_peer(game_state, player);
+            }
+        }
+
+        let next_tick: u64 = game_state.tick + 1;
+        if self.last_sent_tick < next_tick + INPUT_DELAY_TICKS {
+            self.last_sent_tick = next_tick + INPUT_DELAY_TICKS;
+            self.send_local_commands(self.last_sent_tick);
+        }
+        let frame: Frame = if next_tick <= INPUT_DELAY_TICKS {
+            Frame {
+                seat_changes: Vec::new(),
+                commands: Vec::new(),
+                chat: Vec::new(),
+            }
+        } else if self.is_host {
+            let commands: Vec<(usize, PlayerCommand)> = self.take_frame(next_tick)?;
+            Frame {
+                seat_changes: self.take_seat_changes(next_tick),
+                commands,
+                chat: std::mem::take(&mut self.match_chat_lines),
+            }
+        } else {
+            self.frames.remove(&next_tick)?
+        };
+        self.apply_seat_changes(game_state, ais, &frame.seat_changes);
+        for chat_line in frame.chat.iter() {
+            game_state.chat_log.push(ChatMessage {
+                tick: next_tick,
+                name: chat_line.name.clone(),
+                text: chat_line.text.clone(),
+            });
+            self.push_chat_line(chat_line.clone());
+        }
+        let commands: Vec<(usize, PlayerCommand)> = frame.commands;
+        if self.is_host && next_tick > INPUT_DELAY_TICKS {
+            let mut accepted_commands: Vec<(usize, PlayerCommand)> = Vec::new();
+            for (player, command) in commands {
+                match player_command::check_command(game_state, player, command) {
+                    Ok(()) => {
+                        player_command::apply_command(game_state, player, command);
+                        accepted_commands.push((player, command));
+                    }
+                    Err(reason) => self.reject_command(next_tick, player, command, reason),
+                }
+            }
+            self.broadcast(&LockstepMessage::Frame {
+                match_id: self.match_id,
+                tick: next_tick,
+                seat_changes: frame.seat_changes,
+                commands: accepted_commands,
+                chat: frame.chat,
+            });
+        } else {
+            for (player, command) in commands {
+                player_command::apply_command(game_state, player, command);
+            }
+        }
+        let is_ai_improved: bool = game_state.step(ais);
+        if game_state
+            .tick
+            .is_multiple_of(desync_utility::CHECKSUM_INTERVAL_TICKS)
+        {
+            self.record_checkpoint(game_state);
+        }
+        return Some(is_ai_improved);
+    }
+}
+
+// The reader threads hold their own handles, so the connections are closed explicitly.
+impl Drop for LockstepSession {
+    fn drop(&mut self) {
+        if let Some(stream) = &self.host_stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        for peer in self.peers.iter() {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+// The options that only matter on this machine stay as they were when joining a match.
+fn keep_local_settings(game_settings: &mut GameSettings, local_settings: &GameSettings) {
+    game_settings.api_port = local_settings.api_port;
+    game_settings.export_prefix = local_settings.export_prefix.clone();
+    game_settings.host_port = local_settings.host_port;
+    game_settings.join_address = local_settings.join_address.clone();
+    game_settings.player_name = local_settings.player_name.clone();
+    game_settings.room = local_settings.room.clone();
+    game_settings.rejoin_token = local_settings.rejoin_token;
+}
+
+fn get_desync_report_path(tick: u64, player: usize) -> String {
+    return "desync_tick".to_string() + &tick.to_string() + "_player" + &player.to_string();
+}
+
+// Reads a single message without buffering past its end, so the stream can be handed to
+// `add_peer` afterwards.
+pub fn read_message(stream: &mut TcpStream) -> io::Result<LockstepMessage> {
+    let mut line: Vec<u8> = Vec::new();
+    let mut byte: [u8; 1] = [0];
+    while line.len() < MAX_HANDSHAKE_LENGTH {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed",
+            ));
+        } else if byte[0] == b'\n' {
+            let message: LockstepMessage = serde_json::from_slice(&line)?;
+            return Ok(message);
+        }
+        line.push(byte[0]);
+    }
+    return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "message is too long",
+    ));
+}
+
+fn write_message(stream: &mut TcpStream, message: &LockstepMessage) -> io::Result<()> {
+    let line: String = serde_json::to_string(message)? + "\n";
+    stream.write_all(line.as_bytes())?;
+    return stream.flush();
+}
+
+// `max_first_message_length` limits the first line, later ones may be `MAX_MESSAGE_LENGTH` long.
+fn spawn_reader(
+    stream: TcpStream,
+    connection: usize,
+    sender: Sender<LockstepEvent>,
+    max_first_message_length: u64,
+) {
+    thread::spawn(move || {
+        let mut reader: BufReader<TcpStream> = BufReader::new(stream);
+        let mut max_message_length: u64 = max_first_message_length;
+        loop {
+            // A line without its end is either too long or cut off, both end the connection.
+            let mut line: String = String::new();
+            let is_complete_line: bool =
+                match (&mut reader).take(max_message_length).read_line(&mut line) {
+                    Ok(_) => line.ends_with('\n'),
+                    Err(_) => false,
+                };
+            if !is_complete_line {
+                break;
+            }
+            max_message_length = MAX_MESSAGE_LENGTH;
+            let message: Option<LockstepMessage> =
+                serde_json::from_str::<LockstepMessage>(&line).ok();
+            match message {
+                Some(message) => {
+                    if sender
+                        .send(LockstepEvent::Message(connection, message))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                None => break,
+            }
+        }
+        let _ = sender.send(LockstepEvent::Disconnected(connection));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility;
+
+    fn poll_until(
+        sessions: &mut Vec<LockstepSession>,
+        is_done: impl Fn(&Vec<LockstepSession>) -> bool,
+    ) {
+        let deadline: Instant = Instant::now() + Duration::from_secs(10);
+        while !is_done(sessions) {
+            assert!(Instant::now() < deadline, "timed out waiting for the peers");
+            for session in sessions.iter_mut() {
+                session.poll();
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn peers_on_localhost_play_the_same_match() {
+        let number_of_peers: usize = 2;
+        let ticks: u64 = 600;
+        let mut sessions: Vec<LockstepSession> = vec![LockstepSession::host(0, "host").unwrap()];
+        let address: String = "127.0.0.1:".to_string() + &sessions[0].port.to_string();
+        for i in 0..number_of_peers {
+            let name: String = "peer ".to_string() + &i.to_string();
+            sessions.push(LockstepSession::join(&address, &name, "", None).unwrap());
+        }
+        poll_until(&mut sessions, |sessions| {
+            sessions[0].get_number_of_connected_players() == 1 + number_of_peers
+        });
+        for session in sessions.iter_mut() {
+            session.set_choice(Faction::Kingdom, true);
+        }
+        poll_until(&mut sessions, |sessions| {
+            sessions[0].get_unready_players().is_empty()
+        });
+
+        let mut game_settings: GameSettings = game_settings::parse_arguments(Vec::new());
+        game_settings.seed = Some(5);
+        let match_settings: GameSettings = sessions[0].start(&game_settings).unwrap();
+        let mut game_states: Vec<GameState> = vec![GameState::new(match_settings)];
+        poll_until(&mut sessions, |sessions| {
+            sessions[1..]
+                .iter()
+                .all(|session| session.started_settings.is_some())
+        });
+        for session in sessions[1..].iter_mut() {
+            let started_settings: GameSettings =
+                session.take_started_settings(&game_settings).unwrap();
+            game_states.push(GameState::new(started_settings));
+        }
+        let mut ais: Vec<Vec<Option<Box<dyn Ai>>>> = game_states
+            .iter()
+            .map(|game_state| ai::get_opponent_ais(game_state).unwrap())
+            .collect();
+
+        let deadline: Instant = Instant::now() + Duration::from_secs(60);
+        while game_states.iter().any(|game_state| game_state.tick < ticks) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out at tick {}",
+                game_states[0].tick
+            );
+            for (player, session) in sessions.iter_mut().enumerate() {
+                let game_state: &mut GameState = &mut game_states[player];
+                if game_state.tick >= ticks {
+                    session.poll();
+                    continue;
+                }
+                if session.advance(game_state, &mut ais[player]).is_none() {
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                if game_state.tick == 100 {
+                    session.queue_command(PlayerCommand::TakeLoan);
+                }
+                if game_state.tick == 200 + player as u64 {
+                    if let Some((tile_x, tile_y)) = test_utility::get_own_tile(game_state, player) {
+                        session.queue_command(PlayerCommand::Improve(tile_x, tile_y));
+                    }
+                }
+            }
+        }
+
+        let state_dumps: Vec<StateDump> = game_states
+            .iter()
+            .map(desync_utility::get_state_dump)
+            .collect();
+        for player in 1..game_states.len() {
+            assert_eq!(state_dumps[player].checksum, state_dumps[0].checksum);
+            assert_eq!(
+                game_states[player].tile_owned_by,
+                game_states[0].tile_owned_by
+            );
+        }
+        // The loan of every player was played.
+        let replay_commands: Vec<ReplayCommand> = replay::get_replay(&game_states[0]).commands;
+        for player in 0..game_states.len() {
+            assert!(replay_commands.iter().any(|replay_command| {
+                replay_command.player == player && replay_command.command == PlayerCommand::TakeLoan
+            }));
+        }
+    }
+}
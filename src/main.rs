@@ -1,66 +1,578 @@
-mod population_utility;
-mod tile_utility;
-
+extern crate bitter_boundaries;
 extern crate image;
 extern crate quicksilver;
 extern crate rand;
 
+use bitter_boundaries::ai::{self, Ai};
+use bitter_boundaries::api_server::ApiServer;
+use bitter_boundaries::discovery::{
+    DiscoveredGame, DiscoveryAnnouncer, DiscoveryBrowser, GameAnnouncement,
+};
+use bitter_boundaries::game_state::GameState;
+use bitter_boundaries::lockstep::{self, LockstepSession};
+use bitter_boundaries::player_command::{self, PlayerCommand};
+use bitter_boundaries::{
+    diplomacy_utility, economy_utility, export_utility, faction_utility, game_settings,
+    graph_utility, match_log, population_utility, replay, statistics_utility, tech_utility,
+    tile_utility, victory_utility,
+};
 use quicksilver::{
     combinators::result,
-    geom::{Rectangle, Shape, Vector},
-    graphics::{Background::Img, Color, Font, FontStyle, Image, View},
+    geom::{Line, Rectangle, Shape, Vector},
+    graphics::{
+        Background::Blended, Background::Col, Background::Img, Color, Font, FontStyle, Image, View,
+    },
     input::{ButtonState, Key, MouseButton},
-    lifecycle::{run, Asset, Settings, State, Window},
+    lifecycle::{run, Asset, Event, Settings, State, Window},
     sound::Sound,
     Future, Result,
 };
-use rand::Rng;
 use std::process;
 
-pub const GAME_AREA_WIDTH: usize = 8;
-pub const GAME_AREA_HEIGHT: usize = 4;
 pub const TILE_SIZE: i32 = 128;
 
 const RESOLUTION_WIDTH: f32 = 1920f32;
 const RESOLUTION_HEIGHT: f32 = 1080f32;
 const VIEW_WIDTH: usize = 1440;
 const VIEW_HEIGHT: usize = 810;
+// Room right of and below the map for the research, diplomacy and victory HUD and the
+// cash, log and network lines.
+const HUD_COLUMNS: i32 = 5;
+const HUD_ROWS: i32 = 2;
 
+// Levels above the last sprite are drawn with it.
 const SETTLEMENT_NUMBER_OF_LEVELS: usize = 13;
 const SETTLEMENT_TEXTURE_FORMAT: &str = ".png";
 
-const TILE_OWNER_CHANGE_PRICE: f64 = 1500.0;
-const TILE_IMPROVEMENT_BASE_COST: f64 = 1000.0;
-const TILE_POPULATION_CHANGE_BASE: f64 = 100.0;
+const MATCH_LOG_VISIBLE_ENTRIES: usize = 4;
+const LOBBY_CHAT_VISIBLE_LINES: usize = 18;
+const LOBBY_CHAT_LINE_LENGTH: usize = 60;
+const GAME_BROWSER_VISIBLE_ROWS: usize = 10;
+const CHAT_OVERLAY_VISIBLE_LINES: usize = 6;
+const CHAT_OVERLAY_LINE_LENGTH: usize = 70;
+// Chat lines fade from the map after 10 seconds unless the chat is open.
+const CHAT_OVERLAY_TICKS: u64 = 10 * 60;
+const QUICK_MESSAGE_KEYS: [Key; 4] = [Key::F1, Key::F2, Key::F3, Key::F4];
+const QUICK_MESSAGES: [&str; 4] = [
+    "Good luck, have fun!",
+    "Help me!",
+    "Attack now!",
+    "Well played!",
+];
+
+const PLAYER_COLORS: [(u8, u8, u8); 8] = [
+    (255, 97, 72),
+    (116, 254, 218),
+    (255, 214, 82),
+    (178, 120, 255),
+    (120, 220, 90),
+    (255, 140, 200),
+    (90, 170, 255),
+    (230, 230, 230),
+];
+// The terrain sprites have the first two player colors, teammates get darker shades of their
+// team's sprite.
+const TERRAIN_SPRITES: [&str; 2] = ["sprites/terrains/red.png", "sprites/terrains/blue.png"];
+const TEAM_SHADES: [f32; 3] = [1.0, 0.75, 0.5];
+const NEUTRAL_COLOR: (u8, u8, u8) = (96, 96, 96);
 
 struct BitterBoundaries {
     view: Rectangle,
-    settlement_sprites: Vec<Asset<Image>>,
+    players_background_sprite: Vec<Asset<Image>>,
+    settlement_sprites: Vec<Vec<Asset<Image>>>,
     sound_click: Asset<Sound>,
     sound_change: Asset<Sound>,
     sound_unable: Asset<Sound>,
     position: Vec<Vec<Vector>>,
     mouse_click_areas: Vec<Vec<Rectangle>>,
     menu_click_areas: Vec<Rectangle>,
-    tile_owned_by: Vec<Vec<i32>>,
-    tile_improvement_cost: Vec<Vec<f64>>,
-    tile_improvement_level: Vec<Vec<i32>>,
-    tile_population_number: Vec<Vec<i32>>,
-    players_cash: [f64; 2],
-    players_background_sprite: [Asset<Image>; 2],
+    game_state: GameState,
+    ais: Vec<Option<Box<dyn Ai>>>,
+    graph_kind: Option<graph_utility::GraphKind>,
+    selected_player: usize,
     new_game_button_sprite: Asset<Image>,
     exit_button_sprite: Asset<Image>,
     back_to_main_menu_button: Asset<Image>,
     is_running: bool,
-    is_win: bool,
-    winner_player: usize,
+    game_settings: game_settings::GameSettings,
+    export_button_area: Rectangle,
+    export_message: String,
+    is_exported: bool,
+    api_server: Option<ApiServer>,
+    lockstep_session: Option<LockstepSession>,
+    local_player: usize,
+    is_waiting_for_players: bool,
+    network_message: String,
+    is_typing_chat: bool,
+    chat_input: String,
+    discovery_announcer: Option<DiscoveryAnnouncer>,
+    discovery_browser: Option<DiscoveryBrowser>,
+    discovered_games: Vec<DiscoveredGame>,
+}
+
+impl BitterBoundaries {
+    fn reset_match(
+        &mut self,
+        game_settings: game_settings::GameSettings,
+    ) -> std::result::Result<(), String> {
+        let game_state: GameState = GameState::new(game_settings);
+        self.ais = ai::get_opponent_ais(&game_state)?;
+        self.game_state = game_state;
+
+        self.position = Vec::new();
+        self.mouse_click_areas = Vec::new();
+        for i in 0..self.game_state.game_area_width {
+            self.position.push(Vec::new());
+            self.mouse_click_areas.push(Vec::new());
+
+            for j in 0..self.game_state.game_area_height {
+                self.position[i].push(Vector::new(i as i32 * TILE_SIZE, j as i32 * TILE_SIZE));
+                self.mouse_click_areas[i].push(Rectangle::new(
+                    Vector::new(self.position[i][j].x as i32, self.position[i][j].y as i32),
+                    Vector::new(TILE_SIZE, TILE_SIZE),
+                ));
+            }
+        }
+
+        self.local_player = match &self.lockstep_session {
+            Some(lockstep_session) => lockstep_session.local_player,
+            None => 0,
+        };
+        self.selected_player = if self.local_player == 0 { 1 } else { 0 };
+        if let Some(api_server) = &self.api_server {
+            api_server.set_controlled_player(match &self.lockstep_session {
+                Some(_) => Some(self.local_player),
+                None => None,
+            });
+        }
+        self.is_waiting_for_players = false;
+        self.export_message = String::new();
+        self.is_exported = false;
+        return Ok(());
+    }
+
+    // Applies a command of the local player right away, or in a network match sends it to
+    // be applied on every peer. Network commands are reported as accepted.
+    fn submit_command(&mut self, command: PlayerCommand) -> bool {
+        match self.lockstep_session.as_mut() {
+            Some(lockstep_session) => {
+                lockstep_session.queue_command(command);
+                return true;
+            }
+            None => {
+                return player_command::apply_command(
+                    &mut self.game_state,
+                    self.local_player,
+                    command,
+                )
+            }
+        }
+    }
+
+    // Width and height the view can scroll over: the map with the HUD next to and below it,
+    // but never less than the baseline resolution.
+    fn get_scroll_area(&self) -> (f32, f32) {
+        let width: i32 = (self.game_state.game_area_width as i32 + HUD_COLUMNS) * TILE_SIZE;
+        let height: i32 = (self.game_state.game_area_height as i32 + HUD_ROWS) * TILE_SIZE;
+        return (
+            (width as f32).max(RESOLUTION_WIDTH),
+            (height as f32).max(RESOLUTION_HEIGHT),
+        );
+    }
+
+    // What the local network sees of the game this machine hosts.
+    fn get_game_announcement(&self) -> Option<GameAnnouncement> {
+        let lockstep_session: &LockstepSession = self.lockstep_session.as_ref()?;
+        if !lockstep_session.is_host {
+            return None;
+        }
+        return Some(GameAnnouncement {
+            name: self.game_settings.player_name.clone(),
+            port: self.game_settings.host_port?,
+            game_area_width: self.game_settings.game_area_width,
+            game_area_height: self.game_settings.game_area_height,
+            number_of_players: lockstep_session.lobby_players.len(),
+            // Free-for-all grows to fit everyone who joins.
+            max_number_of_players: if self.game_settings.team_mode
+                == game_settings::TeamMode::FreeForAll
+            {
+                game_settings::MAX_NUMBER_OF_PLAYERS
+            } else {
+                self.game_settings.number_of_players
+            },
+            is_in_match: lockstep_session.is_in_match,
+        });
+    }
+
+    // Clicking a discovered game joins it and opens its lobby, Escape goes back to the menu.
+    fn update_game_browser(&mut self, window: &Window) {
+        if let Some(discovery_browser) = &self.discovery_browser {
+            self.discovered_games = discovery_browser.get_games();
+        }
+        if window.keyboard()[Key::Escape] == ButtonState::Pressed {
+            self.discovery_browser = None;
+            self.network_message = String::new();
+            return;
+        }
+        if window.mouse()[MouseButton::Left] != ButtonState::Pressed {
+            return;
+        }
+        let discovered_game: DiscoveredGame = match self
+            .discovered_games
+            .iter()
+            .take(GAME_BROWSER_VISIBLE_ROWS)
+            .enumerate()
+            .find(|&(k, _)| get_game_browser_row_area(k).contains(window.mouse().pos()))
+        {
+            Some((_, discovered_game)) => discovered_game.clone(),
+            None => return,
+        };
+        match LockstepSession::join(
+            &discovered_game.address,
+            &self.game_settings.player_name,
+            &self.game_settings.room,
+            None,
+        ) {
+            Ok(mut lockstep_session) => {
+                lockstep_session.set_choice(self.game_settings.faction, false);
+                self.lockstep_session = Some(lockstep_session);
+                self.discovery_browser = None;
+                self.network_message = String::new();
+            }
+            Err(error) => {
+                self.network_message = "Could not join ".to_string()
+                    + &discovered_game.address
+                    + ": "
+                    + &error.to_string();
+            }
+        }
+    }
+
+    fn draw_game_browser(&self, window: &mut Window) -> Result<()> {
+        let fontstyle_white_12: FontStyle = FontStyle::new(12.0, Color::WHITE);
+        draw_text(
+            window,
+            "Games on the local network, click one to join (Escape to go back)".to_string(),
+            fontstyle_white_12,
+            (VIEW_WIDTH as i32 / 2, 60),
+        )?;
+        if self.discovered_games.is_empty() {
+            draw_text(
+                window,
+                "Searching...".to_string(),
+                fontstyle_white_12,
+                (VIEW_WIDTH as i32 / 2, 100),
+            )?;
+        }
+        for (k, discovered_game) in self
+            .discovered_games
+            .iter()
+            .take(GAME_BROWSER_VISIBLE_ROWS)
+            .enumerate()
+        {
+            let row_area: Rectangle = get_game_browser_row_area(k);
+            window.draw(&row_area, Col(Color::from_rgba(40, 40, 40, 1.0)));
+            let announcement: &GameAnnouncement = &discovered_game.announcement;
+            draw_text(
+                window,
+                announcement.name.clone()
+                    + ", "
+                    + &announcement.game_area_width.to_string()
+                    + "x"
+                    + &announcement.game_area_height.to_string()
+                    + ", "
+                    + &announcement.number_of_players.to_string()
+                    + "/"
+                    + &announcement.max_number_of_players.to_string()
+                    + " players, "
+                    + &match discovered_game.ping_milliseconds {
+                        Some(ping_milliseconds) => ping_milliseconds.to_string() + " ms",
+                        None => "? ms".to_string(),
+                    }
+                    + if announcement.is_in_match {
+                        ", in a match"
+                    } else {
+                        ""
+                    },
+                fontstyle_white_12,
+                (
+                    (row_area.pos.x + row_area.size.x / 2.0) as i32,
+                    (row_area.pos.y + row_area.size.y / 2.0) as i32,
+                ),
+            )?;
+        }
+        if !self.network_message.is_empty() {
+            draw_text(
+                window,
+                self.network_message.clone(),
+                fontstyle_white_12,
+                (
+                    VIEW_WIDTH as i32 / 2,
+                    get_game_browser_row_area(GAME_BROWSER_VISIBLE_ROWS).pos.y as i32 + 16,
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Enter opens the chat line and sends it, Escape closes it. Typed characters arrive in
+    // `event`.
+    fn update_chat_input(&mut self, window: &Window) {
+        if window.keyboard()[Key::Return] == ButtonState::Pressed {
+            if self.is_typing_chat {
+                if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                    lockstep_session.send_chat(&self.chat_input);
+                }
+                self.chat_input = String::new();
+            }
+            self.is_typing_chat = !self.is_typing_chat;
+        } else if window.keyboard()[Key::Escape] == ButtonState::Pressed {
+            self.is_typing_chat = false;
+            self.chat_input = String::new();
+        } else if self.is_typing_chat && window.keyboard()[Key::Back] == ButtonState::Pressed {
+            self.chat_input.pop();
+        }
+    }
+
+    fn draw_graph(
+        &self,
+        window: &mut Window,
+        graph_kind: graph_utility::GraphKind,
+        area: Rectangle,
+    ) -> Result<()> {
+        window.draw(&area, Col(Color::from_rgba(0, 0, 0, 0.85)));
+        let value_range: (f64, f64) =
+            graph_utility::get_value_range(&self.game_state.samples, graph_kind);
+        draw_text(
+            window,
+            graph_kind.name().to_string()
+                + " (max "
+                + &(value_range.1.round() as i64).to_string()
+                + ")",
+            FontStyle::new(12.0, Color::WHITE),
+            (
+                (area.pos.x + area.size.x / 2.0) as i32,
+                area.pos.y as i32 + 12,
+            ),
+        )?;
+        for player in 0..self.game_state.players_cash.len() {
+            let points: Vec<(f32, f32)> = graph_utility::get_graph_points(
+                &graph_utility::get_series(&self.game_state.samples, player, graph_kind),
+                value_range,
+                (area.pos.x + 10.0, area.pos.y + 30.0),
+                (area.size.x - 20.0, area.size.y - 40.0),
+            );
+            for k in 1..points.len() {
+                window.draw(
+                    &Line::new(points[k - 1], points[k]).with_thickness(2.0),
+                    Col(get_player_color(
+                        player as i32,
+                        self.game_state.game_settings.team_mode,
+                    )),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn export_match(&mut self) {
+        let prefix: String = match &self.game_settings.export_prefix {
+            Some(export_prefix) => export_prefix.clone(),
+            None => "match_".to_string() + &self.game_state.seed.to_string(),
+        };
+        let replay_path: String = prefix.clone() + "_replay.json";
+        self.export_message =
+            match export_utility::export_match(&prefix, &self.game_state.get_match_export())
+                .and_then(|mut paths| {
+                    replay::write_replay(&replay_path, &replay::get_replay(&self.game_state))?;
+                    paths.push(replay_path);
+                    Ok(paths)
+                }) {
+                Ok(paths) => "Exported to ".to_string() + &paths.join(", "),
+                Err(error) => "Export failed: ".to_string() + &error.to_string(),
+            };
+        self.is_exported = true;
+    }
+
+    // The menu of a network match: everyone in the lobby with their color, faction and ready
+    // state, the start or ready button in place of "New Game", and the chat.
+    fn draw_lobby(
+        &self,
+        window: &mut Window,
+        lockstep_session: &LockstepSession,
+        menu_settings: &game_settings::GameSettings,
+    ) -> Result<()> {
+        let fontstyle_white_12: FontStyle = FontStyle::new(12.0, Color::WHITE);
+        let (button_label, button_color): (&str, Color) = if lockstep_session.is_host {
+            ("Start match", Color::from_rgba(60, 60, 60, 1.0))
+        } else if lockstep_session.is_ready() {
+            ("Ready", Color::from_rgba(40, 130, 60, 1.0))
+        } else {
+            ("Click when ready", Color::from_rgba(60, 60, 60, 1.0))
+        };
+        window.draw(&self.menu_click_areas[0], Col(button_color));
+        draw_text(
+            window,
+            button_label.to_string(),
+            fontstyle_white_12,
+            (VIEW_WIDTH as i32 / 2, VIEW_HEIGHT as i32 / 2),
+        )?;
+
+        let mut lobby_lines: Vec<String> = vec![lockstep_session.status.clone()];
+        for lobby_player in lockstep_session.lobby_players.iter() {
+            lobby_lines.push(
+                lobby_player.name.clone()
+                    + ", "
+                    + lobby_player.faction.name()
+                    + if lobby_player.is_ready {
+                        ", ready"
+                    } else {
+                        ", not ready"
+                    },
+            );
+        }
+        if !self.network_message.is_empty() {
+            lobby_lines.push(self.network_message.clone());
+        }
+        for (k, lobby_line) in lobby_lines.into_iter().enumerate() {
+            let y: i32 = 40 + k as i32 * 20;
+            // Seats are given out in lobby order, so the first line after the status is player 0.
+            if k > 0 && k <= lockstep_session.lobby_players.len() {
+                window.draw(
+                    &Rectangle::new((VIEW_WIDTH as i32 / 2 - 220, y - 7), (14, 14)),
+                    Col(get_player_color(k as i32 - 1, menu_settings.team_mode)),
+                );
+            }
+            draw_text(
+                window,
+                lobby_line,
+                fontstyle_white_12,
+                (VIEW_WIDTH as i32 / 2, y),
+            )?;
+        }
+
+        let chat_area: Rectangle = Rectangle::new(
+            (40, VIEW_HEIGHT as i32 / 2 - TILE_SIZE),
+            (440, 3 * TILE_SIZE),
+        );
+        window.draw(&chat_area, Col(Color::from_rgba(40, 40, 40, 1.0)));
+        let mut chat_lines: Vec<String> = lockstep_session
+            .chat_lines
+            .iter()
+            .skip(
+                lockstep_session
+                    .chat_lines
+                    .len()
+                    .saturating_sub(LOBBY_CHAT_VISIBLE_LINES),
+            )
+            .map(|chat_line| {
+                (chat_line.name.clone() + ": " + &chat_line.text)
+                    .chars()
+                    .take(LOBBY_CHAT_LINE_LENGTH)
+                    .collect()
+            })
+            .collect();
+        chat_lines.push(if self.is_typing_chat {
+            let hidden_characters: usize = self
+                .chat_input
+                .chars()
+                .count()
+                .saturating_sub(LOBBY_CHAT_LINE_LENGTH);
+            "> ".to_string()
+                + &self
+                    .chat_input
+                    .chars()
+                    .skip(hidden_characters)
+                    .collect::<String>()
+                + "_"
+        } else {
+            "Enter to chat".to_string()
+        });
+        for (k, chat_line) in chat_lines.into_iter().enumerate() {
+            draw_text_left(
+                window,
+                chat_line,
+                fontstyle_white_12,
+                (
+                    chat_area.pos.x as i32 + 10,
+                    chat_area.pos.y as i32 + 16 + k as i32 * 20,
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The latest chat lines over the bottom left of the map, all of the recent ones while the
+    // chat is open.
+    fn draw_chat_overlay(&self, window: &mut Window) -> Result<()> {
+        let fontstyle_white_12: FontStyle = FontStyle::new(12.0, Color::WHITE);
+        let chat_log: &Vec<replay::ChatMessage> = &self.game_state.chat_log;
+        let mut chat_lines: Vec<String> = chat_log
+            .iter()
+            .skip(chat_log.len().saturating_sub(CHAT_OVERLAY_VISIBLE_LINES))
+            .filter(|chat_message| {
+                self.is_typing_chat || chat_message.tick + CHAT_OVERLAY_TICKS > self.game_state.tick
+            })
+            .map(|chat_message| {
+                (chat_message.name.clone() + ": " + &chat_message.text)
+                    .chars()
+                    .take(CHAT_OVERLAY_LINE_LENGTH)
+                    .collect()
+            })
+            .collect();
+        if self.is_typing_chat {
+            let hidden_characters: usize = self
+                .chat_input
+                .chars()
+                .count()
+                .saturating_sub(CHAT_OVERLAY_LINE_LENGTH);
+            chat_lines.push(
+                "> ".to_string()
+                    + &self
+                        .chat_input
+                        .chars()
+                        .skip(hidden_characters)
+                        .collect::<String>()
+                    + "_",
+            );
+            chat_lines.push(
+                QUICK_MESSAGES
+                    .iter()
+                    .enumerate()
+                    .map(|(k, quick_message)| {
+                        "F".to_string() + &(k + 1).to_string() + ": " + quick_message
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  "),
+            );
+        } else {
+            chat_lines.push("Enter: chat, F1-F4: quick messages".to_string());
+        }
+
+        let bottom: i32 = self.game_state.game_area_height as i32 * TILE_SIZE;
+        let chat_area: Rectangle = Rectangle::new(
+            (0, bottom - 10 - chat_lines.len() as i32 * 20),
+            (640, 10 + chat_lines.len() as i32 * 20),
+        );
+        window.draw(&chat_area, Col(Color::from_rgba(0, 0, 0, 0.6)));
+        for (k, chat_line) in chat_lines.into_iter().enumerate() {
+            draw_text_left(
+                window,
+                chat_line,
+                fontstyle_white_12,
+                (
+                    chat_area.pos.x as i32 + 10,
+                    chat_area.pos.y as i32 + 15 + k as i32 * 20,
+                ),
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl State for BitterBoundaries {
     fn new() -> Result<BitterBoundaries> {
         let is_running: bool = false;
-        let is_win: bool = false;
-        let winner_player: usize = 2;
 
         let new_game_button_sprite: Asset<Image> =
             Asset::new(Image::load("sprites/gui/new_game_button.png"));
@@ -69,18 +581,25 @@ impl State for BitterBoundaries {
         let back_to_main_menu_button: Asset<Image> =
             Asset::new(Image::load("sprites/gui/back_to_main_menu_button.png"));
 
-        let players_cash: [f64; 2] = [0.0, 0.0];
+        let graph_kind: Option<graph_utility::GraphKind> = None;
+        let selected_player: usize = 1;
+        let players_background_sprite: Vec<Asset<Image>> = TERRAIN_SPRITES
+            .iter()
+            .map(|&path| Asset::new(Image::load(path)))
+            .collect();
         let mut settlement_sprites = Vec::new();
         let mut menu_click_areas = Vec::new();
-        let players_background_sprite: [Asset<Image>; 2] = [
-            Asset::new(Image::load("sprites/terrains/red.png")),
-            Asset::new(Image::load("sprites/terrains/blue.png")),
-        ];
-        for i in 0..SETTLEMENT_NUMBER_OF_LEVELS {
-            let mut settlement_sprite_path: String = "sprites/settlements/level_".to_string();
-            settlement_sprite_path.push_str(&(i.to_string()));
-            settlement_sprite_path.push_str(SETTLEMENT_TEXTURE_FORMAT);
-            settlement_sprites.push(Asset::new(Image::load(settlement_sprite_path)));
+        for faction in faction_utility::FACTIONS.iter() {
+            let mut faction_settlement_sprites = Vec::new();
+            for i in 0..SETTLEMENT_NUMBER_OF_LEVELS {
+                let mut settlement_sprite_path: String =
+                    faction.get_settlement_sprite_directory().to_string();
+                settlement_sprite_path.push_str("/level_");
+                settlement_sprite_path.push_str(&(i.to_string()));
+                settlement_sprite_path.push_str(SETTLEMENT_TEXTURE_FORMAT);
+                faction_settlement_sprites.push(Asset::new(Image::load(settlement_sprite_path)));
+            }
+            settlement_sprites.push(faction_settlement_sprites);
         }
         for i in 0..2 {
             menu_click_areas.push(Rectangle::new(
@@ -96,117 +615,263 @@ impl State for BitterBoundaries {
         let sound_change = Asset::new(Sound::load("sounds/change.ogg"));
         let sound_unable = Asset::new(Sound::load("sounds/unable.ogg"));
 
-        let mut position = Vec::new();
-        let mut mouse_click_areas = Vec::new();
-        let mut tile_owned_by = Vec::new();
-        let mut tile_improvement_cost = Vec::new();
-        let mut tile_improvement_level = Vec::new();
-        let mut tile_population_number = Vec::new();
-
-        for i in 0..GAME_AREA_WIDTH {
-            position.push(Vec::new());
-            mouse_click_areas.push(Vec::new());
-
-            tile_owned_by.push(Vec::new());
-            tile_improvement_cost.push(Vec::new());
-            tile_improvement_level.push(Vec::new());
-            tile_population_number.push(Vec::new());
-
-            for j in 0..GAME_AREA_HEIGHT {
-                position[i].push(Vector::new(i as i32 * TILE_SIZE, j as i32 * TILE_SIZE));
-                mouse_click_areas[i].push(Rectangle::new(
-                    Vector::new(position[i][j].x as i32, position[i][j].y as i32),
-                    Vector::new(TILE_SIZE, TILE_SIZE),
-                ));
-                if j < (GAME_AREA_HEIGHT / 2) {
-                    tile_owned_by[i].push(0);
-                } else {
-                    tile_owned_by[i].push(1);
+        let game_settings: game_settings::GameSettings =
+            game_settings::parse_arguments(std::env::args().collect());
+        let game_state: GameState = GameState::new(game_settings.clone());
+        let export_button_area: Rectangle = Rectangle::new(
+            Vector::new(
+                VIEW_WIDTH as i32 / 2 + 2 * TILE_SIZE,
+                VIEW_HEIGHT as i32 / 2 - TILE_SIZE / 4,
+            ),
+            Vector::new(TILE_SIZE * 2, TILE_SIZE / 2),
+        );
+        let export_message: String = String::new();
+        let is_exported: bool = false;
+        let api_server: Option<ApiServer> = match game_settings.api_port {
+            Some(api_port) => match ApiServer::start(api_port) {
+                Ok(api_server) => {
+                    println!(
+                        "API listening on http://{} with token {}",
+                        api_server.address, api_server.token
+                    );
+                    Some(api_server)
                 }
-                tile_improvement_cost[i].push(TILE_IMPROVEMENT_BASE_COST);
-                tile_improvement_level[i].push(0);
-                tile_population_number[i].push(1);
-            }
-        }
+                Err(error) => {
+                    eprintln!("Could not start the API on port {}: {}", api_port, error);
+                    None
+                }
+            },
+            None => None,
+        };
+        let lockstep_session: Option<LockstepSession> =
+            match (game_settings.host_port, &game_settings.join_address) {
+                (Some(host_port), _) => {
+                    match LockstepSession::host(host_port, &game_settings.player_name) {
+                        Ok(lockstep_session) => Some(lockstep_session),
+                        Err(error) => {
+                            eprintln!("Could not host on port {}: {}", host_port, error);
+                            None
+                        }
+                    }
+                }
+                (None, Some(join_address)) => {
+                    match LockstepSession::join(
+                        join_address,
+                        &game_settings.player_name,
+                        &game_settings.room,
+                        game_settings.rejoin_token,
+                    ) {
+                        Ok(lockstep_session) => Some(lockstep_session),
+                        Err(error) => {
+                            eprintln!("Could not join {}: {}", join_address, error);
+                            None
+                        }
+                    }
+                }
+                (None, None) => None,
+            };
 
         let view = Rectangle::new_sized((VIEW_WIDTH as i32, VIEW_HEIGHT as i32));
 
-        Ok(BitterBoundaries {
+        let mut bitter_boundaries = BitterBoundaries {
             sound_click,
             sound_change,
             sound_unable,
             view,
             players_background_sprite,
             settlement_sprites,
-            position,
-            mouse_click_areas,
+            position: Vec::new(),
+            mouse_click_areas: Vec::new(),
             menu_click_areas,
-            tile_owned_by,
-            tile_improvement_cost,
-            tile_improvement_level,
-            tile_population_number,
-            players_cash,
+            game_state,
+            ais: Vec::new(),
+            graph_kind,
+            selected_player,
             new_game_button_sprite,
             exit_button_sprite,
             back_to_main_menu_button,
             is_running,
-            is_win,
-            winner_player,
-        })
+            game_settings,
+            export_button_area,
+            export_message,
+            is_exported,
+            api_server,
+            lockstep_session,
+            local_player: 0,
+            is_waiting_for_players: false,
+            network_message: String::new(),
+            is_typing_chat: false,
+            chat_input: String::new(),
+            discovery_announcer: None,
+            discovery_browser: None,
+            discovered_games: Vec::new(),
+        };
+        if let Some(lockstep_session) = bitter_boundaries.lockstep_session.as_mut() {
+            if lockstep_session.is_host {
+                lockstep_session.set_lobby_settings(&bitter_boundaries.game_settings);
+            }
+            lockstep_session.set_choice(bitter_boundaries.game_settings.faction, false);
+        }
+        if let Some(game_announcement) = bitter_boundaries.get_game_announcement() {
+            match DiscoveryAnnouncer::start(game_announcement) {
+                Ok(discovery_announcer) => {
+                    bitter_boundaries.discovery_announcer = Some(discovery_announcer)
+                }
+                Err(error) => eprintln!("Could not announce the game: {}", error),
+            }
+        }
+        if let Err(error) = bitter_boundaries.reset_match(bitter_boundaries.game_settings.clone()) {
+            eprintln!("Could not start the match: {}", error);
+            process::exit(1);
+        }
+
+        Ok(bitter_boundaries)
     }
 
     fn update(&mut self, window: &mut Window) -> Result<()> {
+        if let (Some(discovery_announcer), Some(game_announcement)) =
+            (&self.discovery_announcer, self.get_game_announcement())
+        {
+            discovery_announcer.update(game_announcement);
+        }
+
         if self.is_running {
-            if self.is_win {
+            if self.game_state.is_win {
                 if window.mouse()[MouseButton::Left] == ButtonState::Pressed
                     && self.menu_click_areas[0].contains(window.mouse().pos())
                 {
                     self.is_running = false;
+                    if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                        lockstep_session.end_match();
+                    }
+                } else if window.mouse()[MouseButton::Left] == ButtonState::Pressed
+                    && self.export_button_area.contains(window.mouse().pos())
+                {
+                    self.export_match();
+                } else if self.game_settings.export_prefix.is_some() && !self.is_exported {
+                    self.export_match();
                 }
             } else {
-                for i in 0..self.players_cash.len() {
-                    self.players_cash[i] +=
-                        population_utility::get_cash(population_utility::get_total_population(
-                            i as i32,
-                            &self.tile_population_number,
-                            &self.tile_owned_by,
-                            GAME_AREA_WIDTH,
-                            GAME_AREA_HEIGHT,
-                        ));
-
-                    if i == 1 {
-                        self.players_cash[i] /= 1.00375;
+                if self.lockstep_session.is_some() {
+                    self.update_chat_input(window);
+                    for (k, quick_message_key) in QUICK_MESSAGE_KEYS.iter().enumerate() {
+                        if window.keyboard()[*quick_message_key] == ButtonState::Pressed {
+                            if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                                lockstep_session.send_chat(QUICK_MESSAGES[k]);
+                            }
+                        }
+                    }
+                }
+                // Letters typed into the chat are not commands.
+                if !self.is_typing_chat {
+                    let research_keys: [Key; 4] = [Key::Key1, Key::Key2, Key::Key3, Key::Key4];
+                    for (k, research_key) in research_keys.iter().enumerate() {
+                        if window.keyboard()[*research_key] == ButtonState::Pressed {
+                            if self.submit_command(PlayerCommand::Research(
+                                tech_utility::TECHNOLOGIES[k],
+                            )) {
+                                self.sound_change.execute(|sound| {
+                                    sound.play()?;
+                                    Ok(())
+                                })?;
+                            } else {
+                                self.sound_unable.execute(|sound| {
+                                    sound.play()?;
+                                    Ok(())
+                                })?;
+                            }
+                        }
                     }
 
-                    if tile_utility::is_player_wins(
-                        &self.tile_owned_by,
-                        GAME_AREA_WIDTH,
-                        GAME_AREA_HEIGHT,
-                        i as i32,
-                    ) {
-                        self.is_win = true;
-                        self.winner_player = i;
+                    if window.keyboard()[Key::Tab] == ButtonState::Pressed {
+                        let other_players: Vec<usize> = self
+                            .game_state
+                            .get_active_players()
+                            .into_iter()
+                            .filter(|&player| player != self.local_player)
+                            .collect();
+                        if !other_players.is_empty() {
+                            self.selected_player = match other_players
+                                .iter()
+                                .find(|&&player| player > self.selected_player)
+                            {
+                                Some(&player) => player,
+                                None => other_players[0],
+                            };
+                        }
+                    }
+                    if self.selected_player < self.game_state.players_eliminated.len()
+                        && !self.game_state.players_eliminated[self.selected_player]
+                    {
+                        if window.keyboard()[Key::P] == ButtonState::Pressed {
+                            self.submit_command(PlayerCommand::Propose(
+                                self.selected_player,
+                                diplomacy_utility::DiplomaticState::NonAggression,
+                            ));
+                        }
+                        if window.keyboard()[Key::A] == ButtonState::Pressed {
+                            self.submit_command(PlayerCommand::Propose(
+                                self.selected_player,
+                                diplomacy_utility::DiplomaticState::Alliance,
+                            ));
+                        }
+                        if window.keyboard()[Key::W] == ButtonState::Pressed {
+                            self.submit_command(PlayerCommand::Propose(
+                                self.selected_player,
+                                diplomacy_utility::DiplomaticState::War,
+                            ));
+                        }
+                        if window.keyboard()[Key::C] == ButtonState::Pressed
+                            && !self
+                                .submit_command(PlayerCommand::TransferCash(self.selected_player))
+                        {
+                            self.sound_unable.execute(|sound| {
+                                sound.play()?;
+                                Ok(())
+                            })?;
+                        }
+                        if window.keyboard()[Key::T] == ButtonState::Pressed
+                            && !self
+                                .submit_command(PlayerCommand::SendTribute(self.selected_player))
+                        {
+                            self.sound_unable.execute(|sound| {
+                                sound.play()?;
+                                Ok(())
+                            })?;
+                        }
+                    }
+                    if window.keyboard()[Key::Y] == ButtonState::Pressed {
+                        self.submit_command(PlayerCommand::AnswerProposal(true));
+                    } else if window.keyboard()[Key::N] == ButtonState::Pressed {
+                        self.submit_command(PlayerCommand::AnswerProposal(false));
                     }
-                }
 
-                for i in 0..GAME_AREA_WIDTH {
-                    for j in 0..GAME_AREA_HEIGHT {
-                        self.tile_improvement_level[i][j] =
-                            population_utility::get_level_of_settlement(
-                                self.tile_population_number[i][j],
-                            );
-                        self.tile_improvement_cost[i][j] = TILE_IMPROVEMENT_BASE_COST
-                            * (self.tile_improvement_level[i][j] + 1) as f64;
+                    if window.keyboard()[Key::G] == ButtonState::Pressed {
+                        self.graph_kind = graph_utility::get_next_graph_kind(self.graph_kind);
+                    }
+
+                    if window.keyboard()[Key::L] == ButtonState::Pressed {
+                        if self.submit_command(PlayerCommand::TakeLoan) {
+                            self.sound_change.execute(|sound| {
+                                sound.play()?;
+                                Ok(())
+                            })?;
+                        } else {
+                            self.sound_unable.execute(|sound| {
+                                sound.play()?;
+                                Ok(())
+                            })?;
+                        }
+                    }
+                }
 
+                for i in 0..self.game_state.game_area_width {
+                    for j in 0..self.game_state.game_area_height {
                         if window.mouse()[MouseButton::Left] == ButtonState::Pressed
                             && self.mouse_click_areas[i][j].contains(window.mouse().pos())
                         {
-                            if self.tile_owned_by[i][j] == 0 {
-                                if self.players_cash[0] >= self.tile_improvement_cost[i][j] {
-                                    self.players_cash[0] -= self.tile_improvement_cost[i][j];
-                                    self.tile_population_number[i][j] += TILE_POPULATION_CHANGE_BASE
-                                        as i32
-                                        * (self.tile_improvement_level[i][j] + 1);
+                            if self.game_state.tile_owned_by[i][j] == self.local_player as i32 {
+                                if self.submit_command(PlayerCommand::Improve(i, j)) {
                                     self.sound_click.execute(|sound| {
                                         sound.play()?;
                                         Ok(())
@@ -217,91 +882,185 @@ impl State for BitterBoundaries {
                                         Ok(())
                                     })?;
                                 }
-                            } else if tile_utility::has_adjacent_friendly_tile(
-                                &self.tile_owned_by,
-                                i as i32,
-                                j as i32,
-                                GAME_AREA_WIDTH,
-                                GAME_AREA_HEIGHT,
-                                0,
+                            } else if !diplomacy_utility::can_attack(
+                                &self.game_state.diplomacy,
+                                self.local_player,
+                                self.game_state.tile_owned_by[i][j],
                             ) {
-                                if self.players_cash[0]
-                                    >= TILE_OWNER_CHANGE_PRICE
-                                        * (self.tile_improvement_level[i][j] + 1) as f64
-                                {
-                                    self.players_cash[0] -= TILE_OWNER_CHANGE_PRICE
-                                        * (self.tile_improvement_level[i][j] + 1) as f64;
-                                    self.tile_population_number[i][j] -= TILE_POPULATION_CHANGE_BASE
-                                        as i32
-                                        * (self.tile_improvement_level[i][j] + 1);
-                                    if self.tile_population_number[i][j] < 0 {
-                                        self.tile_owned_by[i][j] = 0;
-                                        self.tile_population_number[i][j] = 1;
-                                    }
-                                } else {
-                                    self.sound_change.execute(|sound| {
-                                        sound.play()?;
-                                        Ok(())
-                                    })?;
-                                }
+                                self.sound_unable.execute(|sound| {
+                                    sound.play()?;
+                                    Ok(())
+                                })?;
+                            } else if self.game_state.can_attack_tile(self.local_player, i, j)
+                                && !self.submit_command(PlayerCommand::Capture(i, j))
+                            {
+                                self.sound_change.execute(|sound| {
+                                    sound.play()?;
+                                    Ok(())
+                                })?;
                             }
                         }
                     }
                 }
 
-                let mut rng = rand::thread_rng();
-                let random_row: usize = rng.gen_range(0, GAME_AREA_HEIGHT);
-                let random_column: usize = rng.gen_range(0, GAME_AREA_WIDTH);
-                if self.tile_owned_by[random_column][random_row] == 1 {
-                    if self.players_cash[1]
-                        >= TILE_IMPROVEMENT_BASE_COST
-                            * (self.tile_improvement_level[random_column][random_row] + 1) as f64
-                    {
-                        self.players_cash[1] -= TILE_IMPROVEMENT_BASE_COST
-                            * (self.tile_improvement_level[random_column][random_row] + 1) as f64;
-                        self.tile_population_number[random_column][random_row] +=
-                            TILE_POPULATION_CHANGE_BASE as i32
-                                * (self.tile_improvement_level[random_column][random_row] + 1);
-                        self.sound_click.execute(|sound| {
-                            sound.play()?;
-                            Ok(())
-                        })?;
+                if let Some(api_server) = &self.api_server {
+                    for (player, action) in api_server.take_actions() {
+                        if let Some(command) = player_command::get_command(action) {
+                            match self.lockstep_session.as_mut() {
+                                // The API refuses actions for the other players' seats.
+                                Some(lockstep_session) => {
+                                    lockstep_session.queue_command(command);
+                                }
+                                None => {
+                                    player_command::apply_command(
+                                        &mut self.game_state,
+                                        player,
+                                        command,
+                                    );
+                                }
+                            }
+                        }
                     }
-                } else {
-                    if tile_utility::has_adjacent_friendly_tile(
-                        &self.tile_owned_by,
-                        random_column as i32,
-                        random_row as i32,
-                        GAME_AREA_WIDTH,
-                        GAME_AREA_HEIGHT,
-                        1,
-                    ) {
-                        if self.players_cash[1]
-                            >= TILE_OWNER_CHANGE_PRICE
-                                * (self.tile_improvement_level[random_column][random_row] + 1)
-                                    as f64
-                        {
-                            self.players_cash[1] -= TILE_OWNER_CHANGE_PRICE
-                                * (self.tile_improvement_level[random_column][random_row] + 1)
-                                    as f64;
-                            self.tile_population_number[random_column][random_row] -=
-                                TILE_POPULATION_CHANGE_BASE as i32
-                                    * (self.tile_improvement_level[random_column][random_row] + 1);
-                            if self.tile_population_number[random_column][random_row] < 0 {
-                                self.tile_owned_by[random_column][random_row] = 1;
-                                self.tile_population_number[random_column][random_row] = 1;
+                }
+
+                let is_ai_improved: bool = match self.lockstep_session.as_mut() {
+                    Some(lockstep_session) => {
+                        match lockstep_session.advance(&mut self.game_state, &mut self.ais) {
+                            Some(is_ai_improved) => {
+                                self.is_waiting_for_players = false;
+                                is_ai_improved
+                            }
+                            None => {
+                                self.is_waiting_for_players = true;
+                                false
                             }
                         }
                     }
+                    None => self.game_state.step(&mut self.ais),
+                };
+                if is_ai_improved {
+                    self.sound_click.execute(|sound| {
+                        sound.play()?;
+                        Ok(())
+                    })?;
                 }
             }
+
+            if let Some(api_server) = &self.api_server {
+                api_server.publish(&self.game_state);
+            }
+        } else if self.discovery_browser.is_some() {
+            self.update_game_browser(window);
         } else {
+            let mut started_settings: Option<game_settings::GameSettings> = None;
+            if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                lockstep_session.poll();
+                if !lockstep_session.is_host {
+                    started_settings = lockstep_session.take_started_settings(&self.game_settings);
+                }
+            }
             if window.mouse()[MouseButton::Left] == ButtonState::Pressed
                 && self.menu_click_areas[0].contains(window.mouse().pos())
             {
-                self.is_running = true;
-                self.is_win = false;
-                self.winner_player = 2;
+                match self.lockstep_session.as_mut() {
+                    // In the lobby the host starts the match, everyone else toggles ready.
+                    Some(lockstep_session) if lockstep_session.is_host => {
+                        match lockstep_session.start(&self.game_settings) {
+                            Ok(game_settings) => started_settings = Some(game_settings),
+                            Err(error) => self.network_message = error,
+                        }
+                    }
+                    Some(lockstep_session) => {
+                        let is_ready: bool = !lockstep_session.is_ready();
+                        lockstep_session.set_choice(self.game_settings.faction, is_ready);
+                    }
+                    None => started_settings = Some(self.game_settings.clone()),
+                }
+            }
+            if let Some(game_settings) = started_settings {
+                match self.reset_match(game_settings) {
+                    Ok(()) => {
+                        self.network_message = String::new();
+                        self.is_running = true;
+                    }
+                    Err(error) => self.network_message = error,
+                }
+            }
+
+            if self.lockstep_session.is_some() {
+                self.update_chat_input(window);
+            } else if window.keyboard()[Key::B] == ButtonState::Pressed {
+                match DiscoveryBrowser::start() {
+                    Ok(discovery_browser) => {
+                        self.discovery_browser = Some(discovery_browser);
+                        self.discovered_games = Vec::new();
+                        self.network_message = String::new();
+                    }
+                    Err(error) => {
+                        self.network_message =
+                            "Could not search for games: ".to_string() + &error.to_string()
+                    }
+                }
+            }
+            // Only the host changes the rules of a network match.
+            let can_change_rules: bool = !self.is_typing_chat
+                && self
+                    .lockstep_session
+                    .as_ref()
+                    .is_none_or(|lockstep_session| lockstep_session.is_host);
+            let mut is_rule_changed: bool = false;
+
+            if can_change_rules && window.keyboard()[Key::M] == ButtonState::Pressed {
+                self.game_settings.game_mode = self.game_settings.game_mode.next();
+                is_rule_changed = true;
+            }
+
+            if can_change_rules
+                && window.keyboard()[Key::P] == ButtonState::Pressed
+                && self.game_settings.team_mode == game_settings::TeamMode::FreeForAll
+            {
+                self.game_settings.number_of_players =
+                    game_settings::get_next_number_of_players(self.game_settings.number_of_players);
+                is_rule_changed = true;
+            }
+
+            if can_change_rules && window.keyboard()[Key::V] == ButtonState::Pressed {
+                self.game_settings.victory_condition = self.game_settings.victory_condition.next();
+                is_rule_changed = true;
+            }
+
+            if can_change_rules && window.keyboard()[Key::S] == ButtonState::Pressed {
+                let (game_area_width, game_area_height): (usize, usize) =
+                    game_settings::get_next_map_size((
+                        self.game_settings.game_area_width,
+                        self.game_settings.game_area_height,
+                    ));
+                self.game_settings.game_area_width = game_area_width;
+                self.game_settings.game_area_height = game_area_height;
+                is_rule_changed = true;
+            }
+
+            if !self.is_typing_chat && window.keyboard()[Key::F] == ButtonState::Pressed {
+                self.game_settings.faction = self.game_settings.faction.next();
+                if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                    let is_ready: bool = lockstep_session.is_ready();
+                    lockstep_session.set_choice(self.game_settings.faction, is_ready);
+                }
+            }
+
+            if can_change_rules && window.keyboard()[Key::T] == ButtonState::Pressed {
+                self.game_settings.team_mode = self.game_settings.team_mode.next();
+                self.game_settings.number_of_players = self
+                    .game_settings
+                    .team_mode
+                    .get_number_of_players(self.game_settings.number_of_players);
+                is_rule_changed = true;
+            }
+
+            if is_rule_changed {
+                if let Some(lockstep_session) = self.lockstep_session.as_mut() {
+                    lockstep_session.set_lobby_settings(&self.game_settings);
+                }
             }
 
             if window.mouse()[MouseButton::Left] == ButtonState::Pressed
@@ -311,18 +1070,19 @@ impl State for BitterBoundaries {
             }
         }
 
+        let (scroll_width, scroll_height): (f32, f32) = self.get_scroll_area();
         if window.keyboard()[Key::Left].is_down() {
             if self.view.pos.x > (0.0f32 - 0.1f32 * self.view.size.x) {
                 self.view = self.view.translate((-4, 0));
             }
         }
         if window.keyboard()[Key::Right].is_down() {
-            if self.view.pos.x < (RESOLUTION_WIDTH - 0.9f32 * self.view.size.x) {
+            if self.view.pos.x < (scroll_width - 0.9f32 * self.view.size.x) {
                 self.view = self.view.translate((4, 0));
             }
         }
         if window.keyboard()[Key::Down].is_down() {
-            if self.view.pos.y < (RESOLUTION_HEIGHT - 0.9f32 * self.view.size.y) {
+            if self.view.pos.y < (scroll_height - 0.9f32 * self.view.size.y) {
                 self.view = self.view.translate((0, 4));
             }
         }
@@ -336,31 +1096,87 @@ impl State for BitterBoundaries {
         Ok(())
     }
 
+    fn event(&mut self, event: &Event, _window: &mut Window) -> Result<()> {
+        if let Event::Typed(character) = event {
+            if self.is_typing_chat && self.chat_input.chars().count() < lockstep::MAX_CHAT_LENGTH {
+                self.chat_input.push(*character);
+            }
+        }
+        Ok(())
+    }
+
     fn draw(&mut self, window: &mut Window) -> Result<()> {
         window.clear(Color::BLACK)?;
 
         let fontstyle_white_9: FontStyle = FontStyle::new(9.0, Color::WHITE);
         let fontstyle_white_12: FontStyle = FontStyle::new(12.0, Color::WHITE);
+        let game_area_width: usize = self.game_state.game_area_width;
+        let game_area_height: usize = self.game_state.game_area_height;
 
         if self.is_running {
-            if self.is_win {
-                let winner_string: String =
-                    "Winner: Player ".to_string() + &(self.winner_player.to_string());
-                let mut winner_text: Asset<Image> = Asset::new(
-                    Font::load("fonts/FiraCode-Regular.ttf").and_then(move |font| {
-                        result(font.render(&winner_string, &fontstyle_white_12))
-                    }),
-                );
-                winner_text.execute(|image| {
-                    window.draw(
-                        &image.area().with_center((
-                            GAME_AREA_WIDTH as i32 * TILE_SIZE + TILE_SIZE / 2,
-                            GAME_AREA_HEIGHT as i32 * TILE_SIZE + 18,
-                        )),
-                        Img(&image),
-                    );
-                    Ok(())
-                })?;
+            if self.game_state.is_win {
+                let mut summary_lines: Vec<String> = vec![
+                    "Winner: ".to_string()
+                        + &self.game_state.get_team_name(self.game_state.winner_team)
+                        + "   Time played: "
+                        + &statistics_utility::get_time_played(self.game_state.tick)
+                        + "   Your score: "
+                        + &(self.game_state.get_score(self.local_player).round() as i64)
+                            .to_string(),
+                    String::new(),
+                    format!(
+                        "{:<10} {:<10} {:>10} {:>6} {:>9} {:>5} {:>12} {:>10}",
+                        "Player",
+                        "Faction",
+                        "Score",
+                        "Tiles",
+                        "Captured",
+                        "Lost",
+                        "Peak pop",
+                        "Spent"
+                    ),
+                ];
+                for player in 0..self.game_state.players_cash.len() {
+                    let statistics: statistics_utility::PlayerStatistics =
+                        self.game_state.players_statistics[player];
+                    summary_lines.push(format!(
+                        "{:<10} {:<10} {:>10} {:>6} {:>9} {:>5} {:>12} {:>10}",
+                        "Player ".to_string() + &player.to_string(),
+                        self.game_state.players_faction[player].name(),
+                        self.game_state.get_score(player).round() as i64,
+                        tile_utility::get_number_of_tiles(
+                            &self.game_state.tile_owned_by,
+                            game_area_width,
+                            game_area_height,
+                            player as i32,
+                        ),
+                        statistics.tiles_captured,
+                        statistics.tiles_lost,
+                        statistics.peak_population,
+                        statistics.total_spending.round() as i64,
+                    ));
+                }
+                for (k, summary_line) in summary_lines.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        summary_line,
+                        fontstyle_white_12,
+                        (VIEW_WIDTH as i32 / 2, TILE_SIZE / 2 + k as i32 * 20),
+                    )?;
+                }
+                for (k, graph_kind) in graph_utility::GRAPH_KINDS.iter().enumerate() {
+                    self.draw_graph(
+                        window,
+                        *graph_kind,
+                        Rectangle::new(
+                            (
+                                20 + k as i32 * 470,
+                                VIEW_HEIGHT as i32 / 2 + TILE_SIZE / 2 + 20,
+                            ),
+                            (440, 260),
+                        ),
+                    )?;
+                }
                 self.back_to_main_menu_button.execute(|image| {
                     window.draw(
                         &image
@@ -370,9 +1186,54 @@ impl State for BitterBoundaries {
                     );
                     Ok(())
                 })?;
+                window.draw(
+                    &self.export_button_area,
+                    Col(Color::from_rgba(96, 96, 96, 1.0)),
+                );
+                draw_text(
+                    window,
+                    "Export statistics".to_string(),
+                    fontstyle_white_12,
+                    (
+                        self.export_button_area.center().x as i32,
+                        self.export_button_area.center().y as i32,
+                    ),
+                )?;
+                if !self.export_message.is_empty() {
+                    draw_text(
+                        window,
+                        self.export_message.clone(),
+                        fontstyle_white_12,
+                        (
+                            VIEW_WIDTH as i32 / 2,
+                            VIEW_HEIGHT as i32 / 2 + TILE_SIZE / 2 + 10,
+                        ),
+                    )?;
+                }
             } else {
-                let players_cash_string: String =
-                    "Cash: ".to_string() + &(self.players_cash[0].to_string());
+                let mut players_cash_string: String = "Cash: ".to_string()
+                    + &(self.game_state.get_cash(self.local_player).to_string())
+                    + " Upkeep: "
+                    + &(economy_utility::from_cash_units(
+                        self.game_state.players_upkeep[self.local_player],
+                    )
+                    .to_string())
+                    + " Loan: "
+                    + &(economy_utility::from_cash_units(
+                        self.game_state.players_loan[self.local_player],
+                    )
+                    .round()
+                    .to_string())
+                    + "/"
+                    + &(economy_utility::from_cash_units(
+                        self.game_state.players_loan_limit[self.local_player],
+                    )
+                    .round()
+                    .to_string())
+                    + " (L: borrow)";
+                if self.game_state.players_cash[self.local_player] < 0 {
+                    players_cash_string.push_str(" In debt, population is shrinking!");
+                }
                 let mut players_cash_text: Asset<Image> = Asset::new(
                     Font::load("fonts/FiraCode-Regular.ttf").and_then(move |font| {
                         result(font.render(&players_cash_string, &fontstyle_white_12))
@@ -381,27 +1242,215 @@ impl State for BitterBoundaries {
                 players_cash_text.execute(|image| {
                     window.draw(
                         &image.area().with_center((
-                            GAME_AREA_WIDTH as i32 * TILE_SIZE + TILE_SIZE / 2,
-                            GAME_AREA_HEIGHT as i32 * TILE_SIZE + 18,
+                            game_area_width as i32 * TILE_SIZE + TILE_SIZE / 2,
+                            game_area_height as i32 * TILE_SIZE + 18,
                         )),
                         Img(&image),
                     );
                     Ok(())
                 })?;
 
-                for i in 0..GAME_AREA_WIDTH {
-                    for j in 0..GAME_AREA_HEIGHT {
+                if self.game_state.tick < self.game_state.announcement_expires_at_tick {
+                    draw_text(
+                        window,
+                        self.game_state.announcement.clone(),
+                        fontstyle_white_12,
+                        (
+                            game_area_width as i32 * TILE_SIZE / 2,
+                            game_area_height as i32 * TILE_SIZE + 18,
+                        ),
+                    )?;
+                }
+                let mut research_lines: Vec<String> = vec!["Research".to_string()];
+                for (k, technology) in tech_utility::TECHNOLOGIES.iter().enumerate() {
+                    let mut research_line: String = (k + 1).to_string()
+                        + ": "
+                        + technology.name()
+                        + " ("
+                        + &technology.research_cost().to_string()
+                        + ") "
+                        + technology.description();
+                    if tech_utility::has_technology(
+                        &self.game_state.players_research[self.local_player],
+                        *technology,
+                    ) {
+                        research_line.push_str(" [done]");
+                    } else if self.game_state.players_research[self.local_player].current
+                        == Some(*technology)
+                    {
+                        research_line = research_line
+                            + " ["
+                            + &((tech_utility::get_progress(
+                                &self.game_state.players_research[self.local_player],
+                            ) * 100.0) as i32)
+                                .to_string()
+                            + "%]";
+                    }
+                    research_lines.push(research_line);
+                }
+                for (k, research_line) in research_lines.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        research_line,
+                        fontstyle_white_12,
+                        (
+                            game_area_width as i32 * TILE_SIZE + 2 * TILE_SIZE,
+                            TILE_SIZE / 2 + k as i32 * 20,
+                        ),
+                    )?;
+                }
+
+                let mut diplomacy_lines: Vec<String> = vec![
+                    "Diplomacy (Tab: select, P: pact, A: alliance, W: war, T: tribute, C: give cash)".to_string(),
+                ];
+                for player in 0..self.game_state.players_cash.len() {
+                    if player == self.local_player {
+                        continue;
+                    }
+                    let mut diplomacy_line: String = if player == self.selected_player {
+                        "> ".to_string()
+                    } else {
+                        "  ".to_string()
+                    };
+                    diplomacy_line = diplomacy_line
+                        + "Player "
+                        + &player.to_string()
+                        + " ("
+                        + self.game_state.players_faction[player].name()
+                        + "): ";
+                    if self.game_state.players_eliminated[player] {
+                        diplomacy_line.push_str("eliminated");
+                    } else if self.game_state.is_teammate(self.local_player, player) {
+                        diplomacy_line = diplomacy_line
+                            + "Teammate, cash "
+                            + &(self.game_state.get_cash(player).round() as i32).to_string();
+                    } else {
+                        diplomacy_line = diplomacy_line
+                            + self.game_state.diplomacy[self.local_player][player].name()
+                            + ", strength "
+                            + &(self.game_state.get_strength(player).round() as i32).to_string();
+                    }
+                    diplomacy_lines.push(diplomacy_line);
+                }
+                if let Some(proposal) = self
+                    .game_state
+                    .diplomatic_proposals
+                    .iter()
+                    .find(|proposal| proposal.to == self.local_player)
+                {
+                    diplomacy_lines.push(
+                        "Player ".to_string()
+                            + &proposal.from.to_string()
+                            + " offers "
+                            + proposal.state.name()
+                            + " (Y/N)",
+                    );
+                }
+                for (k, diplomacy_line) in diplomacy_lines.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        diplomacy_line,
+                        fontstyle_white_12,
+                        (
+                            game_area_width as i32 * TILE_SIZE + 2 * TILE_SIZE,
+                            TILE_SIZE / 2
+                                + (tech_utility::TECHNOLOGIES.len() as i32 + 2) * 20
+                                + k as i32 * 20,
+                        ),
+                    )?;
+                }
+
+                let mut victory_lines: Vec<String> = vec![
+                    "Victory: ".to_string()
+                        + self.game_state.game_settings.victory_condition.name()
+                        + ", "
+                        + &self
+                            .game_state
+                            .game_settings
+                            .victory_condition
+                            .description(),
+                ];
+                for team in self.game_state.get_active_teams() {
+                    victory_lines.push(
+                        self.game_state.get_team_name(team)
+                            + ": "
+                            + &((self.game_state.get_victory_progress(team) * 100.0) as i32)
+                                .to_string()
+                            + "%",
+                    );
+                }
+                for (k, victory_line) in victory_lines.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        victory_line,
+                        fontstyle_white_12,
+                        (
+                            game_area_width as i32 * TILE_SIZE + 2 * TILE_SIZE,
+                            TILE_SIZE / 2
+                                + (tech_utility::TECHNOLOGIES.len() as i32
+                                    + game_settings::MAX_NUMBER_OF_PLAYERS as i32
+                                    + 5)
+                                    * 20
+                                + k as i32 * 20,
+                        ),
+                    )?;
+                }
+
+                let match_log_entries: Vec<String> = match_log::get_recent_entries(
+                    &self.game_state.match_log,
+                    MATCH_LOG_VISIBLE_ENTRIES,
+                );
+                for (k, entry) in match_log_entries.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        entry,
+                        fontstyle_white_9,
+                        (
+                            game_area_width as i32 * TILE_SIZE / 2,
+                            game_area_height as i32 * TILE_SIZE + 40 + k as i32 * 14,
+                        ),
+                    )?;
+                }
+                if let Some(lockstep_session) = &self.lockstep_session {
+                    let network_line: String = if self.is_waiting_for_players {
+                        "Waiting for other players... ".to_string() + &lockstep_session.status
+                    } else {
+                        lockstep_session.status.clone()
+                    };
+                    draw_text(
+                        window,
+                        network_line,
+                        fontstyle_white_12,
+                        (
+                            game_area_width as i32 * TILE_SIZE / 2,
+                            game_area_height as i32 * TILE_SIZE
+                                + 40
+                                + MATCH_LOG_VISIBLE_ENTRIES as i32 * 14
+                                + 10,
+                        ),
+                    )?;
+                }
+
+                for i in 0..game_area_width {
+                    for j in 0..game_area_height {
                         let population_number_string: String =
-                            String::from(self.tile_population_number[i][j].to_string());
+                            String::from(self.game_state.tile_population_number[i][j].to_string());
                         let settlement_type_string: String =
-                            population_utility::get_type_of_settlement(
-                                self.tile_population_number[i][j],
+                            population_utility::get_type_of_settlement_level(
+                                self.game_state.tile_improvement_level[i][j],
                             );
                         let mut improvement_cost_string: String =
-                            self.tile_improvement_cost[i][j].to_string();
-                        if self.tile_owned_by[i][j] == 1 {
-                            improvement_cost_string = (TILE_OWNER_CHANGE_PRICE
-                                * (self.tile_improvement_level[i][j] + 1) as f64)
+                            self.game_state.tile_improvement_cost[i][j].to_string();
+                        let happiness_string: String =
+                            (self.game_state.tile_happiness[i][j].round() as i32).to_string()
+                                + "% happy";
+                        if self.game_state.tile_owned_by[i][j] != self.local_player as i32 {
+                            improvement_cost_string = self
+                                .game_state
+                                .get_owner_change_cost(
+                                    self.local_player as i32,
+                                    self.game_state.tile_improvement_level[i][j],
+                                )
                                 .to_string();
                         }
 
@@ -423,6 +1472,12 @@ impl State for BitterBoundaries {
                             }),
                         );
 
+                        let mut happiness_text: Asset<Image> = Asset::new(
+                            Font::load("fonts/FiraCode-Regular.ttf").and_then(move |font| {
+                                result(font.render(&happiness_string, &fontstyle_white_9))
+                            }),
+                        );
+
                         population_number_text.execute(|image| {
                             window.draw(
                                 &image.area().with_center((
@@ -456,31 +1511,63 @@ impl State for BitterBoundaries {
                             Ok(())
                         })?;
 
+                        happiness_text.execute(|image| {
+                            window.draw(
+                                &image.area().with_center((
+                                    i as i32 * TILE_SIZE + TILE_SIZE / 2,
+                                    j as i32 * TILE_SIZE + TILE_SIZE - 34,
+                                )),
+                                Img(&image),
+                            );
+                            Ok(())
+                        })?;
+
                         let new_x: i32 = self.position[i][j].x as i32;
                         let new_y: i32 = self.position[i][j].y as i32;
 
-                        self.players_background_sprite[self.tile_owned_by[i][j] as usize].execute(
-                            |image| {
+                        let owner: i32 = self.game_state.tile_owned_by[i][j];
+                        let team_mode: game_settings::TeamMode =
+                            self.game_state.game_settings.team_mode;
+                        match get_tile_sprite(owner, team_mode) {
+                            Some((sprite, tint)) => {
+                                self.players_background_sprite[sprite].execute(|image| {
+                                    window.draw(
+                                        &image.area().with_center((
+                                            TILE_SIZE / 2 + new_x,
+                                            TILE_SIZE / 2 + new_y,
+                                        )),
+                                        Blended(&image, tint),
+                                    );
+                                    Ok(())
+                                })?;
+                            }
+                            None => {
                                 window.draw(
-                                    &image.area().with_center((
-                                        TILE_SIZE / 2 + new_x,
-                                        TILE_SIZE / 2 + new_y,
-                                    )),
-                                    Img(&image),
+                                    &Rectangle::new((new_x, new_y), (TILE_SIZE, TILE_SIZE)),
+                                    Col(get_player_color(owner, team_mode)),
                                 );
-                                Ok(())
-                            },
-                        )?;
+                            }
+                        }
                     }
                 }
 
-                for i in 0..GAME_AREA_WIDTH {
-                    for j in 0..GAME_AREA_HEIGHT {
+                for i in 0..game_area_width {
+                    for j in 0..game_area_height {
                         let new_x: i32 = self.position[i][j].x as i32;
                         let new_y: i32 = self.position[i][j].y as i32;
 
-                        self.settlement_sprites[self.tile_improvement_level[i][j] as usize]
-                            .execute(|image| {
+                        let faction: faction_utility::Faction =
+                            if self.game_state.tile_owned_by[i][j] == tile_utility::NEUTRAL_OWNER {
+                                faction_utility::Faction::Kingdom
+                            } else {
+                                self.game_state.players_faction
+                                    [self.game_state.tile_owned_by[i][j] as usize]
+                            };
+                        let sprite_level: usize = (self.game_state.tile_improvement_level[i][j]
+                            as usize)
+                            .min(SETTLEMENT_NUMBER_OF_LEVELS - 1);
+                        self.settlement_sprites[faction.index()][sprite_level].execute(
+                            |image| {
                                 window.draw(
                                     &image.area().with_center((
                                         TILE_SIZE / 2 + new_x,
@@ -489,20 +1576,83 @@ impl State for BitterBoundaries {
                                     Img(&image),
                                 );
                                 Ok(())
-                            })?;
+                            },
+                        )?;
                     }
                 }
+
+                if let Some(graph_kind) = self.graph_kind {
+                    self.draw_graph(
+                        window,
+                        graph_kind,
+                        Rectangle::new(
+                            (0, 0),
+                            (
+                                game_area_width as i32 * TILE_SIZE,
+                                game_area_height as i32 * TILE_SIZE,
+                            ),
+                        ),
+                    )?;
+                }
+
+                if self.game_state.game_settings.victory_condition
+                    == victory_utility::VictoryCondition::KingOfTheHill
+                {
+                    let (hill_x, hill_y) =
+                        victory_utility::get_center_tile(game_area_width, game_area_height);
+                    draw_text(
+                        window,
+                        "King of the Hill".to_string(),
+                        fontstyle_white_12,
+                        (
+                            self.position[hill_x][hill_y].x as i32 + TILE_SIZE / 2,
+                            self.position[hill_x][hill_y].y as i32 + 14,
+                        ),
+                    )?;
+                }
+
+                if self.lockstep_session.is_some() {
+                    self.draw_chat_overlay(window)?;
+                }
             }
+        } else if self.discovery_browser.is_some() {
+            self.draw_game_browser(window)?;
         } else {
-            self.new_game_button_sprite.execute(|image| {
-                window.draw(
-                    &image
-                        .area()
-                        .with_center((VIEW_WIDTH as i32 / 2, VIEW_HEIGHT as i32 / 2)),
-                    Img(&image),
-                );
-                Ok(())
-            })?;
+            // Peers show the host's rules, which only the host can change.
+            let can_change_rules: bool = self
+                .lockstep_session
+                .as_ref()
+                .is_none_or(|lockstep_session| lockstep_session.is_host);
+            let menu_settings: game_settings::GameSettings = match &self.lockstep_session {
+                Some(lockstep_session) if !can_change_rules => lockstep_session
+                    .lobby_settings
+                    .clone()
+                    .unwrap_or_else(|| self.game_settings.clone()),
+                _ => self.game_settings.clone(),
+            };
+            let get_change_hint = |key: &str| -> String {
+                if can_change_rules {
+                    return " (".to_string() + key + " to change)";
+                }
+                return String::new();
+            };
+
+            match &self.lockstep_session {
+                Some(lockstep_session) => {
+                    self.draw_lobby(window, lockstep_session, &menu_settings)?;
+                }
+                None => {
+                    self.new_game_button_sprite.execute(|image| {
+                        window.draw(
+                            &image
+                                .area()
+                                .with_center((VIEW_WIDTH as i32 / 2, VIEW_HEIGHT as i32 / 2)),
+                            Img(&image),
+                        );
+                        Ok(())
+                    })?;
+                }
+            }
             self.exit_button_sprite.execute(|image| {
                 window.draw(
                     &image.area().with_center((
@@ -513,12 +1663,174 @@ impl State for BitterBoundaries {
                 );
                 Ok(())
             })?;
+            draw_text(
+                window,
+                "Mode: ".to_string() + menu_settings.game_mode.name() + &get_change_hint("M"),
+                fontstyle_white_12,
+                (VIEW_WIDTH as i32 / 2, VIEW_HEIGHT as i32 / 2 + TILE_SIZE),
+            )?;
+            draw_text(
+                window,
+                "Players: ".to_string()
+                    + &menu_settings.number_of_players.to_string()
+                    + &get_change_hint("P"),
+                fontstyle_white_12,
+                (
+                    VIEW_WIDTH as i32 / 2,
+                    VIEW_HEIGHT as i32 / 2 + TILE_SIZE + 20,
+                ),
+            )?;
+            draw_text(
+                window,
+                "Teams: ".to_string() + menu_settings.team_mode.name() + &get_change_hint("T"),
+                fontstyle_white_12,
+                (
+                    VIEW_WIDTH as i32 / 2,
+                    VIEW_HEIGHT as i32 / 2 + TILE_SIZE + 40,
+                ),
+            )?;
+            draw_text(
+                window,
+                "Faction: ".to_string()
+                    + self.game_settings.faction.name()
+                    + ", "
+                    + self.game_settings.faction.description()
+                    + " (F to change)",
+                fontstyle_white_12,
+                (
+                    VIEW_WIDTH as i32 / 2,
+                    VIEW_HEIGHT as i32 / 2 + TILE_SIZE + 60,
+                ),
+            )?;
+            draw_text(
+                window,
+                "Map: ".to_string()
+                    + &menu_settings.game_area_width.to_string()
+                    + "x"
+                    + &menu_settings.game_area_height.to_string()
+                    + &get_change_hint("S"),
+                fontstyle_white_12,
+                (
+                    VIEW_WIDTH as i32 / 2,
+                    VIEW_HEIGHT as i32 / 2 + TILE_SIZE + 80,
+                ),
+            )?;
+            draw_text(
+                window,
+                "Victory: ".to_string()
+                    + menu_settings.victory_condition.name()
+                    + ", "
+                    + &menu_settings.victory_condition.description()
+                    + &get_change_hint("V"),
+                fontstyle_white_12,
+                (VIEW_WIDTH as i32 / 2, VIEW_HEIGHT as i32 / 2 - TILE_SIZE),
+            )?;
+            if self.lockstep_session.is_none() {
+                let mut menu_lines: Vec<String> =
+                    vec!["Find games on the local network (B)".to_string()];
+                if !self.network_message.is_empty() {
+                    menu_lines.push(self.network_message.clone());
+                }
+                for (k, menu_line) in menu_lines.into_iter().enumerate() {
+                    draw_text(
+                        window,
+                        menu_line,
+                        fontstyle_white_12,
+                        (
+                            VIEW_WIDTH as i32 / 2,
+                            VIEW_HEIGHT as i32 / 2 + TILE_SIZE + 100 + k as i32 * 20,
+                        ),
+                    )?;
+                }
+            }
         }
 
         Ok(())
     }
 }
 
+fn get_player_color(player: i32, team_mode: game_settings::TeamMode) -> Color {
+    let (red, green, blue): (u8, u8, u8) = if player == tile_utility::NEUTRAL_OWNER {
+        NEUTRAL_COLOR
+    } else if team_mode == game_settings::TeamMode::FreeForAll {
+        PLAYER_COLORS[player as usize % PLAYER_COLORS.len()]
+    } else {
+        // Teammates share a color family so alliances are readable at a glance.
+        let (sprite, tint): (usize, Color) = get_tile_sprite(player, team_mode).unwrap();
+        let (red, green, blue): (u8, u8, u8) = PLAYER_COLORS[sprite];
+        return Color::from_rgba(red, green, blue, 1.0).multiply(tint);
+    };
+    return Color::from_rgba(red, green, blue, 1.0);
+}
+
+// The terrain sprite of a tile and the tint it is drawn with. Neutral tiles and the free-for-all
+// players past the sprite colors are drawn in plain color instead.
+fn get_tile_sprite(player: i32, team_mode: game_settings::TeamMode) -> Option<(usize, Color)> {
+    if player == tile_utility::NEUTRAL_OWNER {
+        return None;
+    } else if team_mode == game_settings::TeamMode::FreeForAll {
+        if (player as usize) < TERRAIN_SPRITES.len() {
+            return Some((player as usize, Color::WHITE));
+        }
+        return None;
+    }
+    let shade: f32 = TEAM_SHADES[player as usize % team_mode.get_team_size()];
+    return Some((
+        team_mode.get_team(player as usize) % TERRAIN_SPRITES.len(),
+        Color {
+            r: shade,
+            g: shade,
+            b: shade,
+            a: 1.0,
+        },
+    ));
+}
+
+fn draw_text(
+    window: &mut Window,
+    text: String,
+    font_style: FontStyle,
+    center: (i32, i32),
+) -> Result<()> {
+    let mut text_image: Asset<Image> = Asset::new(
+        Font::load("fonts/FiraCode-Regular.ttf")
+            .and_then(move |font| result(font.render(&text, &font_style))),
+    );
+    text_image.execute(|image| {
+        window.draw(&image.area().with_center(center), Img(&image));
+        Ok(())
+    })
+}
+
+fn get_game_browser_row_area(row: usize) -> Rectangle {
+    return Rectangle::new(
+        (VIEW_WIDTH as i32 / 2 - 400, 90 + row as i32 * 40),
+        (800, 32),
+    );
+}
+
+// Like `draw_text`, but the text starts at `left` instead of being centered.
+fn draw_text_left(
+    window: &mut Window,
+    text: String,
+    font_style: FontStyle,
+    (left, center_y): (i32, i32),
+) -> Result<()> {
+    let mut text_image: Asset<Image> = Asset::new(
+        Font::load("fonts/FiraCode-Regular.ttf")
+            .and_then(move |font| result(font.render(&text, &font_style))),
+    );
+    text_image.execute(|image| {
+        window.draw(
+            &image
+                .area()
+                .with_center((left as f32 + image.area().size.x / 2.0, center_y as f32)),
+            Img(&image),
+        );
+        Ok(())
+    })
+}
+
 fn main() {
     std::env::set_var("WINIT_HIDPI_FACTOR", "1.0");
     run::<BitterBoundaries>(
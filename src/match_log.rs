@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+pub struct MatchLogEntry {
+    pub tick: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActionLogEntry {
+    pub tick: u64,
+    pub player: usize,
+    pub action: String,
+    pub detail: String,
+    pub x: Option<usize>,
+    pub y: Option<usize>,
+    pub target_player: Option<usize>,
+    pub amount: f64,
+}
+
+pub fn add_entry(match_log: &mut Vec<MatchLogEntry>, tick: u64, message: String) {
+    match_log.push(MatchLogEntry { tick, message });
+}
+
+pub fn get_recent_entries(match_log: &Vec<MatchLogEntry>, count: usize) -> Vec<String> {
+    return match_log
+        .iter()
+        .skip(match_log.len().saturating_sub(count))
+        .map(|entry| "[".to_string() + &(entry.tick / 60).to_string() + "s] " + &entry.message)
+        .collect();
+}
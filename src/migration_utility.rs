@@ -0,0 +1,126 @@
+use crate::tile_utility;
+
+pub fn migrate_population(
+    tile_population_number: &mut Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    tile_improvement_level: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    migration_ratio: f64,
+) {
+    let mut population_change: Vec<Vec<i32>> = vec![vec![0; game_area_height]; game_area_width];
+
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == tile_utility::NEUTRAL_OWNER {
+                continue;
+            }
+            let destinations: Vec<(usize, usize)> =
+                tile_utility::get_adjacent_tiles(i, j, game_area_width, game_area_height)
+                    .into_iter()
+                    .filter(|&(x, y)| {
+                        tile_owned_by[x][y] == tile_owned_by[i][j]
+                            && tile_improvement_level[x][y] > tile_improvement_level[i][j]
+                    })
+                    .collect();
+            if destinations.is_empty() {
+                continue;
+            }
+
+            let migrants: i32 = ((tile_population_number[i][j] - 1) as f64 * migration_ratio
+                / destinations.len() as f64) as i32;
+            if migrants <= 0 {
+                continue;
+            }
+            for &(x, y) in destinations.iter() {
+                population_change[i][j] -= migrants;
+                population_change[x][y] += migrants;
+            }
+        }
+    }
+
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            tile_population_number[i][j] += population_change[i][j];
+        }
+    }
+}
+
+pub fn flee_population(
+    tile_population_number: &mut Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    tile_x: usize,
+    tile_y: usize,
+    game_area_width: usize,
+    game_area_height: usize,
+    refugees: i32,
+) -> i32 {
+    if tile_owned_by[tile_x][tile_y] == tile_utility::NEUTRAL_OWNER {
+        return 0;
+    }
+    let shelters: Vec<(usize, usize)> =
+        tile_utility::get_adjacent_tiles(tile_x, tile_y, game_area_width, game_area_height)
+            .into_iter()
+            .filter(|&(x, y)| tile_owned_by[x][y] == tile_owned_by[tile_x][tile_y])
+            .collect();
+    if shelters.is_empty() || refugees <= 0 {
+        return 0;
+    }
+
+    let refugees_per_shelter: i32 = refugees / shelters.len() as i32;
+    for &(x, y) in shelters.iter() {
+        tile_population_number[x][y] += refugees_per_shelter;
+    }
+    return refugees_per_shelter * shelters.len() as i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tile_utility::NEUTRAL_OWNER;
+
+    // Three columns and two rows, indexed `[x][y]`. Player 0 holds the top row, the bottom row
+    // is two neutral tiles and a tile of player 1.
+    fn get_tile_owned_by() -> Vec<Vec<i32>> {
+        return vec![vec![0, NEUTRAL_OWNER], vec![0, NEUTRAL_OWNER], vec![0, 1]];
+    }
+
+    #[test]
+    fn population_moves_to_larger_settlements_of_the_same_owner() {
+        let mut tile_population_number: Vec<Vec<i32>> = vec![vec![101; 2]; 3];
+        let tile_improvement_level: Vec<Vec<i32>> = vec![vec![0, 0], vec![2, 2], vec![1, 3]];
+        migrate_population(
+            &mut tile_population_number,
+            &get_tile_owned_by(),
+            &tile_improvement_level,
+            3,
+            2,
+            0.1,
+        );
+        assert_eq!(
+            tile_population_number,
+            vec![vec![91, 101], vec![121, 101], vec![91, 101]]
+        );
+    }
+
+    #[test]
+    fn refugees_flee_to_tiles_of_the_same_owner() {
+        let mut tile_population_number: Vec<Vec<i32>> = vec![vec![50; 2]; 3];
+        let tile_owned_by: Vec<Vec<i32>> = get_tile_owned_by();
+        assert_eq!(
+            flee_population(&mut tile_population_number, &tile_owned_by, 1, 0, 3, 2, 21),
+            20
+        );
+        assert_eq!(
+            tile_population_number,
+            vec![vec![60, 50], vec![50, 50], vec![60, 50]]
+        );
+
+        // A neutral tile has no friendly neighbours, even when they are neutral too.
+        assert_eq!(
+            flee_population(&mut tile_population_number, &tile_owned_by, 0, 1, 3, 2, 21),
+            0
+        );
+        assert_eq!(tile_population_number[1][1], 50);
+    }
+}
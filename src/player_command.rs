@@ -0,0 +1,297 @@
+use crate::ai::Action;
+use crate::diplomacy_utility::{self, DiplomaticState};
+use crate::economy_utility;
+use crate::game_state::{GameState, TEAM_TRANSFER_AMOUNT, TRIBUTE_AMOUNT};
+use crate::replay::ReplayCommand;
+use crate::tech_utility::{self, Technology};
+use crate::tile_utility;
+use serde::{Deserialize, Serialize};
+
+// Everything a human player can do during a match. Local input and input from other peers
+// both go through `apply_command`, so every peer changes the state the same way.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    Improve(usize, usize),
+    Capture(usize, usize),
+    Research(Technology),
+    TakeLoan,
+    Propose(usize, DiplomaticState),
+    AnswerProposal(bool),
+    TransferCash(usize),
+    SendTribute(usize),
+}
+
+pub fn get_command(action: Action) -> Option<PlayerCommand> {
+    match action {
+        Action::Improve(tile_x, tile_y) => Some(PlayerCommand::Improve(tile_x, tile_y)),
+        Action::Capture(tile_x, tile_y) => Some(PlayerCommand::Capture(tile_x, tile_y)),
+        Action::Pass => None,
+    }
+}
+
+fn check_cost(game_state: &GameState, player: usize, cost: f64) -> Result<(), String> {
+    if !game_state.can_afford(player, cost) {
+        return Err("costs ".to_string() + &(cost.round() as i64).to_string());
+    }
+    return Ok(());
+}
+
+fn check_tile(game_state: &GameState, tile_x: usize, tile_y: usize) -> Result<(), String> {
+    if tile_x >= game_state.game_area_width || tile_y >= game_state.game_area_height {
+        return Err("tile is outside the map".to_string());
+    }
+    return Ok(());
+}
+
+fn check_other_player(game_state: &GameState, player: usize, to: usize) -> Result<(), String> {
+    if to >= game_state.players_cash.len() || to == player {
+        return Err("unknown player ".to_string() + &to.to_string());
+    } else if game_state.players_eliminated[to] {
+        return Err("player ".to_string() + &to.to_string() + " is eliminated");
+    }
+    return Ok(());
+}
+
+// Checks a command against the rules and the player's cash without changing anything, so a
+// host or server can refuse it with a reason before it reaches the other players.
+pub fn check_command(
+    game_state: &GameState,
+    player: usize,
+    command: PlayerCommand,
+) -> Result<(), String> {
+    if player >= game_state.players_cash.len() || game_state.players_eliminated[player] {
+        return Err("player is not in the match".to_string());
+    }
+    match command {
+        PlayerCommand::Improve(tile_x, tile_y) => {
+            check_tile(game_state, tile_x, tile_y)?;
+            if game_state.tile_owned_by[tile_x][tile_y] != player as i32 {
+                return Err("tile is not yours".to_string());
+            }
+            return check_cost(
+                game_state,
+                player,
+                game_state.get_improvement_cost(
+                    player as i32,
+                    game_state.tile_improvement_level[tile_x][tile_y],
+                ),
+            );
+        }
+        PlayerCommand::Capture(tile_x, tile_y) => {
+            check_tile(game_state, tile_x, tile_y)?;
+            if !diplomacy_utility::can_attack(
+                &game_state.diplomacy,
+                player,
+                game_state.tile_owned_by[tile_x][tile_y],
+            ) {
+                return Err("tile owner is not at war with you".to_string());
+            }
+            if !tile_utility::has_adjacent_friendly_tile(
+                &game_state.tile_owned_by,
+                tile_x as i32,
+                tile_y as i32,
+                game_state.game_area_width,
+                game_state.game_area_height,
+                &diplomacy_utility::get_friendly_players(&game_state.diplomacy, player),
+            ) {
+                return Err("tile has no adjacent friendly tile".to_string());
+            }
+            return check_cost(
+                game_state,
+                player,
+                game_state.get_owner_change_cost(
+                    player as i32,
+                    game_state.tile_improvement_level[tile_x][tile_y],
+                ),
+            );
+        }
+        PlayerCommand::Research(technology) => {
+            if !tech_utility::can_start_research(
+                &game_state.players_research[player],
+                technology,
+                game_state.get_cash(player),
+            ) {
+                return Err("cannot research ".to_string() + technology.name() + " now");
+            }
+            return Ok(());
+        }
+        PlayerCommand::TakeLoan => {
+            if economy_utility::get_available_loan(
+                game_state.players_loan[player],
+                game_state.players_loan_limit[player],
+            ) <= 0
+            {
+                return Err("no loan available".to_string());
+            }
+            return Ok(());
+        }
+        PlayerCommand::Propose(to, _) => return check_other_player(game_state, player, to),
+        PlayerCommand::AnswerProposal(_) => return Ok(()),
+        PlayerCommand::TransferCash(to) => {
+            check_other_player(game_state, player, to)?;
+            if !game_state.is_teammate(player, to) {
+                return Err("player ".to_string() + &to.to_string() + " is not a teammate");
+            }
+            return check_cost(game_state, player, TEAM_TRANSFER_AMOUNT);
+        }
+        PlayerCommand::SendTribute(to) => {
+            check_other_player(game_state, player, to)?;
+            return check_cost(game_state, player, TRIBUTE_AMOUNT);
+        }
+    }
+}
+
+// Commands from the network are checked here, an invalid one does nothing and returns false.
+// Every applied command is recorded for the replay.
+pub fn apply_command(game_state: &mut GameState, player: usize, command: PlayerCommand) -> bool {
+    if check_command(game_state, player, command).is_err() {
+        return false;
+    }
+    game_state.command_log.push(ReplayCommand {
+        tick: game_state.tick,
+        player,
+        command,
+    });
+    match command {
+        PlayerCommand::Improve(tile_x, tile_y) => {
+            return game_state.apply_action(player, Action::Improve(tile_x, tile_y));
+        }
+        PlayerCommand::Capture(tile_x, tile_y) => {
+            return game_state.apply_action(player, Action::Capture(tile_x, tile_y));
+        }
+        PlayerCommand::Research(technology) => {
+            return game_state.start_research(player, technology);
+        }
+        PlayerCommand::TakeLoan => return game_state.take_loan(player),
+        PlayerCommand::Propose(to, state) => {
+            game_state.propose(player, to, state);
+            return true;
+        }
+        PlayerCommand::AnswerProposal(is_accepted) => {
+            game_state.answer_proposal(player, is_accepted);
+            return true;
+        }
+        PlayerCommand::TransferCash(to) => {
+            return game_state.transfer_cash(player, to, TEAM_TRANSFER_AMOUNT);
+        }
+        PlayerCommand::SendTribute(to) => {
+            return game_state.send_tribute(player, to, TRIBUTE_AMOUNT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_settings;
+
+    // A seeded 2v2 match on an empty map: players 0 and 1 are a team, 2 and 3 the other.
+    fn get_game_state() -> GameState {
+        let arguments: Vec<String> = ["bitter-boundaries", "--teams", "2v2", "--seed", "1"]
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+        let mut game_state: GameState = GameState::new(game_settings::parse_arguments(arguments));
+        for i in 0..game_state.game_area_width {
+            for j in 0..game_state.game_area_height {
+                game_state.tile_owned_by[i][j] = tile_utility::NEUTRAL_OWNER;
+            }
+        }
+        game_state.tile_owned_by[1][1] = 0;
+        game_state.tile_owned_by[2][2] = 2;
+        for player in 0..game_state.players_cash.len() {
+            game_state.players_cash[player] = economy_utility::to_cash_units(100000.0);
+        }
+        return game_state;
+    }
+
+    #[test]
+    fn tiles_outside_the_map_are_rejected() {
+        let game_state: GameState = get_game_state();
+        let width: usize = game_state.game_area_width;
+        let height: usize = game_state.game_area_height;
+        assert!(check_command(&game_state, 0, PlayerCommand::Improve(width, 0)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::Capture(0, height)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::Improve(1, 1)).is_ok());
+    }
+
+    #[test]
+    fn captures_need_an_adjacent_tile_and_a_war() {
+        let mut game_state: GameState = get_game_state();
+        assert!(check_command(&game_state, 0, PlayerCommand::Improve(2, 2)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::Capture(5, 1)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::Capture(2, 2)).is_ok());
+
+        game_state.set_diplomatic_state(0, 2, DiplomaticState::NonAggression);
+        assert!(check_command(&game_state, 0, PlayerCommand::Capture(2, 2)).is_err());
+    }
+
+    #[test]
+    fn unaffordable_commands_are_rejected() {
+        let mut game_state: GameState = get_game_state();
+        game_state.players_cash[0] = 0;
+        assert!(check_command(&game_state, 0, PlayerCommand::Improve(1, 1)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::Capture(2, 2)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::TransferCash(1)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::SendTribute(2)).is_err());
+    }
+
+    #[test]
+    fn cash_goes_to_other_players_and_transfers_to_teammates_only() {
+        let game_state: GameState = get_game_state();
+        assert!(check_command(&game_state, 0, PlayerCommand::TransferCash(0)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::TransferCash(2)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::TransferCash(4)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::TransferCash(1)).is_ok());
+
+        assert!(check_command(&game_state, 0, PlayerCommand::SendTribute(0)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::SendTribute(4)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::SendTribute(2)).is_ok());
+    }
+
+    #[test]
+    fn eliminated_players_can_neither_act_nor_be_targeted() {
+        let mut game_state: GameState = get_game_state();
+        game_state.players_eliminated[2] = true;
+        assert!(check_command(&game_state, 0, PlayerCommand::SendTribute(2)).is_err());
+        assert!(check_command(
+            &game_state,
+            0,
+            PlayerCommand::Propose(2, DiplomaticState::Alliance)
+        )
+        .is_err());
+
+        game_state.players_eliminated[0] = true;
+        assert!(check_command(&game_state, 0, PlayerCommand::Improve(1, 1)).is_err());
+        assert!(check_command(&game_state, 0, PlayerCommand::AnswerProposal(true)).is_err());
+        assert!(check_command(&game_state, 4, PlayerCommand::TakeLoan).is_err());
+    }
+
+    #[test]
+    fn only_applied_commands_are_logged() {
+        let mut game_state: GameState = get_game_state();
+        assert!(!apply_command(
+            &mut game_state,
+            0,
+            PlayerCommand::Improve(2, 2)
+        ));
+        assert!(!apply_command(
+            &mut game_state,
+            0,
+            PlayerCommand::TransferCash(2)
+        ));
+        assert!(game_state.command_log.is_empty());
+
+        assert!(apply_command(
+            &mut game_state,
+            0,
+            PlayerCommand::Improve(1, 1)
+        ));
+        assert_eq!(game_state.command_log.len(), 1);
+        assert_eq!(game_state.command_log[0].player, 0);
+        assert_eq!(
+            game_state.command_log[0].command,
+            PlayerCommand::Improve(1, 1)
+        );
+    }
+}
@@ -1,35 +1,9 @@
-pub fn get_type_of_settlement(number_of_population: i32) -> String {
-    if number_of_population > 10000000 {
-        return String::from("megapolis");
-    } else if number_of_population > 1000000 {
-        return String::from("metropolis");
-    } else if number_of_population > 500000 {
-        return String::from("great city");
-    } else if number_of_population > 100000 {
-        return String::from("city");
-    } else if number_of_population > 50000 {
-        return String::from("big town");
-    } else if number_of_population > 20000 {
-        return String::from("town");
-    } else if number_of_population > 10000 {
-        return String::from("little town");
-    } else if number_of_population > 5000 {
-        return String::from("giant village");
-    } else if number_of_population > 2000 {
-        return String::from("large village");
-    } else if number_of_population > 1000 {
-        return String::from("village");
-    } else if number_of_population > 500 {
-        return String::from("small village");
-    } else if number_of_population > 100 {
-        return String::from("little village");
-    } else {
-        return String::from("hamlet");
-    }
-}
-
 pub fn get_level_of_settlement(number_of_population: i32) -> i32 {
-    if number_of_population > 10000000 {
+    if number_of_population > 100000000 {
+        return 14;
+    } else if number_of_population > 50000000 {
+        return 13;
+    } else if number_of_population > 10000000 {
         return 12;
     } else if number_of_population > 1000000 {
         return 11;
@@ -76,6 +50,54 @@ pub fn get_total_population(
     return total_population;
 }
 
-pub fn get_cash(total_population: i32) -> f64 {
-    return 4.0 * (get_level_of_settlement(total_population) + 1) as f64;
+// Income grows with the settlement level of the whole population, up to the highest level the
+// player has unlocked.
+pub fn get_cash(total_population: i32, max_level_of_settlement: i32) -> f64 {
+    return 4.0
+        * (get_level_of_settlement(total_population).min(max_level_of_settlement) + 1) as f64;
+}
+
+pub fn get_type_of_settlement_level(level_of_settlement: i32) -> String {
+    let settlement_types: [&str; 15] = [
+        "hamlet",
+        "little village",
+        "small village",
+        "village",
+        "large village",
+        "giant village",
+        "little town",
+        "town",
+        "big town",
+        "city",
+        "great city",
+        "metropolis",
+        "megapolis",
+        "conurbation",
+        "megalopolis",
+    ];
+    return String::from(settlement_types[level_of_settlement.clamp(0, 14) as usize]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tech_utility;
+
+    #[test]
+    fn income_stops_at_the_unlocked_level() {
+        let level_12_income: f64 = get_cash(20000000, tech_utility::BASE_MAX_SETTLEMENT_LEVEL);
+        assert_eq!(level_12_income, 52.0);
+        assert_eq!(
+            get_cash(60000000, tech_utility::BASE_MAX_SETTLEMENT_LEVEL),
+            level_12_income
+        );
+        assert_eq!(
+            get_cash(60000000, tech_utility::URBAN_PLANNING_MAX_SETTLEMENT_LEVEL),
+            56.0
+        );
+        assert_eq!(
+            get_cash(200000000, tech_utility::URBAN_PLANNING_MAX_SETTLEMENT_LEVEL),
+            60.0
+        );
+    }
 }
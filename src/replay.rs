@@ -0,0 +1,233 @@
+use crate::ai::{self, Ai};
+use crate::desync_utility::{self, StateDump};
+use crate::game_settings::GameSettings;
+use crate::game_state::GameState;
+use crate::player_command::{self, PlayerCommand};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+
+pub const REPLAY_VERSION: u32 = 2;
+
+// A command is applied to the state of `tick`, before the next tick is simulated.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReplayCommand {
+    pub tick: u64,
+    pub player: usize,
+    pub command: PlayerCommand,
+}
+
+// A seat handed to the AI when its player lost the connection, or back when they rejoined.
+// Applied to the state of `tick` before the commands of that tick.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SeatChange {
+    pub tick: u64,
+    pub player: usize,
+    pub is_ai: bool,
+}
+
+// A chat line of a network match at the tick of the frame that carried it. Chat does not
+// change the match, it is kept for whoever watches the replay.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub tick: u64,
+    pub name: String,
+    pub text: String,
+}
+
+// Everything needed to play a match again: the settings with the seed, the commands of the
+// human players and state dumps to check the replayed match against.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub game_settings: GameSettings,
+    pub commands: Vec<ReplayCommand>,
+    #[serde(default)]
+    pub seat_changes: Vec<SeatChange>,
+    #[serde(default)]
+    pub chat: Vec<ChatMessage>,
+    pub checkpoints: Vec<StateDump>,
+}
+
+pub fn get_replay(game_state: &GameState) -> Replay {
+    let mut game_settings: GameSettings = game_state.game_settings.clone();
+    game_settings.seed = Some(game_state.seed);
+    let mut checkpoints: Vec<StateDump> = game_state.checkpoints.clone();
+    if checkpoints.last().map(|checkpoint| checkpoint.tick) != Some(game_state.tick) {
+        checkpoints.push(desync_utility::get_state_dump(game_state));
+    }
+    return Replay {
+        version: REPLAY_VERSION,
+        game_settings,
+        commands: game_state.command_log.clone(),
+        seat_changes: game_state.seat_changes.clone(),
+        chat: game_state.chat_log.clone(),
+        checkpoints,
+    };
+}
+
+pub fn write_replay(path: &str, replay: &Replay) -> io::Result<()> {
+    let mut file: File = File::create(path)?;
+    file.write_all(serde_json::to_string(replay)?.as_bytes())?;
+    return Ok(());
+}
+
+pub fn read_replay(path: &str) -> io::Result<Replay> {
+    let replay: Replay = serde_json::from_str(&fs::read_to_string(path)?)?;
+    return Ok(replay);
+}
+
+// Position in the recorded commands and seat changes while a match is played again.
+struct Playback {
+    next_command: usize,
+    next_seat_change: usize,
+}
+
+// Steps the match up to `tick` with the recorded seat changes and commands.
+fn play_until(
+    game_state: &mut GameState,
+    ais: &mut Vec<Option<Box<dyn Ai>>>,
+    commands: &Vec<ReplayCommand>,
+    seat_changes: &Vec<SeatChange>,
+    tick: u64,
+    playback: &mut Playback,
+) -> Result<(), String> {
+    while game_state.tick < tick && !game_state.is_win {
+        while playback.next_seat_change < seat_changes.len()
+            && seat_changes[playback.next_seat_change].tick <= game_state.tick
+        {
+            let seat_change: SeatChange = seat_changes[playback.next_seat_change];
+            game_state.set_ai_controlled(ais, seat_change.player, seat_change.is_ai)?;
+            playback.next_seat_change += 1;
+        }
+        while playback.next_command < commands.len()
+            && commands[playback.next_command].tick <= game_state.tick
+        {
+            let replay_command: ReplayCommand = commands[playback.next_command];
+            player_command::apply_command(
+                game_state,
+                replay_command.player,
+                replay_command.command,
+            );
+            playback.next_command += 1;
+        }
+        game_state.step(ais);
+    }
+    return Ok(());
+}
+
+// A rebuilt match and the AIs that play its AI seats.
+pub type RebuiltMatch = (GameState, Vec<Option<Box<dyn Ai>>>);
+
+// Rebuilds a running match from its history, for a player who rejoins it. The seat changes
+// and commands of `tick` itself still come with the next frame.
+pub fn play_to_tick(
+    game_settings: GameSettings,
+    commands: &Vec<ReplayCommand>,
+    seat_changes: &Vec<SeatChange>,
+    tick: u64,
+) -> Result<RebuiltMatch, String> {
+    let mut game_state: GameState = GameState::new(game_settings);
+    let mut ais: Vec<Option<Box<dyn Ai>>> = ai::get_opponent_ais(&game_state)?;
+    let mut playback: Playback = Playback {
+        next_command: 0,
+        next_seat_change: 0,
+    };
+    play_until(
+        &mut game_state,
+        &mut ais,
+        commands,
+        seat_changes,
+        tick,
+        &mut playback,
+    )?;
+    return Ok((game_state, ais));
+}
+
+// Why a replay could not be verified.
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    // The recorded opponent cannot be started.
+    Opponent(String),
+    // The recorded and the replayed state at the first difference.
+    Desync(Box<(StateDump, StateDump)>),
+}
+
+// Plays the match again with the recorded commands and compares every checkpoint. Returns the
+// last tick on success. Matches against external bots cannot be verified, see `ai::is_bot_name`.
+pub fn verify_replay(replay: &Replay) -> Result<u64, VerifyError> {
+    let mut game_state: GameState = GameState::new(replay.game_settings.clone());
+    let mut ais: Vec<Option<Box<dyn Ai>>> =
+        ai::get_opponent_ais(&game_state).map_err(VerifyError::Opponent)?;
+
+    let mut playback: Playback = Playback {
+        next_command: 0,
+        next_seat_change: 0,
+    };
+    for checkpoint in replay.checkpoints.iter() {
+        play_until(
+            &mut game_state,
+            &mut ais,
+            &replay.commands,
+            &replay.seat_changes,
+            checkpoint.tick,
+            &mut playback,
+        )
+        .map_err(VerifyError::Opponent)?;
+        let state_dump: StateDump = desync_utility::get_state_dump(&game_state);
+        if state_dump != *checkpoint {
+            return Err(VerifyError::Desync(Box::new((
+                checkpoint.clone(),
+                state_dump,
+            ))));
+        }
+    }
+    return Ok(game_state.tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility;
+
+    #[test]
+    fn replay_survives_a_round_trip_through_a_file() {
+        let replay: Replay = get_replay(&test_utility::play_match(11, 1, 900));
+        assert!(!replay.commands.is_empty());
+        let path: String = std::env::temp_dir()
+            .join("bitter_boundaries_replay_test.json")
+            .to_string_lossy()
+            .to_string();
+        write_replay(&path, &replay).unwrap();
+        let read: Replay = read_replay(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(read.version, REPLAY_VERSION);
+        assert_eq!(read.game_settings.seed, Some(11));
+        assert_eq!(read.commands, replay.commands);
+        assert_eq!(read.seat_changes, replay.seat_changes);
+        assert_eq!(read.chat, replay.chat);
+        assert_eq!(read.checkpoints, replay.checkpoints);
+    }
+
+    #[test]
+    fn recorded_match_verifies() {
+        let game_state: GameState = test_utility::play_match(11, 1, 900);
+        let replay: Replay = get_replay(&game_state);
+        assert_eq!(verify_replay(&replay), Ok(game_state.tick));
+    }
+
+    #[test]
+    fn changed_checkpoint_fails_verification() {
+        let mut replay: Replay = get_replay(&test_utility::play_match(11, 1, 900));
+        replay.checkpoints[1].players_cash[0] += 1;
+        match verify_replay(&replay) {
+            Err(VerifyError::Desync(states)) => {
+                let (recorded, replayed) = *states;
+                assert_eq!(recorded.tick, replay.checkpoints[1].tick);
+                assert_eq!(recorded.players_cash[0], replayed.players_cash[0] + 1);
+            }
+            result => panic!("expected a desync, got {:?}", result),
+        }
+    }
+}
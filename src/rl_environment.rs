@@ -0,0 +1,388 @@
+use crate::ai::{self, Action, Ai};
+use crate::diplomacy_utility;
+use crate::economy_utility;
+use crate::game_settings::{self, GameSettings};
+use crate::game_state::GameState;
+use crate::simulation_utility;
+use crate::tech_utility;
+use crate::tile_utility;
+use std::cell::Cell;
+use std::rc::Rc;
+
+pub const OBSERVATION_CHANNELS: usize = 6;
+pub const CHANNEL_OWN: usize = 0;
+pub const CHANNEL_FRIENDLY: usize = 1;
+pub const CHANNEL_ENEMY: usize = 2;
+pub const CHANNEL_NEUTRAL: usize = 3;
+pub const CHANNEL_POPULATION: usize = 4;
+pub const CHANNEL_LEVEL: usize = 5;
+pub const OBSERVATION_FEATURES: usize = 4;
+
+pub const POPULATION_SCALE: f64 = 10000.0;
+pub const CASH_SCALE: f64 = 10000.0;
+
+// Rewards are paid every step from the change of the agent's position, plus once at the end.
+#[derive(Clone, Copy, Debug)]
+pub struct RewardShaping {
+    pub win: f64,
+    pub loss: f64,
+    pub draw: f64,
+    pub per_tile: f64,
+    pub per_population: f64,
+    pub per_cash: f64,
+    pub per_step: f64,
+}
+
+impl RewardShaping {
+    // Only the outcome of the match counts.
+    pub fn sparse() -> RewardShaping {
+        return RewardShaping {
+            win: 1.0,
+            loss: -1.0,
+            draw: 0.0,
+            per_tile: 0.0,
+            per_population: 0.0,
+            per_cash: 0.0,
+            per_step: 0.0,
+        };
+    }
+
+    pub fn dense() -> RewardShaping {
+        return RewardShaping {
+            win: 1.0,
+            loss: -1.0,
+            draw: 0.0,
+            per_tile: 0.05,
+            per_population: 0.00001,
+            per_cash: 0.0,
+            per_step: 0.0,
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvironmentSettings {
+    pub game_settings: GameSettings,
+    pub agent_player: usize,
+    pub opponent: String,
+    pub ticks_per_step: u64,
+    pub max_ticks: u64,
+    pub reward_shaping: RewardShaping,
+}
+
+impl EnvironmentSettings {
+    pub fn new(game_settings: GameSettings) -> EnvironmentSettings {
+        return EnvironmentSettings {
+            game_settings,
+            agent_player: 0,
+            opponent: "random".to_string(),
+            ticks_per_step: 1,
+            max_ticks: simulation_utility::DEFAULT_MAX_TICKS,
+            reward_shaping: RewardShaping::sparse(),
+        };
+    }
+}
+
+// Grids are padded to the largest map, so every map size gives the same shape.
+// `grids` is indexed [channel][y][x] flattened, `features` holds the agent's cash, loan,
+// number of finished technologies and the share of the tick limit that has passed.
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub grids: Vec<f32>,
+    pub features: Vec<f32>,
+}
+
+impl Observation {
+    pub fn get_grid_shape() -> (usize, usize, usize) {
+        return (
+            OBSERVATION_CHANNELS,
+            game_settings::MAX_GAME_AREA_SIZE,
+            game_settings::MAX_GAME_AREA_SIZE,
+        );
+    }
+
+    pub fn get(&self, channel: usize, tile_x: usize, tile_y: usize) -> f32 {
+        let size: usize = game_settings::MAX_GAME_AREA_SIZE;
+        return self.grids[(channel * size + tile_y) * size + tile_x];
+    }
+}
+
+// Plays the action it is handed by the environment, so the agent gets the same automatic
+// research, loans and diplomacy as the built-in AIs.
+struct AgentAi {
+    next_action: Rc<Cell<Action>>,
+}
+
+impl Ai for AgentAi {
+    fn name(&self) -> &str {
+        return "agent";
+    }
+
+    fn choose_action(&mut self, _game_state: &GameState, _player: usize) -> Action {
+        return self.next_action.replace(Action::Pass);
+    }
+}
+
+// Action `0` passes, action `1 + 2 * (y * MAX_GAME_AREA_SIZE + x)` improves the tile at x, y
+// and the one after it captures that tile.
+pub fn get_number_of_actions() -> usize {
+    return 1 + 2 * game_settings::MAX_GAME_AREA_SIZE * game_settings::MAX_GAME_AREA_SIZE;
+}
+
+pub fn get_action_index(action: Action) -> usize {
+    let size: usize = game_settings::MAX_GAME_AREA_SIZE;
+    match action {
+        Action::Pass => 0,
+        Action::Improve(tile_x, tile_y) => 1 + 2 * (tile_y * size + tile_x),
+        Action::Capture(tile_x, tile_y) => 2 + 2 * (tile_y * size + tile_x),
+    }
+}
+
+pub fn get_action(action_index: usize) -> Option<Action> {
+    let size: usize = game_settings::MAX_GAME_AREA_SIZE;
+    if action_index == 0 {
+        return Some(Action::Pass);
+    } else if action_index >= get_number_of_actions() {
+        return None;
+    }
+    let tile_index: usize = (action_index - 1) / 2;
+    let (tile_x, tile_y) = (tile_index % size, tile_index / size);
+    if (action_index - 1).is_multiple_of(2) {
+        return Some(Action::Improve(tile_x, tile_y));
+    } else {
+        return Some(Action::Capture(tile_x, tile_y));
+    }
+}
+
+pub struct Environment {
+    pub settings: EnvironmentSettings,
+    pub game_state: GameState,
+    ais: Vec<Option<Box<dyn Ai>>>,
+    next_action: Rc<Cell<Action>>,
+    previous_tiles: usize,
+    previous_population: i32,
+    previous_cash: f64,
+}
+
+impl Environment {
+    // Fails when the opponent AI cannot be created.
+    pub fn new(settings: EnvironmentSettings) -> Result<Environment, String> {
+        let mut game_settings: GameSettings = settings.game_settings.clone();
+        game_settings.number_of_humans = 0;
+        let mut environment: Environment = Environment {
+            game_state: GameState::new(game_settings),
+            settings,
+            ais: Vec::new(),
+            next_action: Rc::new(Cell::new(Action::Pass)),
+            previous_tiles: 0,
+            previous_population: 0,
+            previous_cash: 0.0,
+        };
+        environment.reset(0)?;
+        return Ok(environment);
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Result<Observation, String> {
+        let mut game_settings: GameSettings = self.settings.game_settings.clone();
+        game_settings.number_of_humans = 0;
+        game_settings.seed = Some(seed);
+        self.game_state = GameState::new(game_settings);
+
+        let agent_player: usize = self.settings.agent_player;
+        self.next_action.set(Action::Pass);
+        self.ais = Vec::new();
+        for player in 0..self.game_state.players_cash.len() {
+            if player == agent_player {
+                self.ais.push(Some(Box::new(AgentAi {
+                    next_action: self.next_action.clone(),
+                })));
+            } else {
+                self.ais.push(Some(ai::get_ai(
+                    &self.settings.opponent,
+                    seed.wrapping_add(player as u64),
+                )?));
+            }
+        }
+
+        self.previous_tiles = self.game_state.get_number_of_tiles(agent_player);
+        self.previous_population = self.game_state.get_total_population(agent_player);
+        self.previous_cash = self.game_state.get_cash(agent_player);
+        return Ok(self.get_observation());
+    }
+
+    // Illegal or unknown actions are played as a pass. Legal actions the agent cannot afford,
+    // even with a loan, do nothing.
+    pub fn step(&mut self, action_index: usize) -> (Observation, f64, bool) {
+        if self.is_done() {
+            return (self.get_observation(), 0.0, true);
+        }
+
+        let agent_player: usize = self.settings.agent_player;
+        let action: Action = match get_action(action_index) {
+            Some(action) if self.game_state.is_legal_action(agent_player, action) => action,
+            _ => Action::Pass,
+        };
+        self.next_action.set(action);
+        for _ in 0..self.settings.ticks_per_step.max(1) {
+            self.game_state.step(&mut self.ais);
+            if self.is_done() {
+                break;
+            }
+        }
+
+        let reward_shaping: RewardShaping = self.settings.reward_shaping;
+        let number_of_tiles: usize = self.game_state.get_number_of_tiles(agent_player);
+        let total_population: i32 = self.game_state.get_total_population(agent_player);
+        let cash: f64 = self.game_state.get_cash(agent_player);
+        let mut reward: f64 = reward_shaping.per_step
+            + reward_shaping.per_tile * (number_of_tiles as f64 - self.previous_tiles as f64)
+            + reward_shaping.per_population * (total_population - self.previous_population) as f64
+            + reward_shaping.per_cash * (cash - self.previous_cash);
+        self.previous_tiles = number_of_tiles;
+        self.previous_population = total_population;
+        self.previous_cash = cash;
+
+        let done: bool = self.is_done();
+        if done {
+            reward += if !self.game_state.is_win {
+                reward_shaping.draw
+            } else if self.game_state.winner_team == self.game_state.get_team(agent_player) {
+                reward_shaping.win
+            } else {
+                reward_shaping.loss
+            };
+        }
+        return (self.get_observation(), reward, done);
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.game_state.is_win
+            || self.game_state.tick >= self.settings.max_ticks
+            || self.game_state.players_eliminated[self.settings.agent_player];
+    }
+
+    pub fn legal_actions(&self) -> Vec<usize> {
+        let agent_player: usize = self.settings.agent_player;
+        let mut legal_actions: Vec<usize> = vec![get_action_index(Action::Pass)];
+        for j in 0..self.game_state.game_area_height {
+            for i in 0..self.game_state.game_area_width {
+                for &action in [Action::Improve(i, j), Action::Capture(i, j)].iter() {
+                    if self.game_state.is_legal_action(agent_player, action) {
+                        legal_actions.push(get_action_index(action));
+                    }
+                }
+            }
+        }
+        return legal_actions;
+    }
+
+    pub fn get_observation(&self) -> Observation {
+        let (channels, height, width) = Observation::get_grid_shape();
+        let agent_player: usize = self.settings.agent_player;
+        let mut grids: Vec<f32> = vec![0.0; channels * height * width];
+        for i in 0..self.game_state.game_area_width {
+            for j in 0..self.game_state.game_area_height {
+                let owner: i32 = self.game_state.tile_owned_by[i][j];
+                let owner_channel: usize = if owner == agent_player as i32 {
+                    CHANNEL_OWN
+                } else if owner == tile_utility::NEUTRAL_OWNER {
+                    CHANNEL_NEUTRAL
+                } else if self.game_state.diplomacy[agent_player][owner as usize]
+                    == diplomacy_utility::DiplomaticState::War
+                {
+                    CHANNEL_ENEMY
+                } else {
+                    CHANNEL_FRIENDLY
+                };
+                grids[(owner_channel * height + j) * width + i] = 1.0;
+                grids[(CHANNEL_POPULATION * height + j) * width + i] =
+                    (self.game_state.tile_population_number[i][j] as f64 / POPULATION_SCALE) as f32;
+                grids[(CHANNEL_LEVEL * height + j) * width + i] =
+                    (self.game_state.tile_improvement_level[i][j] as f64
+                        / tech_utility::URBAN_PLANNING_MAX_SETTLEMENT_LEVEL as f64)
+                        as f32;
+            }
+        }
+
+        let features: Vec<f32> = vec![
+            (self.game_state.get_cash(agent_player) / CASH_SCALE) as f32,
+            (economy_utility::from_cash_units(self.game_state.players_loan[agent_player])
+                / CASH_SCALE) as f32,
+            self.game_state.players_research[agent_player]
+                .researched
+                .len() as f32,
+            (self.game_state.tick as f64 / self.settings.max_ticks.max(1) as f64) as f32,
+        ];
+        return Observation { grids, features };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_environment() -> Environment {
+        let mut settings: EnvironmentSettings =
+            EnvironmentSettings::new(game_settings::parse_arguments(Vec::new()));
+        settings.ticks_per_step = 10;
+        settings.max_ticks = 3000;
+        return Environment::new(settings).unwrap();
+    }
+
+    #[test]
+    fn action_indices_round_trip() {
+        for action_index in 0..get_number_of_actions() {
+            let action: Action = get_action(action_index).unwrap();
+            assert_eq!(get_action_index(action), action_index);
+        }
+        assert!(get_action(get_number_of_actions()).is_none());
+    }
+
+    #[test]
+    fn legal_actions_are_played() {
+        let mut environment: Environment = get_environment();
+        environment.reset(3).unwrap();
+        let agent_player: usize = environment.settings.agent_player;
+        while !environment.is_done() {
+            let legal_actions: Vec<usize> = environment.legal_actions();
+            assert_eq!(legal_actions[0], get_action_index(Action::Pass));
+            // `step` plays exactly the actions that map back to a legal action.
+            for &action_index in legal_actions.iter() {
+                let action: Action = get_action(action_index).unwrap();
+                assert!(environment.game_state.is_legal_action(agent_player, action));
+            }
+            // Passing in between leaves cash to capture with.
+            let action_index: usize = if environment.game_state.tick % 100 < 50 {
+                legal_actions[legal_actions.len() - 1]
+            } else {
+                0
+            };
+            environment.step(action_index);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_observations() {
+        let mut environment: Environment = get_environment();
+        let mut other_environment: Environment = get_environment();
+        let first_observation: Observation = environment.reset(5).unwrap();
+        let other_observation: Observation = other_environment.reset(5).unwrap();
+        assert_eq!(first_observation.grids, other_observation.grids);
+        assert_eq!(first_observation.features, other_observation.features);
+
+        for _ in 0..100 {
+            let legal_actions: Vec<usize> = environment.legal_actions();
+            let action_index: usize = legal_actions[legal_actions.len() - 1];
+            let (observation, reward, done) = environment.step(action_index);
+            let (other_observation, other_reward, other_done) =
+                other_environment.step(action_index);
+            assert_eq!(observation.grids, other_observation.grids);
+            assert_eq!(observation.features, other_observation.features);
+            assert_eq!(reward, other_reward);
+            assert_eq!(done, other_done);
+        }
+
+        // A reset starts the same match again.
+        assert_eq!(environment.reset(5).unwrap().grids, first_observation.grids);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::ai::{self, Ai};
+use crate::game_settings::GameSettings;
+use crate::game_state::GameState;
+
+pub const DEFAULT_MAX_TICKS: u64 = 36000;
+
+pub struct MatchResult {
+    pub ai_names: Vec<String>,
+    // None when the match hit the tick limit without a winner.
+    pub winner_team: Option<usize>,
+    pub ticks: u64,
+}
+
+// Plays a match between AIs only, seat `player` is controlled by `ai_names[player]`.
+pub fn play_match(
+    game_settings: &GameSettings,
+    ai_names: Vec<String>,
+    max_ticks: u64,
+) -> Result<MatchResult, String> {
+    let mut game_settings: GameSettings = game_settings.clone();
+    game_settings.number_of_humans = 0;
+    let mut game_state: GameState = GameState::new(game_settings);
+    let mut ais: Vec<Option<Box<dyn Ai>>> = Vec::new();
+    for (player, ai_name) in ai_names.iter().enumerate() {
+        ais.push(Some(ai::get_ai(
+            ai_name,
+            game_state.seed.wrapping_add(player as u64),
+        )?));
+    }
+    while !game_state.is_win && game_state.tick < max_ticks {
+        game_state.step(&mut ais);
+    }
+    return Ok(MatchResult {
+        ai_names,
+        winner_team: if game_state.is_win {
+            Some(game_state.winner_team)
+        } else {
+            None
+        },
+        ticks: game_state.tick,
+    });
+}
+
+pub fn get_percentage(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    return count as f64 / total as f64 * 100.0;
+}
@@ -0,0 +1,147 @@
+use crate::tile_utility;
+use serde::Serialize;
+
+pub const SAMPLE_INTERVAL_TICKS: u64 = 300;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PlayerSample {
+    pub population: i32,
+    pub cash: f64,
+    pub number_of_tiles: usize,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PlayerStatistics {
+    pub tiles_captured: u32,
+    pub tiles_lost: u32,
+    pub peak_population: i32,
+    pub total_spending: f64,
+}
+
+pub fn new_statistics(number_of_players: usize) -> Vec<PlayerStatistics> {
+    return vec![
+        PlayerStatistics {
+            tiles_captured: 0,
+            tiles_lost: 0,
+            peak_population: 0,
+            total_spending: 0.0,
+        };
+        number_of_players
+    ];
+}
+
+pub fn record_ownership_changes(
+    statistics: &mut Vec<PlayerStatistics>,
+    previous_tile_owned_by: &Vec<Vec<i32>>,
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+) {
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            let previous_owner: i32 = previous_tile_owned_by[i][j];
+            let owner: i32 = tile_owned_by[i][j];
+            if previous_owner == owner {
+                continue;
+            }
+            if previous_owner != tile_utility::NEUTRAL_OWNER {
+                statistics[previous_owner as usize].tiles_lost += 1;
+            }
+            if owner != tile_utility::NEUTRAL_OWNER {
+                statistics[owner as usize].tiles_captured += 1;
+            }
+        }
+    }
+}
+
+pub fn record_population(statistics: &mut PlayerStatistics, total_population: i32) {
+    statistics.peak_population = statistics.peak_population.max(total_population);
+}
+
+pub fn get_time_played(ticks: u64) -> String {
+    let seconds: u64 = ticks / 60;
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Ai;
+    use crate::economy_utility;
+    use crate::game_settings;
+    use crate::game_state::{GameState, BANKRUPTCY_CASH_LIMIT};
+    use crate::test_utility;
+    use crate::victory_utility;
+    use tile_utility::NEUTRAL_OWNER;
+
+    #[test]
+    fn ownership_changes_count_captures_and_losses() {
+        let previous_tile_owned_by: Vec<Vec<i32>> =
+            vec![vec![0, 1, NEUTRAL_OWNER], vec![0, 2, 2], vec![1, 1, 0]];
+        // (0, 1) is captured by player 0, (0, 2) by player 1 from no one, (1, 0) revolts,
+        // player 2 is eliminated and (2, 2) is untouched.
+        let tile_owned_by: Vec<Vec<i32>> = vec![
+            vec![0, 0, 1],
+            vec![NEUTRAL_OWNER, NEUTRAL_OWNER, NEUTRAL_OWNER],
+            vec![1, 1, 0],
+        ];
+        let mut statistics: Vec<PlayerStatistics> = new_statistics(3);
+        record_ownership_changes(
+            &mut statistics,
+            &previous_tile_owned_by,
+            &tile_owned_by,
+            3,
+            3,
+        );
+        let counts: Vec<(u32, u32)> = statistics
+            .iter()
+            .map(|statistics| (statistics.tiles_captured, statistics.tiles_lost))
+            .collect();
+        assert_eq!(counts, vec![(1, 1), (1, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn peak_population_keeps_the_highest_total() {
+        let mut statistics: Vec<PlayerStatistics> = new_statistics(1);
+        for total_population in [300, 1200, 800].iter() {
+            record_population(&mut statistics[0], *total_population);
+        }
+        assert_eq!(statistics[0].peak_population, 1200);
+    }
+
+    #[test]
+    fn match_statistics_follow_spending_eliminations_and_score() {
+        let mut game_state: GameState = GameState::new(game_settings::parse_arguments(Vec::new()));
+        let mut ais: Vec<Option<Box<dyn Ai>>> = vec![None, None];
+        let (tile_x, tile_y) = test_utility::get_own_tile(&game_state, 0).unwrap();
+        game_state.players_cash[0] = economy_utility::to_cash_units(10000.0);
+        let improvement_cost: f64 =
+            game_state.get_improvement_cost(0, game_state.tile_improvement_level[tile_x][tile_y]);
+        assert!(game_state.improve_tile(0, tile_x, tile_y));
+        assert_eq!(
+            game_state.players_statistics[0].total_spending,
+            improvement_cost
+        );
+
+        let number_of_tiles: usize = game_state.get_number_of_tiles(1);
+        game_state.players_cash[1] = BANKRUPTCY_CASH_LIMIT * 2;
+        game_state.step(&mut ais);
+        assert!(game_state.players_eliminated[1]);
+        assert_eq!(
+            game_state.players_statistics[1].tiles_lost,
+            number_of_tiles as u32
+        );
+        assert_eq!(game_state.players_statistics[0].tiles_captured, 0);
+        assert!(game_state.players_statistics[0].peak_population > 0);
+
+        assert_eq!(game_state.get_score(1), 0.0);
+        assert_eq!(
+            game_state.get_score(0),
+            victory_utility::get_score(
+                game_state.get_total_population(0),
+                game_state.get_cash(0),
+                game_state.get_number_of_tiles(0),
+            )
+        );
+    }
+}
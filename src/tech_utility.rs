@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+pub const BASE_MAX_SETTLEMENT_LEVEL: i32 = 12;
+pub const URBAN_PLANNING_MAX_SETTLEMENT_LEVEL: i32 = 14;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Technology {
+    Masonry,
+    Siegecraft,
+    Commerce,
+    UrbanPlanning,
+}
+
+pub const TECHNOLOGIES: [Technology; 4] = [
+    Technology::Masonry,
+    Technology::Siegecraft,
+    Technology::Commerce,
+    Technology::UrbanPlanning,
+];
+
+impl Technology {
+    pub fn name(&self) -> &str {
+        match self {
+            Technology::Masonry => "Masonry",
+            Technology::Siegecraft => "Siegecraft",
+            Technology::Commerce => "Commerce",
+            Technology::UrbanPlanning => "Urban Planning",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            Technology::Masonry => "improvements -25%",
+            Technology::Siegecraft => "captures -25%",
+            Technology::Commerce => "income +20%",
+            Technology::UrbanPlanning => "conurbations and megalopolises",
+        }
+    }
+
+    pub fn research_cost(&self) -> f64 {
+        match self {
+            Technology::Masonry => 4000.0,
+            Technology::Siegecraft => 6000.0,
+            Technology::Commerce => 8000.0,
+            Technology::UrbanPlanning => 20000.0,
+        }
+    }
+
+    pub fn research_ticks(&self) -> u64 {
+        match self {
+            Technology::Masonry => 1800,
+            Technology::Siegecraft => 2400,
+            Technology::Commerce => 3000,
+            Technology::UrbanPlanning => 5400,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Research {
+    pub researched: Vec<Technology>,
+    pub current: Option<Technology>,
+    pub progress_ticks: u64,
+}
+
+pub fn new_research() -> Research {
+    return Research {
+        researched: Vec::new(),
+        current: None,
+        progress_ticks: 0,
+    };
+}
+
+pub fn has_technology(research: &Research, technology: Technology) -> bool {
+    return research.researched.contains(&technology);
+}
+
+pub fn can_start_research(research: &Research, technology: Technology, cash: f64) -> bool {
+    return research.current.is_none()
+        && !has_technology(research, technology)
+        && cash >= technology.research_cost();
+}
+
+// The caller pays the research cost when this returns true.
+pub fn start_research(research: &mut Research, technology: Technology, cash: f64) -> bool {
+    if !can_start_research(research, technology, cash) {
+        return false;
+    }
+    research.current = Some(technology);
+    research.progress_ticks = 0;
+    return true;
+}
+
+pub fn advance_research(research: &mut Research) -> Option<Technology> {
+    if let Some(technology) = research.current {
+        research.progress_ticks += 1;
+        if research.progress_ticks >= technology.research_ticks() {
+            research.researched.push(technology);
+            research.current = None;
+            research.progress_ticks = 0;
+            return Some(technology);
+        }
+    }
+    return None;
+}
+
+pub fn get_progress(research: &Research) -> f64 {
+    match research.current {
+        Some(technology) => {
+            return research.progress_ticks as f64 / technology.research_ticks() as f64;
+        }
+        None => return 0.0,
+    }
+}
+
+pub fn get_improvement_cost_modifier(research: &Research) -> f64 {
+    if has_technology(research, Technology::Masonry) {
+        return 0.75;
+    } else {
+        return 1.0;
+    }
+}
+
+pub fn get_owner_change_cost_modifier(research: &Research) -> f64 {
+    if has_technology(research, Technology::Siegecraft) {
+        return 0.75;
+    } else {
+        return 1.0;
+    }
+}
+
+pub fn get_income_modifier(research: &Research) -> f64 {
+    if has_technology(research, Technology::Commerce) {
+        return 1.2;
+    } else {
+        return 1.0;
+    }
+}
+
+pub fn get_max_settlement_level(research: &Research) -> i32 {
+    if has_technology(research, Technology::UrbanPlanning) {
+        return URBAN_PLANNING_MAX_SETTLEMENT_LEVEL;
+    } else {
+        return BASE_MAX_SETTLEMENT_LEVEL;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population_utility;
+
+    fn get_research(technologies: &[Technology]) -> Research {
+        let mut research: Research = new_research();
+        research.researched = technologies.to_vec();
+        return research;
+    }
+
+    #[test]
+    fn research_finishes_after_its_ticks() {
+        let mut research: Research = new_research();
+        assert!(!start_research(&mut research, Technology::Masonry, 0.0));
+        assert!(start_research(&mut research, Technology::Masonry, 4000.0));
+        assert!(!can_start_research(
+            &research,
+            Technology::Commerce,
+            100000.0
+        ));
+        for _ in 1..Technology::Masonry.research_ticks() {
+            assert_eq!(advance_research(&mut research), None);
+        }
+        assert_eq!(advance_research(&mut research), Some(Technology::Masonry));
+        assert!(has_technology(&research, Technology::Masonry));
+        assert!(!can_start_research(
+            &research,
+            Technology::Masonry,
+            100000.0
+        ));
+    }
+
+    #[test]
+    fn urban_planning_unlocks_levels_13_and_14() {
+        let get_level = |research: &Research, population: i32| -> i32 {
+            return population_utility::get_level_of_settlement(population)
+                .min(get_max_settlement_level(research));
+        };
+        let research: Research = new_research();
+        assert_eq!(get_level(&research, 20000000), 12);
+        assert_eq!(get_level(&research, 60000000), 12);
+        assert_eq!(get_level(&research, 200000000), 12);
+
+        let research: Research = get_research(&[Technology::UrbanPlanning]);
+        assert_eq!(get_level(&research, 20000000), 12);
+        assert_eq!(get_level(&research, 60000000), 13);
+        assert_eq!(get_level(&research, 200000000), 14);
+    }
+
+    #[test]
+    fn each_technology_changes_only_its_modifier() {
+        let research: Research = new_research();
+        assert_eq!(get_improvement_cost_modifier(&research), 1.0);
+        assert_eq!(get_owner_change_cost_modifier(&research), 1.0);
+        assert_eq!(get_income_modifier(&research), 1.0);
+
+        let research: Research = get_research(&[Technology::Masonry]);
+        assert_eq!(get_improvement_cost_modifier(&research), 0.75);
+        assert_eq!(get_owner_change_cost_modifier(&research), 1.0);
+        assert_eq!(get_income_modifier(&research), 1.0);
+
+        let research: Research = get_research(&[Technology::Siegecraft]);
+        assert_eq!(get_improvement_cost_modifier(&research), 1.0);
+        assert_eq!(get_owner_change_cost_modifier(&research), 0.75);
+        assert_eq!(get_income_modifier(&research), 1.0);
+
+        let research: Research = get_research(&[Technology::Commerce]);
+        assert_eq!(get_improvement_cost_modifier(&research), 1.0);
+        assert_eq!(get_owner_change_cost_modifier(&research), 1.0);
+        assert_eq!(get_income_modifier(&research), 1.2);
+        assert_eq!(
+            get_max_settlement_level(&research),
+            BASE_MAX_SETTLEMENT_LEVEL
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use crate::ai::{self, Ai};
+use crate::game_settings::{self, GameSettings};
+use crate::game_state::GameState;
+use crate::player_command::{self, PlayerCommand};
+
+pub fn get_own_tile(game_state: &GameState, player: usize) -> Option<(usize, usize)> {
+    return (0..game_state.game_area_width)
+        .flat_map(|i| (0..game_state.game_area_height).map(move |j| (i, j)))
+        .find(|&(i, j)| game_state.tile_owned_by[i][j] == player as i32);
+}
+
+// A seeded match on the default map. Every human seat takes a loan and grows its first tile,
+// the other seats are played by the default opponent.
+pub fn play_match(seed: u64, number_of_humans: usize, ticks: u64) -> GameState {
+    let mut game_settings: GameSettings = game_settings::parse_arguments(Vec::new());
+    game_settings.seed = Some(seed);
+    game_settings.number_of_humans = number_of_humans;
+    let mut game_state: GameState = GameState::new(game_settings);
+    let mut ais: Vec<Option<Box<dyn Ai>>> = ai::get_opponent_ais(&game_state).unwrap();
+    while game_state.tick < ticks && !game_state.is_win {
+        for player in 0..number_of_humans {
+            if game_state.tick == 120 {
+                player_command::apply_command(&mut game_state, player, PlayerCommand::TakeLoan);
+            }
+            if game_state.tick == 360 {
+                if let Some((tile_x, tile_y)) = get_own_tile(&game_state, player) {
+                    player_command::apply_command(
+                        &mut game_state,
+                        player,
+                        PlayerCommand::Improve(tile_x, tile_y),
+                    );
+                }
+            }
+        }
+        game_state.step(&mut ais);
+    }
+    return game_state;
+}
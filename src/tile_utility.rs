@@ -1,5 +1,7 @@
 // use std::f32;
 
+pub const NEUTRAL_OWNER: i32 = -1;
+
 /* pub struct TileCoordinates {
     pub x: usize,
     pub y: usize,
@@ -63,7 +65,7 @@ pub fn has_adjacent_friendly_tile(
     enemy_tile_y: i32,
     game_area_width: usize,
     game_area_height: usize,
-    player_id: i32,
+    friendly_players: &Vec<i32>,
 ) -> bool {
     let mut x_start: usize = enemy_tile_x as usize;
     let mut x_end: usize = enemy_tile_x as usize;
@@ -92,7 +94,7 @@ pub fn has_adjacent_friendly_tile(
         for j in y_start..y_end + 1 {
             if i != enemy_tile_x as usize
                 && j != enemy_tile_y as usize
-                && tile_owned_by[i][j] == player_id
+                && friendly_players.contains(&tile_owned_by[i][j])
             {
                 result = true;
             }
@@ -102,19 +104,69 @@ pub fn has_adjacent_friendly_tile(
     return result;
 }
 
-pub fn is_player_wins(
+pub fn get_starting_owner(
+    tile_x: usize,
+    tile_y: usize,
+    game_area_width: usize,
+    game_area_height: usize,
+    number_of_players: usize,
+) -> i32 {
+    let tile_index: usize = tile_y * game_area_width + tile_x;
+    return (tile_index * number_of_players / (game_area_width * game_area_height)) as i32;
+}
+
+pub fn get_number_of_tiles(
     tile_owned_by: &Vec<Vec<i32>>,
     game_area_width: usize,
     game_area_height: usize,
     player: i32,
+) -> usize {
+    let mut number_of_tiles: usize = 0;
+    for i in 0..game_area_width {
+        for j in 0..game_area_height {
+            if tile_owned_by[i][j] == player {
+                number_of_tiles += 1;
+            }
+        }
+    }
+    return number_of_tiles;
+}
+
+pub fn is_team_wins(
+    tile_owned_by: &Vec<Vec<i32>>,
+    game_area_width: usize,
+    game_area_height: usize,
+    team_players: &Vec<i32>,
 ) -> bool {
-    let mut player_wins = true;
+    let mut team_wins = true;
     for i in 0..game_area_width {
         for j in 0..game_area_height {
-            if tile_owned_by[i][j] != player {
-                player_wins = false;
+            if !team_players.contains(&tile_owned_by[i][j]) {
+                team_wins = false;
             }
         }
     }
-    return player_wins;
+    return team_wins;
+}
+
+pub fn get_adjacent_tiles(
+    tile_x: usize,
+    tile_y: usize,
+    game_area_width: usize,
+    game_area_height: usize,
+) -> Vec<(usize, usize)> {
+    let mut adjacent_tiles: Vec<(usize, usize)> = Vec::new();
+    if tile_x > 0 {
+        adjacent_tiles.push((tile_x - 1, tile_y));
+    }
+    if tile_x + 1 < game_area_width {
+        adjacent_tiles.push((tile_x + 1, tile_y));
+    }
+    if tile_y > 0 {
+        adjacent_tiles.push((tile_x, tile_y - 1));
+    }
+    if tile_y + 1 < game_area_height {
+        adjacent_tiles.push((tile_x, tile_y + 1));
+    }
+    return adjacent_tiles;
 }
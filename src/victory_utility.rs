@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+
+pub const POPULATION_TARGET: i32 = 1000000;
+pub const CASH_TARGET: f64 = 100000.0;
+pub const TERRITORY_SHARE: f64 = 0.6;
+pub const TERRITORY_HOLD_TICKS: u64 = 3600;
+pub const KING_OF_THE_HILL_HOLD_TICKS: u64 = 5400;
+pub const TIME_LIMIT_TICKS: u64 = 36000;
+
+pub const SCORE_PER_TILE: f64 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum VictoryCondition {
+    Conquest,
+    Population,
+    Cash,
+    Territory,
+    KingOfTheHill,
+    TimeLimit,
+}
+
+impl VictoryCondition {
+    pub fn name(&self) -> &str {
+        match self {
+            VictoryCondition::Conquest => "Conquest",
+            VictoryCondition::Population => "Population",
+            VictoryCondition::Cash => "Cash",
+            VictoryCondition::Territory => "Territory",
+            VictoryCondition::KingOfTheHill => "King of the Hill",
+            VictoryCondition::TimeLimit => "Time Limit",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            VictoryCondition::Conquest => "own every tile".to_string(),
+            VictoryCondition::Population => {
+                "reach ".to_string() + &POPULATION_TARGET.to_string() + " population"
+            }
+            VictoryCondition::Cash => "save ".to_string() + &CASH_TARGET.to_string() + " cash",
+            VictoryCondition::Territory => {
+                "hold ".to_string()
+                    + &((TERRITORY_SHARE * 100.0) as i32).to_string()
+                    + "% of the map for "
+                    + &(TERRITORY_HOLD_TICKS / 60).to_string()
+                    + "s"
+            }
+            VictoryCondition::KingOfTheHill => {
+                "hold the center tile for ".to_string()
+                    + &(KING_OF_THE_HILL_HOLD_TICKS / 60).to_string()
+                    + "s"
+            }
+            VictoryCondition::TimeLimit => {
+                "best score after ".to_string() + &(TIME_LIMIT_TICKS / 60).to_string() + "s"
+            }
+        }
+    }
+
+    pub fn next(&self) -> VictoryCondition {
+        match self {
+            VictoryCondition::Conquest => VictoryCondition::Population,
+            VictoryCondition::Population => VictoryCondition::Cash,
+            VictoryCondition::Cash => VictoryCondition::Territory,
+            VictoryCondition::Territory => VictoryCondition::KingOfTheHill,
+            VictoryCondition::KingOfTheHill => VictoryCondition::TimeLimit,
+            VictoryCondition::TimeLimit => VictoryCondition::Conquest,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VictoryCondition> {
+        match name.to_lowercase().as_str() {
+            "conquest" => Some(VictoryCondition::Conquest),
+            "population" => Some(VictoryCondition::Population),
+            "cash" => Some(VictoryCondition::Cash),
+            "territory" => Some(VictoryCondition::Territory),
+            "hill" => Some(VictoryCondition::KingOfTheHill),
+            "time" => Some(VictoryCondition::TimeLimit),
+            _ => None,
+        }
+    }
+}
+
+pub fn get_center_tile(game_area_width: usize, game_area_height: usize) -> (usize, usize) {
+    return (game_area_width / 2, game_area_height / 2);
+}
+
+pub fn get_score(total_population: i32, cash: f64, number_of_tiles: usize) -> f64 {
+    return total_population as f64 + cash.max(0.0) + number_of_tiles as f64 * SCORE_PER_TILE;
+}
+
+pub fn update_hold_ticks(hold_ticks: u64, is_holding: bool) -> u64 {
+    if is_holding {
+        return hold_ticks + 1;
+    } else {
+        return 0;
+    }
+}
+
+pub fn get_progress(
+    victory_condition: VictoryCondition,
+    total_population: i32,
+    cash: f64,
+    number_of_tiles: usize,
+    total_number_of_tiles: usize,
+    hold_ticks: u64,
+    tick: u64,
+) -> f64 {
+    let progress: f64 = match victory_condition {
+        VictoryCondition::Conquest => number_of_tiles as f64 / total_number_of_tiles as f64,
+        VictoryCondition::Population => total_population as f64 / POPULATION_TARGET as f64,
+        VictoryCondition::Cash => cash / CASH_TARGET,
+        VictoryCondition::Territory => hold_ticks as f64 / TERRITORY_HOLD_TICKS as f64,
+        VictoryCondition::KingOfTheHill => hold_ticks as f64 / KING_OF_THE_HILL_HOLD_TICKS as f64,
+        VictoryCondition::TimeLimit => tick as f64 / TIME_LIMIT_TICKS as f64,
+    };
+    return progress.clamp(0.0, 1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy_utility;
+    use crate::game_settings;
+    use crate::game_state::GameState;
+    use crate::test_utility;
+    use crate::tile_utility;
+
+    fn get_game_state(victory_condition: VictoryCondition, number_of_players: usize) -> GameState {
+        let arguments: Vec<String> = vec![
+            "bitter-boundaries".to_string(),
+            "--players".to_string(),
+            number_of_players.to_string(),
+        ];
+        let mut game_settings: game_settings::GameSettings =
+            game_settings::parse_arguments(arguments);
+        game_settings.seed = Some(2);
+        game_settings.victory_condition = victory_condition;
+        return GameState::new(game_settings);
+    }
+
+    fn set_all_tiles(game_state: &mut GameState, owner: i32) {
+        for i in 0..game_state.game_area_width {
+            for j in 0..game_state.game_area_height {
+                game_state.tile_owned_by[i][j] = owner;
+            }
+        }
+    }
+
+    #[test]
+    fn population_target_ends_the_match_for_the_team_reaching_it() {
+        let mut game_state: GameState = get_game_state(VictoryCondition::Population, 2);
+        game_state.check_victory();
+        assert!(!game_state.is_win);
+
+        let (tile_x, tile_y) = test_utility::get_own_tile(&game_state, 1).unwrap();
+        game_state.tile_population_number[tile_x][tile_y] = POPULATION_TARGET;
+        assert_eq!(game_state.get_victory_progress(1), 1.0);
+        assert!(game_state.get_victory_progress(0) < 1.0);
+        game_state.check_victory();
+        assert!(game_state.is_win);
+        assert_eq!(game_state.winner_team, 1);
+    }
+
+    #[test]
+    fn cash_target_ends_the_match_for_the_team_reaching_it() {
+        let mut game_state: GameState = get_game_state(VictoryCondition::Cash, 2);
+        game_state.players_cash[0] = economy_utility::to_cash_units(CASH_TARGET) - 1;
+        game_state.check_victory();
+        assert!(!game_state.is_win);
+
+        game_state.players_cash[0] += 1;
+        game_state.check_victory();
+        assert!(game_state.is_win);
+        assert_eq!(game_state.winner_team, 0);
+    }
+
+    #[test]
+    fn hill_hold_resets_when_the_hill_changes_hands() {
+        let mut game_state: GameState = get_game_state(VictoryCondition::KingOfTheHill, 2);
+        let (hill_x, hill_y) =
+            get_center_tile(game_state.game_area_width, game_state.game_area_height);
+        game_state.tile_owned_by[hill_x][hill_y] = 0;
+        for _ in 0..100 {
+            game_state.check_victory();
+        }
+        assert_eq!(game_state.team_hold_ticks, vec![100, 0]);
+
+        game_state.tile_owned_by[hill_x][hill_y] = 1;
+        game_state.check_victory();
+        assert_eq!(game_state.team_hold_ticks, vec![0, 1]);
+
+        for _ in 1..KING_OF_THE_HILL_HOLD_TICKS {
+            assert!(!game_state.is_win);
+            game_state.check_victory();
+        }
+        assert!(game_state.is_win);
+        assert_eq!(game_state.winner_team, 1);
+    }
+
+    #[test]
+    fn territory_hold_resets_when_the_share_drops() {
+        let mut game_state: GameState = get_game_state(VictoryCondition::Territory, 2);
+        set_all_tiles(&mut game_state, 0);
+        for _ in 0..100 {
+            game_state.check_victory();
+        }
+        assert_eq!(game_state.team_hold_ticks[0], 100);
+
+        set_all_tiles(&mut game_state, tile_utility::NEUTRAL_OWNER);
+        game_state.tile_owned_by[0][0] = 0;
+        game_state.check_victory();
+        assert_eq!(game_state.team_hold_ticks[0], 0);
+        assert!(!game_state.is_win);
+
+        set_all_tiles(&mut game_state, 0);
+        for _ in 0..TERRITORY_HOLD_TICKS {
+            game_state.check_victory();
+        }
+        assert!(game_state.is_win);
+        assert_eq!(game_state.winner_team, 0);
+    }
+
+    #[test]
+    fn time_limit_goes_to_the_best_active_team() {
+        let mut game_state: GameState = get_game_state(VictoryCondition::TimeLimit, 3);
+        game_state.players_cash[0] = economy_utility::to_cash_units(1000.0);
+        game_state.players_cash[1] = economy_utility::to_cash_units(50000.0);
+        game_state.players_cash[2] = economy_utility::to_cash_units(900000.0);
+        game_state.players_eliminated[2] = true;
+
+        game_state.tick = TIME_LIMIT_TICKS - 1;
+        game_state.check_victory();
+        assert!(!game_state.is_win);
+
+        game_state.tick = TIME_LIMIT_TICKS;
+        game_state.check_victory();
+        assert!(game_state.is_win);
+        assert_eq!(game_state.winner_team, 1);
+    }
+}